@@ -0,0 +1,247 @@
+//! Typed calls to each of the server's endpoints
+
+use std::fmt::{Debug, Display};
+use std::future::Future;
+use std::pin::Pin;
+
+use areyougoing_shared::{
+    ClearResponsesRequest, ClearResponsesResult, CreatePollResult, HasRespondedResult, Poll,
+    PollQueryResult, PollResponse, PollSubmissionResult, ProgressReportResult, ReactRequest,
+    ReactResult, EDITOR_SECRET_HEADER, PASSWORD_HEADER,
+};
+use futures_lite::future;
+use gloo::{console::__macro::JsValue, net::http::RequestMode};
+use serde::{de::DeserializeOwned, Serialize};
+use url::form_urlencoded::byte_serialize;
+use wasm_bindgen::JsCast;
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{Request, RequestInit, Response};
+
+use crate::misc::{console_log, get_window};
+use crate::SERVER_URL;
+
+#[derive(Debug)]
+pub enum ApiError {
+    /// No response came back, e.g. the server or the network is down
+    Network(String),
+    /// The server responded with an error status and no readable body
+    Status {
+        status: u16,
+        request_id: Option<String>,
+    },
+    /// The body wasn't what the endpoint returns
+    Unreadable { request_id: Option<String> },
+}
+
+impl Display for ApiError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ApiError::Network(e) => write!(f, "no response: {e}"),
+            ApiError::Status { status, request_id } => {
+                write!(f, "status {status} (request id {request_id:?})")
+            }
+            ApiError::Unreadable { request_id } => {
+                write!(f, "unreadable response (request id {request_id:?})")
+            }
+        }
+    }
+}
+
+/// The id the server tagged a request's logs with, to match up a failure with them
+fn request_id(response: &Response) -> Option<String> {
+    response.headers().get("x-request-id").ok().flatten()
+}
+
+/// The header for a poll's view password, if it has one, to send with requests about it
+fn password_header(password: Option<String>) -> Vec<(&'static str, String)> {
+    password
+        .map(|password| (PASSWORD_HEADER, password))
+        .into_iter()
+        .collect()
+}
+
+async fn fetch(
+    method: &str,
+    path: String,
+    body: Option<String>,
+    headers: Vec<(&'static str, String)>,
+) -> Result<Response, ApiError> {
+    let mut opts = RequestInit::new();
+    opts.method(method);
+    opts.mode(RequestMode::Cors);
+    if let Some(body) = &body {
+        opts.body(Some(&JsValue::from_str(body)));
+    }
+    let url = format!("{SERVER_URL}/{path}");
+    let request = Request::new_with_str_and_init(&url, &opts)
+        .map_err(|e| ApiError::Network(format!("{e:?}")))?;
+    if body.is_some() {
+        request
+            .headers()
+            .set("Content-Type", "application/json")
+            .unwrap();
+    }
+    for (name, value) in &headers {
+        request.headers().set(name, value).unwrap();
+    }
+    let window = get_window().ok_or_else(|| ApiError::Network("no window".to_string()))?;
+    Ok(JsFuture::from(window.fetch_with_request(&request))
+        .await
+        .map_err(|e| ApiError::Network(format!("{e:?}")))?
+        .dyn_into()
+        .unwrap())
+}
+
+async fn request<T: DeserializeOwned + Debug>(
+    method: &str,
+    path: String,
+    body: Option<String>,
+    headers: Vec<(&'static str, String)>,
+) -> Result<T, ApiError> {
+    let response = fetch(method, path, body, headers).await?;
+    let request_id = request_id(&response);
+    // Error statuses can still carry one of the endpoint's results
+    let json = match response.json() {
+        Ok(json) => JsFuture::from(json).await.ok(),
+        Err(_) => None,
+    };
+    match json.and_then(|json| serde_wasm_bindgen::from_value(json).ok()) {
+        Some(result) => {
+            console_log!("Received from server: {result:?}");
+            Ok(result)
+        }
+        None if !response.ok() => Err(ApiError::Status {
+            status: response.status(),
+            request_id,
+        }),
+        None => Err(ApiError::Unreadable { request_id }),
+    }
+}
+
+async fn post<SendT: Serialize, ReceiveT: DeserializeOwned + Debug>(
+    path: &str,
+    data: &SendT,
+    headers: Vec<(&'static str, String)>,
+) -> Result<ReceiveT, ApiError> {
+    request(
+        "POST",
+        path.to_string(),
+        Some(serde_json::to_string(data).unwrap()),
+        headers,
+    )
+    .await
+}
+
+pub async fn get_poll(key: u64, password: Option<String>) -> Result<PollQueryResult, ApiError> {
+    request(
+        "GET",
+        format!("?poll_key={key}"),
+        None,
+        password_header(password),
+    )
+    .await
+}
+
+pub async fn create_poll(poll: Poll) -> Result<CreatePollResult, ApiError> {
+    post("new_poll", &poll, Vec::new()).await
+}
+
+pub async fn submit_response(
+    response: PollResponse,
+    password: Option<String>,
+) -> Result<PollSubmissionResult, ApiError> {
+    post("submit", &response, password_header(password)).await
+}
+
+/// Whether `user` on the device with `device_key` has responded, going by the poll's dedup
+pub async fn has_responded(
+    key: u64,
+    user: String,
+    device_key: String,
+    password: Option<String>,
+) -> Result<HasRespondedResult, ApiError> {
+    let user = byte_serialize(user.as_bytes()).collect::<String>();
+    let device_key = byte_serialize(device_key.as_bytes()).collect::<String>();
+    request(
+        "GET",
+        format!("has_responded?poll_key={key}&user={user}&device_key={device_key}"),
+        None,
+        password_header(password),
+    )
+    .await
+}
+
+/// Progress as anyone else sees it, or with `editor_secret`, as the poll's editors do
+pub async fn get_progress(
+    key: u64,
+    password: Option<String>,
+    editor_secret: Option<String>,
+) -> Result<ProgressReportResult, ApiError> {
+    let mut headers = password_header(password);
+    headers.extend(editor_secret.map(|secret| (EDITOR_SECRET_HEADER, secret)));
+    post("progress", &key, headers).await
+}
+
+/// The poll and its responses as a RON file, for editors to keep as a backup
+pub async fn get_archive(key: u64, editor_secret: String) -> Result<String, ApiError> {
+    let response = fetch(
+        "GET",
+        format!("archive?poll_key={key}"),
+        None,
+        vec![(EDITOR_SECRET_HEADER, editor_secret)],
+    )
+    .await?;
+    if !response.ok() {
+        return Err(ApiError::Status {
+            status: response.status(),
+            request_id: request_id(&response),
+        });
+    }
+    let text = match response.text() {
+        Ok(text) => JsFuture::from(text).await.ok(),
+        Err(_) => None,
+    };
+    text.and_then(|text| text.as_string())
+        .ok_or_else(|| ApiError::Unreadable {
+            request_id: request_id(&response),
+        })
+}
+
+pub async fn clear_responses(
+    request: ClearResponsesRequest,
+) -> Result<ClearResponsesResult, ApiError> {
+    post("clear_responses", &request, Vec::new()).await
+}
+
+pub async fn react(
+    request: ReactRequest,
+    password: Option<String>,
+) -> Result<ReactResult, ApiError> {
+    post("react", &request, password_header(password)).await
+}
+
+type BoxedCall<T> = Pin<Box<dyn Future<Output = Result<T, ApiError>>>>;
+
+/// One of the calls above in flight, checked on each frame with `poll`
+pub struct ApiCall<T>(Option<BoxedCall<T>>);
+
+impl<T> ApiCall<T> {
+    pub fn new(call: impl Future<Output = Result<T, ApiError>> + 'static) -> Self {
+        Self(Some(Box::pin(call)))
+    }
+
+    /// The result once it's in. Only returns it once.
+    pub fn poll(&mut self) -> Option<Result<T, ApiError>> {
+        let result = future::block_on(future::poll_once(self.0.as_mut()?))?;
+        self.0 = None;
+        Some(result)
+    }
+}
+
+impl<T> Debug for ApiCall<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ApiCall")
+            .field(&if self.0.is_some() { "pending" } else { "done" })
+            .finish()
+    }
+}
@@ -1,13 +1,17 @@
-use crate::misc::{get_window, listen_in_window, AtomicBoolExt};
+use crate::misc::{get_window, listen_in_window, AtomicBoolExt, UrlExt};
 use crate::new_poll::NewPoll;
-use crate::participation::ParticipationState;
+use crate::participation::{LocalSave, ParticipationState};
 use crate::poll::PollState;
+use crate::results_ui::Palette;
 use crate::retrieve::RetrievingState;
 
+use areyougoing_shared::{link_is_embedded, parse_poll_link, poll_key_from_link, Poll};
 use egui::{panel::TopBottomSide, Align, CentralPanel, Layout, RichText, TopBottomPanel};
-use egui::{vec2, Frame, Stroke, TextStyle, Visuals};
+use egui::{vec2, Align2, Frame, Stroke, TextStyle, Visuals, Window};
+use enum_iterator::all;
 
 use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::sync::atomic::AtomicBool;
 use std::sync::Arc;
 
@@ -20,8 +24,16 @@ pub struct App {
     poll_state: PollState,
     sign_in_data: SignInData,
     top_panel_inner_height: Option<f32>,
+    /// Colors for met and unmet results
+    palette: Palette,
     #[serde(skip)]
     original_url: Option<Url>,
+    /// Set by `?embed=1`, for showing just the poll in an iframe
+    #[serde(skip)]
+    embed: bool,
+    /// Asking before a poll being created is thrown away
+    #[serde(skip)]
+    confirming_clear: bool,
     #[serde(skip)]
     need_reload: Arc<AtomicBool>,
 }
@@ -30,6 +42,27 @@ pub struct App {
 pub struct SignInData {
     pub user_entry: String,
     pub old_names: Vec<String>,
+    /// Identifies this browser to polls that dedup responses by device
+    #[serde(default = "new_device_key")]
+    pub device_key: String,
+    /// Optional address for submission confirmations
+    #[serde(default)]
+    pub email: String,
+    /// Keys of the polls each remembered name has submitted to from this browser
+    #[serde(default)]
+    pub answered_polls: HashMap<String, HashSet<u64>>,
+    /// Secrets for the polls created in this browser, so their results show to whoever
+    /// made them even while hidden from everyone else
+    #[serde(default)]
+    pub editor_secrets: HashMap<u64, String>,
+    #[serde(skip)]
+    pub local_save: LocalSave,
+}
+
+fn new_device_key() -> String {
+    (0..4)
+        .map(|_| format!("{:08x}", (js_sys::Math::random() * u32::MAX as f64) as u32))
+        .collect()
 }
 
 impl Default for App {
@@ -42,9 +75,17 @@ impl Default for App {
                     .iter()
                     .map(|s| s.to_string())
                     .collect::<Vec<_>>(),
+                device_key: new_device_key(),
+                email: "".to_string(),
+                answered_polls: Default::default(),
+                editor_secrets: Default::default(),
+                local_save: Default::default(),
             },
             top_panel_inner_height: None,
+            palette: Default::default(),
             original_url: None,
+            embed: false,
+            confirming_clear: false,
             need_reload: Default::default(),
         }
     }
@@ -77,67 +118,68 @@ impl App {
             });
         }
 
-        let url_key = {
-            let mut url_key = None;
-            let window = web_sys::window().expect("no global `window` exists");
-            let url_string = window.location().href().unwrap();
-            if let Ok(url) = Url::parse(&url_string) {
-                app.original_url = Some(url.clone());
-                for (query_key, query_value) in url.query_pairs() {
-                    if query_key == "poll_key" {
-                        if let Ok(key) = query_value.parse::<u64>() {
-                            url_key = Some(key);
-                        }
-                    }
-                }
-            }
-            url_key
-        };
+        let url = get_window()
+            .and_then(|window| window.location().href().ok())
+            .and_then(|href| Url::parse(&href).ok());
+        let url_key = app.follow_link(url);
 
-        match (&mut app.poll_state, url_key) {
-            (PollState::Found { key, .. }, Some(url_key)) if *key != url_key => {
-                app.poll_state = PollState::Retrieving {
-                    key: url_key,
-                    state: RetrievingState::None,
-                };
-            }
+        match (&app.poll_state, url_key) {
             (PollState::Found { .. }, None) => {
-                app.poll_state = PollState::NewPoll {
-                    state: NewPoll::Creating {
-                        ui_data: Default::default(),
-                        ui_tab: Default::default(),
-                    },
-                    poll: Default::default(),
-                };
+                app.poll_state = new_poll_state();
             }
-            (_, Some(url_key)) => {
+            (poll_state, Some(url_key)) => {
+                // The poll is fetched again in case it changed, but a response that was
+                // being filled in or sent picks up where it was left
+                let resume = match poll_state {
+                    PollState::Found {
+                        key,
+                        poll,
+                        participation_state,
+                        ..
+                    } if *key == url_key => participation_state.resume(poll),
+                    _ => None,
+                };
                 app.poll_state = PollState::Retrieving {
                     key: url_key,
+                    password: None,
+                    resume,
                     state: RetrievingState::None,
                 };
             }
             _ => {}
         }
-        {
-            if let PollState::Found {
-                ref mut participation_state,
-                ..
-            } = app.poll_state
-            {
-                let mut new_participation_state = None;
-                if let ParticipationState::Submitting { ref response, .. } = participation_state {
-                    new_participation_state = Some(ParticipationState::SignedIn {
-                        user: response.user.clone(),
-                        question_responses: response.responses.clone(),
-                    });
-                }
-                if let Some(state) = new_participation_state {
-                    *participation_state = state;
-                }
+
+        app
+    }
+
+    /// Picks up which poll the page's link is for and how to show it, returning the poll's
+    /// key. Outside of a browser there's no link, and nothing is picked up.
+    fn follow_link(&mut self, url: Option<Url>) -> Option<u64> {
+        let mut url = url?;
+        let url_key = parse_poll_link(url.as_str());
+        // Older links put the key in the path, so move it to the query
+        if let Some(key) = poll_key_from_link(url.path(), None) {
+            let in_query = poll_key_from_link("/", url.query()).is_some();
+            url = url.with_path("/");
+            if !in_query {
+                url.query_pairs_mut()
+                    .append_pair("poll_key", &key.to_string());
             }
+            url.replace_in_window();
         }
+        self.embed = link_is_embedded(url.query());
+        self.original_url = Some(url);
+        url_key
+    }
+}
 
-        app
+fn new_poll_state() -> PollState {
+    PollState::NewPoll {
+        state: NewPoll::Creating {
+            ui_data: Default::default(),
+            ui_tab: Default::default(),
+        },
+        poll: Default::default(),
     }
 }
 
@@ -145,11 +187,21 @@ impl eframe::App for App {
     /// Called by the frame work to save state before shutdown.
     fn save(&mut self, storage: &mut dyn eframe::Storage) {
         eframe::set_value(storage, eframe::APP_KEY, self);
+        if self.sign_in_data.local_save == LocalSave::Pending {
+            self.sign_in_data.local_save = LocalSave::Saved;
+        }
+    }
+
+    /// Often enough that answers being filled in are soon kept if the tab is closed
+    fn auto_save_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(5)
     }
 
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
         if self.need_reload.get() {
-            get_window().location().reload().expect("Failed to reload");
+            if let Some(window) = get_window() {
+                window.location().reload().expect("Failed to reload");
+            }
         }
         let mut next_poll_state = None;
 
@@ -160,7 +212,7 @@ impl eframe::App for App {
         if let Some(height) = self.top_panel_inner_height {
             top_panel = top_panel.exact_height(height);
         }
-        top_panel.show(ctx, |ui| {
+        top_panel.show_animated(ctx, !self.embed, |ui| {
             ui.columns(3, |columns| {
                 let response = columns[0].with_layout(Layout::left_to_right(Align::Min), |ui| {
                     let create_poll_text = if let PollState::NewPoll { .. } = &self.poll_state {
@@ -173,60 +225,124 @@ impl eframe::App for App {
                         .on_hover_text(create_poll_text)
                         .clicked()
                     {
-                        next_poll_state = Some(PollState::NewPoll {
-                            state: NewPoll::Creating {
-                                ui_data: Default::default(),
-                                ui_tab: Default::default(),
-                            },
-                            poll: Default::default(),
-                        });
+                        match &self.poll_state {
+                            PollState::NewPoll {
+                                state: NewPoll::Creating { .. },
+                                poll,
+                            } if *poll != Poll::default() => {
+                                self.confirming_clear = true;
+                            }
+                            _ => next_poll_state = Some(new_poll_state()),
+                        }
                     }
                 });
                 self.top_panel_inner_height = Some(response.response.rect.height());
                 if let PollState::Found {
-                    ref mut participation_state,
+                    participation_state: ParticipationState::SignedIn { ref user, .. },
                     ..
                 } = self.poll_state
                 {
-                    if let ParticipationState::SignedIn {
-                        user,
-                        question_responses: _,
-                    } = &participation_state
+                    columns[1].with_layout(
+                        Layout::top_down(Align::Min).with_cross_align(Align::Center),
+                        |ui| {
+                            Frame::none()
+                                .fill(ui.style().visuals.faint_bg_color)
+                                .stroke(Stroke {
+                                    width: 1.2,
+                                    color: ui.style().visuals.window_stroke.color,
+                                })
+                                .rounding(ui.style().text_styles[&TextStyle::Body].size / 2.0)
+                                .inner_margin(vec2(0., 0.0))
+                                .outer_margin(vec2(0.0, 0.0))
+                                .show(ui, |ui| {
+                                    ui.label(RichText::new(format!("😶 {user}")).strong());
+                                });
+                        },
+                    );
+                }
+                columns[2].with_layout(Layout::right_to_left(Align::Min), |ui| {
+                    ui.menu_button("⚙", |ui| {
+                        ui.label("Result colors");
+                        for palette in all::<Palette>() {
+                            ui.radio_value(&mut self.palette, palette, palette.label());
+                        }
+                    })
+                    .response
+                    .on_hover_text("Settings");
+                    if let PollState::Found {
+                        participation_state:
+                            ref mut participation_state @ ParticipationState::SignedIn { .. },
+                        ..
+                    } = self.poll_state
                     {
-                        columns[1].with_layout(
-                            Layout::top_down(Align::Min).with_cross_align(Align::Center),
-                            |ui| {
-                                Frame::none()
-                                    .fill(ui.style().visuals.faint_bg_color)
-                                    .stroke(Stroke {
-                                        width: 1.2,
-                                        color: ui.style().visuals.window_stroke.color,
-                                    })
-                                    .rounding(ui.style().text_styles[&TextStyle::Body].size / 2.0)
-                                    .inner_margin(vec2(0., 0.0))
-                                    .outer_margin(vec2(0.0, 0.0))
-                                    .show(ui, |ui| {
-                                        ui.label(RichText::new(format!("😶 {user}")).strong());
-                                    });
-                            },
-                        );
-                        columns[2].with_layout(Layout::right_to_left(Align::Min), |ui| {
-                            if ui.small_button("⬅").on_hover_text("Sign Out").clicked() {
-                                *participation_state = ParticipationState::SignIn;
-                            }
-                        });
+                        if ui.small_button("⬅").on_hover_text("Sign Out").clicked() {
+                            *participation_state = ParticipationState::SignIn;
+                        }
                     }
-                }
+                });
             });
         });
 
-        CentralPanel::default().show(ctx, |ui| {
+        if self.confirming_clear {
+            Window::new("Clear Poll?")
+                .collapsible(false)
+                .resizable(false)
+                .anchor(Align2::CENTER_CENTER, vec2(0.0, 0.0))
+                .show(ctx, |ui| {
+                    ui.label("The poll you're creating will be lost.");
+                    ui.horizontal(|ui| {
+                        if ui.button("Clear").clicked() {
+                            next_poll_state = Some(new_poll_state());
+                            self.confirming_clear = false;
+                        }
+                        if ui.button("Keep Editing").clicked() {
+                            self.confirming_clear = false;
+                        }
+                    });
+                });
+        }
+
+        let mut central_panel = CentralPanel::default();
+        if self.embed {
+            central_panel =
+                central_panel.frame(Frame::central_panel(&ctx.style()).inner_margin(2.0));
+        }
+        central_panel.show(ctx, |ui| {
             self.poll_state.process(
                 ui,
                 &mut next_poll_state,
                 &self.original_url,
                 &mut self.sign_in_data,
+                self.palette,
             );
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    // These run under node, which has no browser window
+
+    #[wasm_bindgen_test]
+    fn no_link_picks_nothing_up() {
+        let mut app = App::default();
+        assert_eq!(app.follow_link(None), None);
+        assert!(!app.embed);
+        assert!(app.original_url.is_none());
+    }
+
+    #[wasm_bindgen_test]
+    fn older_links_are_followed_without_a_window() {
+        let mut app = App::default();
+        let url = Url::parse("https://example.com/42?embed=1").unwrap();
+        assert_eq!(app.follow_link(Some(url)), Some(42));
+        assert!(app.embed);
+        assert_eq!(
+            app.original_url.map(String::from).as_deref(),
+            Some("https://example.com/?embed=1&poll_key=42")
+        );
+    }
+}
@@ -1,5 +1,6 @@
 #![warn(clippy::all, rust_2018_idioms)]
 
+pub mod api;
 mod app;
 mod time;
 pub use app::App;
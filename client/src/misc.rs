@@ -1,18 +1,23 @@
 use std::fmt::Debug;
-use std::marker::PhantomData;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 
-use egui::{pos2, vec2, Align, Layout, NumExt, Rect, RichText, Sense, Ui, Vec2};
+use areyougoing_shared::{parse_markup, ListEdits};
+use egui::text::LayoutJob;
+use egui::{
+    pos2, vec2, Align, Button, FontId, Id, Layout, NumExt, Rect, RichText, Sense, TextFormat,
+    TextStyle, Ui, Vec2, WidgetInfo, WidgetText, WidgetType,
+};
 use futures_lite::{future, Future};
 use gloo::events::EventListener;
 use gloo::{console::__macro::JsValue, net::http::RequestMode};
-use serde::{Deserialize, Serialize};
 use url::Url;
 use wasm_bindgen::prelude::wasm_bindgen;
 use wasm_bindgen::JsCast;
 use wasm_bindgen_futures::JsFuture;
-use web_sys::{Event, Window};
+use web_sys::{
+    Event, HtmlAnchorElement, Notification, NotificationOptions, NotificationPermission, Window,
+};
 use web_sys::{Request, RequestInit, Response};
 
 #[wasm_bindgen]
@@ -56,13 +61,26 @@ pub trait UrlExt {
         new_link
     }
 
+    /// Does nothing outside of a browser
     fn push_to_window(&self) {
-        web_sys::window()
-            .expect("no global `window` exists")
-            .history()
-            .expect("Failed to access browser history")
-            .push_state_with_url(&JsValue::NULL, "", Some(self.get().as_ref()))
-            .expect("Failed to set URL");
+        let Some(history) = get_window().and_then(|window| window.history().ok()) else {
+            return;
+        };
+        if let Err(e) = history.push_state_with_url(&JsValue::NULL, "", Some(self.get().as_ref())) {
+            console_log!("Failed to set URL: {e:?}");
+        }
+    }
+
+    /// Like `push_to_window`, without adding to the history
+    fn replace_in_window(&self) {
+        let Some(history) = get_window().and_then(|window| window.history().ok()) else {
+            return;
+        };
+        if let Err(e) =
+            history.replace_state_with_url(&JsValue::NULL, "", Some(self.get().as_ref()))
+        {
+            console_log!("Failed to set URL: {e:?}");
+        }
     }
 }
 
@@ -78,16 +96,153 @@ impl UrlExt for Option<Url> {
     }
 }
 
-pub fn get_window() -> Window {
-    web_sys::window().expect("no global `window` exists")
+/// Splits a leading icon off of an option, e.g. "🍕 Pizza" becomes `(Some("🍕"), "Pizza")`.
+/// The full option is still what's shown in results.
+pub fn split_icon(option: &str) -> (Option<&str>, &str) {
+    match option.split_once(' ') {
+        Some((icon, label))
+            if !icon.is_empty() && icon.chars().all(|c| !c.is_ascii() && !c.is_alphanumeric()) =>
+        {
+            (Some(icon), label)
+        }
+        _ => (None, option),
+    }
+}
+
+/// Text with the formatting `parse_markup` picks up, e.g. a poll's announcement
+pub fn show_markup(ui: &mut Ui, text: &str) {
+    for line in parse_markup(text) {
+        ui.horizontal_wrapped(|ui| {
+            ui.spacing_mut().item_spacing.x = 0.0;
+            if line.is_empty() {
+                ui.label("");
+            }
+            for span in line {
+                let mut rich_text = RichText::new(span.text);
+                if span.bold {
+                    rich_text = rich_text.strong();
+                }
+                if span.italic {
+                    rich_text = rich_text.italics();
+                }
+                match span.link {
+                    Some(link) => ui.hyperlink_to(rich_text, link),
+                    None => ui.label(rich_text),
+                };
+            }
+        });
+    }
+}
+
+/// An option's text, with any leading icon shown larger than the rest
+pub fn option_text(ui: &Ui, option: &str) -> WidgetText {
+    if let (Some(icon), label) = split_icon(option) {
+        let font_id = TextStyle::Button.resolve(ui.style());
+        let color = ui.visuals().text_color();
+        let mut job = LayoutJob::default();
+        job.append(
+            icon,
+            0.0,
+            TextFormat::simple(
+                FontId::new(font_id.size * 1.5, font_id.family.clone()),
+                color,
+            ),
+        );
+        job.append(
+            label,
+            font_id.size / 3.0,
+            TextFormat::simple(font_id, color),
+        );
+        job.into()
+    } else {
+        option.into()
+    }
+}
+
+/// A toggleable option button, highlighted when selected. Screen readers announce it by
+/// `label` along with whether it's selected.
+pub fn option_button(
+    ui: &mut Ui,
+    text: impl Into<WidgetText>,
+    label: &str,
+    selected: bool,
+    enabled: bool,
+) -> egui::Response {
+    let mut button = Button::new(text);
+    if selected {
+        button = button.fill(ui.style().visuals.selection.bg_fill);
+    }
+    let response = ui.add_enabled(enabled, button);
+    response.widget_info(|| WidgetInfo::selected(WidgetType::SelectableLabel, selected, label));
+    response
+}
+
+/// The browser window, which doesn't exist outside of a browser, e.g. in tests
+pub fn get_window() -> Option<Window> {
+    web_sys::window()
+}
+
+pub fn notification_permission() -> NotificationPermission {
+    Notification::permission()
+}
+
+/// Whether the user has asked their system to minimize animations
+pub fn prefers_reduced_motion() -> bool {
+    matches!(
+        get_window().map(|window| window.match_media("(prefers-reduced-motion: reduce)")),
+        Some(Ok(Some(query))) if query.matches()
+    )
+}
+
+/// Asks the browser for permission to show notifications, if it hasn't been asked yet
+pub fn request_notification_permission() {
+    if notification_permission() == NotificationPermission::Default {
+        if let Err(e) = Notification::request_permission() {
+            console_log!("Failed to request notification permission: {e:?}");
+        }
+    }
+}
+
+pub fn show_notification(title: &str, body: &str) {
+    if notification_permission() != NotificationPermission::Granted {
+        return;
+    }
+    let mut options = NotificationOptions::new();
+    options.body(body);
+    if let Err(e) = Notification::new_with_options(title, &options) {
+        console_log!("Failed to show notification: {e:?}");
+    }
+}
+
+/// Has the browser save `contents` as a file called `file_name`
+pub fn download_file(file_name: &str, mime_type: &str, contents: &str) {
+    let url = format!(
+        "data:{mime_type};charset=utf-8,{}",
+        js_sys::encode_uri_component(contents)
+    );
+    let anchor = get_window()
+        .and_then(|window| window.document())
+        .and_then(|document| document.create_element("a").ok())
+        .and_then(|element| element.dyn_into::<HtmlAnchorElement>().ok());
+    match anchor {
+        Some(anchor) => {
+            anchor.set_href(&url);
+            anchor.set_download(file_name);
+            anchor.click();
+        }
+        None => {
+            console_log!("Failed to create a download link for {file_name}");
+        }
+    }
 }
 
 pub fn listen_in_window<F>(event_type: &'static str, callback: F)
 where
     F: FnMut(&Event) + 'static,
 {
-    let listener = EventListener::new(&get_window(), event_type, callback);
-    listener.forget();
+    if let Some(window) = get_window() {
+        EventListener::new(&window, event_type, callback).forget();
+    }
 }
 
 pub trait AtomicBoolExt {
@@ -110,84 +265,121 @@ impl AtomicBoolExt for AtomicBool {
     }
 }
 
+use crate::time::Instant;
+
 #[derive(Debug)]
-enum SubmitterState {
+enum TextFetcherState {
     None,
-    Submitting(JsFuture),
-    Converting(JsFuture),
+    Fetching(JsFuture),
+    Reading(JsFuture),
 }
 
+/// Fetches text from anywhere, rather than from our server, for importing data a user
+/// points at
 #[derive(Debug)]
-pub struct Submitter<SendT, ReceiveT> {
-    path: String,
-    data: SendT,
-    state: SubmitterState,
-    receive_t: PhantomData<ReceiveT>,
+pub struct TextFetcher {
+    url: String,
+    content_type: String,
+    state: TextFetcherState,
 }
 
-use crate::time::Instant;
-use crate::SERVER_URL;
-
-impl<SendT: Serialize, ReceiveT: Debug + for<'de> Deserialize<'de>> Submitter<SendT, ReceiveT> {
-    pub fn new(path: &str, data: SendT) -> Self {
+impl TextFetcher {
+    pub fn new(url: &str) -> Self {
         Self {
-            path: path.to_string(),
-            state: SubmitterState::None,
-            data,
-            receive_t: Default::default(),
+            url: url.trim().to_string(),
+            content_type: String::new(),
+            state: TextFetcherState::None,
         }
     }
 
-    pub fn poll(&mut self) -> Option<ReceiveT> {
-        let mut next_state = None;
+    /// The text once it arrives, or why it couldn't be fetched
+    pub fn poll(&mut self) -> Option<Result<String, String>> {
         match &mut self.state {
-            SubmitterState::None => {
+            TextFetcherState::None => {
+                if Url::parse(&self.url).is_err() {
+                    return Some(Err(format!("\"{}\" isn't a valid URL", self.url)));
+                }
                 let mut opts = RequestInit::new();
-                opts.method("POST");
-                opts.body(Some(&JsValue::from(
-                    serde_json::to_string(&self.data).unwrap(),
-                )));
-                // opts.credentials(web_sys::RequestCredentials::Include);
+                opts.method("GET");
                 opts.mode(RequestMode::Cors);
-                let url = format!("{SERVER_URL}/{}", self.path);
-                let request = Request::new_with_str_and_init(&url, &opts).unwrap();
-                request
-                    .headers()
-                    .set("Content-Type", "application/json")
-                    .unwrap();
-                next_state = Some(SubmitterState::Submitting(JsFuture::from(
-                    get_window().fetch_with_request(&request),
-                )));
-            }
-            SubmitterState::Submitting(ref mut future) => {
-                if let Some(result) = future.poll() {
-                    next_state = Some(SubmitterState::None);
-                    if let Ok(response) = result {
-                        assert!(response.is_instance_of::<Response>());
-                        let resp: Response = response.dyn_into().unwrap();
-                        if let Ok(json) = resp.json() {
-                            next_state = Some(SubmitterState::Converting(JsFuture::from(json)));
-                        }
+                match (
+                    Request::new_with_str_and_init(&self.url, &opts),
+                    get_window(),
+                ) {
+                    (Ok(request), Some(window)) => {
+                        self.state = TextFetcherState::Fetching(JsFuture::from(
+                            window.fetch_with_request(&request),
+                        ));
+                    }
+                    (Ok(_), None) => {
+                        return Some(Err(format!("Couldn't request {}", self.url)));
+                    }
+                    (Err(e), _) => {
+                        console_log!("Failed to build a request for {}: {e:?}", self.url);
+                        return Some(Err(format!("Couldn't request {}", self.url)));
                     }
                 }
             }
-            SubmitterState::Converting(ref mut future) => {
-                if let Some(result) = future.poll() {
-                    next_state = Some(SubmitterState::None);
-                    if let Ok(json) = result {
-                        if let Ok(submission_result) = serde_wasm_bindgen::from_value(json) {
-                            console_log!("Received from server: {submission_result:?}");
-                            return Some(submission_result);
-                        }
+            TextFetcherState::Fetching(future) => {
+                let result = future.poll()?;
+                // Browsers don't say when a response was blocked by CORS, it just looks
+                // like the fetch failed
+                let response: Response = match result {
+                    Ok(response) => response.dyn_into().unwrap(),
+                    Err(e) => {
+                        console_log!("Fetching {} failed: {e:?}", self.url);
+                        return Some(Err(format!(
+                            "Couldn't fetch {}. If it opens in a new tab, the site likely \
+                            doesn't allow other sites to read it (CORS).",
+                            self.url
+                        )));
+                    }
+                };
+                if !response.ok() {
+                    return Some(Err(format!(
+                        "{} responded with status {}",
+                        self.url,
+                        response.status()
+                    )));
+                }
+                let content_type = response
+                    .headers()
+                    .get("content-type")
+                    .ok()
+                    .flatten()
+                    .unwrap_or_default();
+                if !(content_type.is_empty()
+                    || content_type.starts_with("text/")
+                    || content_type.contains("json")
+                    || content_type.contains("csv"))
+                {
+                    return Some(Err(format!("{} isn't text, it's {content_type}", self.url)));
+                }
+                self.content_type = content_type;
+                match response.text() {
+                    Ok(text) => self.state = TextFetcherState::Reading(JsFuture::from(text)),
+                    Err(e) => {
+                        console_log!("Reading {} failed: {e:?}", self.url);
+                        return Some(Err(format!("Couldn't read {}", self.url)));
                     }
                 }
             }
-        }
-        if let Some(next_state) = next_state {
-            self.state = next_state;
+            TextFetcherState::Reading(future) => {
+                let result = future.poll()?;
+                return Some(match result.ok().and_then(|text| text.as_string()) {
+                    Some(text) => Ok(text),
+                    None => Err(format!("Couldn't read {}", self.url)),
+                });
+            }
         }
         None
     }
+
+    /// Whether the text is a CSV, going by its content type or else its file extension
+    pub fn is_csv(&self) -> bool {
+        self.content_type.contains("csv")
+            || Url::parse(&self.url).is_ok_and(|url| url.path().ends_with(".csv"))
+    }
 }
 
 pub trait UiExt {
@@ -299,6 +491,9 @@ pub struct OrederableListInner {
     pub current_index: usize,
     min_items: usize,
     new_index: Option<usize>,
+    /// Where an item was actually inserted, which `new_index` doesn't say if the factory
+    /// gave nothing
+    inserted_index: Option<usize>,
     delete_index: Option<usize>,
     swap_indices: Option<(usize, usize)>,
     item_description: String,
@@ -442,6 +637,7 @@ impl<'a, T> OrderableList<'a, T> {
         }
         if let Some(index) = self.inner.delete_index {
             self.items.remove(index);
+            mark_list_changed(ui);
             ui.ctx().request_repaint_after(Duration::from_millis(100));
         }
         if self.items.len() < self.inner.min_items {
@@ -450,13 +646,44 @@ impl<'a, T> OrderableList<'a, T> {
         if let Some(index) = self.inner.new_index {
             if let Some(new_item) = (self.item_factory)() {
                 self.items.insert(index, new_item);
+                self.inner.inserted_index = Some(index);
+                mark_list_changed(ui);
                 ui.ctx().request_repaint_after(Duration::from_millis(100));
             }
         }
         if let Some((a, b)) = self.inner.swap_indices {
             self.items.swap(a, b);
+            mark_list_changed(ui);
             ui.ctx().request_repaint_after(Duration::from_millis(100));
         }
         self.inner.delete_index
     }
+
+    /// Where the item at `old_index` before the last `show` is now, or `None` if it was
+    /// deleted. For keeping indices into the list pointed at the same items.
+    pub fn new_position(&self, old_index: usize) -> Option<usize> {
+        // In the order `show` applies them
+        ListEdits {
+            deleted: self.inner.delete_index,
+            inserted: self.inner.inserted_index,
+            swapped: self.inner.swap_indices,
+        }
+        .new_position(old_index)
+    }
+}
+
+fn list_changed_id() -> Id {
+    Id::new("orderable_list_changed")
+}
+
+fn mark_list_changed(ui: &Ui) {
+    ui.data().insert_temp(list_changed_id(), true);
+}
+
+/// Whether any `OrderableList` added, removed or moved an item since this was last called
+pub fn take_list_changes(ui: &Ui) -> bool {
+    let mut data = ui.data();
+    let changed = data.get_temp(list_changed_id()).unwrap_or(false);
+    data.remove::<bool>(list_changed_id());
+    changed
 }
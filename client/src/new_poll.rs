@@ -1,15 +1,32 @@
-use crate::misc::{OrderableList, Submitter, UiExt};
+use crate::{
+    api::{self, ApiCall},
+    misc::{
+        console_log, download_file, show_markup, split_icon, take_list_changes, OrderableList,
+        TextFetcher, UiExt,
+    },
+    participation,
+    results_ui::{Palette, ResultsUi},
+};
 use areyougoing_shared::{
-    Choice, CreatePollResult, Form, Metric, MetricTracker, Poll, PollResult, Requirement,
+    duplicate_options, parse_option_list, remap_metric_indices, Choice, ClearResponsesRequest,
+    ClearResponsesResult, Constraint, CreatePollResult, DedupStrategy, Form, FormResponse, Metric,
+    MetricTracker, Poll, PollResult, PollTheme, Question, Requirement, ResponderField,
+    TiebreakRule,
 };
+use chrono::{DateTime, Local, TimeZone, Timelike, Utc};
 use derivative::Derivative;
 use egui::{
-    pos2, Align, Button, ComboBox, FontId, Layout, Pos2, Rect, RichText, ScrollArea, TextEdit, Ui,
-    Vec2,
+    pos2, Align, Button, ComboBox, DragValue, FontId, Frame, Key, Layout, Modifiers, Pos2, Rect,
+    RichText, ScrollArea, TextEdit, TextStyle, Ui, Vec2,
 };
+use egui_extras::DatePickerButton;
 use enum_iterator::{all, Sequence};
 use serde::{Deserialize, Serialize};
-use std::{mem::discriminant, time::Duration};
+use std::{
+    collections::HashMap,
+    mem::{self, discriminant},
+    time::Duration,
+};
 use strum::IntoEnumIterator;
 use url::Url;
 
@@ -18,28 +35,90 @@ use url::Url;
 #[derive(Deserialize, Serialize, Debug)]
 pub enum NewPoll {
     Creating {
-        ui_data: CreatingUiData,
+        ui_data: Box<CreatingUiData>,
         ui_tab: UiTab,
     },
     Submitting {
         poll: Poll,
         #[serde(skip)]
         #[derivative(PartialEq = "ignore")]
-        state: Option<Submitter<Poll, CreatePollResult>>,
+        state: Option<ApiCall<CreatePollResult>>,
     },
     Submitted {
         key: u64,
+        editor_secret: String,
         copied: bool,
+        #[serde(skip)]
+        #[derivative(PartialEq = "ignore")]
+        clear_responses: ClearResponses,
+        #[serde(skip)]
+        #[derivative(PartialEq = "ignore")]
+        backup: Option<ApiCall<String>>,
     },
 }
 
-#[derive(Deserialize, Serialize, Debug, Default, PartialEq)]
+/// Clearing every response to a submitted poll, which has to be confirmed first
+#[derive(Debug, Default)]
+pub enum ClearResponses {
+    #[default]
+    Idle,
+    Confirming,
+    Clearing(ApiCall<ClearResponsesResult>),
+    Done(ClearResponsesResult),
+}
+
+#[derive(Derivative)]
+#[derivative(PartialEq)]
+#[derive(Deserialize, Serialize, Debug, Default)]
 pub struct CreatingUiData {
     fields_rect: Option<Rect>,
     question_group_rect: Option<Rect>,
     available_rect: Option<Rect>,
     group_border_thickness: Option<f32>,
     tabs_rect: Option<Rect>,
+    simulated_responders: u32,
+    /// Results for made-up responses, to preview the poll without publishing it
+    #[serde(skip)]
+    simulation: Option<ResultsUi>,
+    /// Options being fetched for the question at the index
+    #[serde(skip)]
+    #[derivative(PartialEq = "ignore")]
+    option_import: Option<(usize, TextFetcher)>,
+    /// Answers to the draft while it's shown as responders will see it
+    #[serde(skip)]
+    preview: Option<Vec<FormResponse>>,
+    /// The poll from before each item was added, removed or moved, latest last
+    #[serde(skip)]
+    #[derivative(PartialEq = "ignore")]
+    undo: Vec<Poll>,
+    #[serde(skip)]
+    #[derivative(PartialEq = "ignore")]
+    redo: Vec<Poll>,
+}
+
+/// How many edits can be undone
+const UNDO_DEPTH: usize = 50;
+
+impl CreatingUiData {
+    fn push_undo(&mut self, poll: Poll) {
+        self.undo.push(poll);
+        if self.undo.len() > UNDO_DEPTH {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+
+    fn undo(&mut self, poll: &mut Poll) {
+        if let Some(previous) = self.undo.pop() {
+            self.redo.push(mem::replace(poll, previous));
+        }
+    }
+
+    fn redo(&mut self, poll: &mut Poll) {
+        if let Some(next) = self.redo.pop() {
+            self.undo.push(mem::replace(poll, next));
+        }
+    }
 }
 
 #[derive(Deserialize, Serialize, Debug, PartialEq, Sequence)]
@@ -56,7 +135,14 @@ impl Default for UiTab {
 }
 
 impl NewPoll {
-    pub fn process(&mut self, ui: &mut Ui, poll: &mut Poll, original_url: &Option<Url>) {
+    pub fn process(
+        &mut self,
+        ui: &mut Ui,
+        poll: &mut Poll,
+        original_url: &Option<Url>,
+        palette: Palette,
+        editor_secrets: &mut HashMap<u64, String>,
+    ) {
         let mut next_new_poll_state = None;
         match self {
             NewPoll::Creating {
@@ -66,13 +152,27 @@ impl NewPoll {
                 if let Some(rect) = ui_data.available_rect {
                     if rect != ui.available_rect_before_wrap() {
                         // Somehow the size of the window has changed, so reset/recalculate everything
-                        *ui_data = Default::default();
+                        **ui_data = CreatingUiData {
+                            undo: mem::take(&mut ui_data.undo),
+                            redo: mem::take(&mut ui_data.redo),
+                            ..Default::default()
+                        };
                     }
                 }
                 ui_data.available_rect = Some(ui.available_rect_before_wrap());
 
                 ui.heading("Create a new poll!");
 
+                // Text fields have their own undo
+                if ui.memory().focus().is_none() {
+                    let mut input = ui.ctx().input_mut();
+                    if input.consume_key(Modifiers::COMMAND, Key::Z) {
+                        ui_data.undo(poll);
+                    } else if input.consume_key(Modifiers::COMMAND, Key::Y) {
+                        ui_data.redo(poll);
+                    }
+                }
+
                 let tabs_rect = if let Some(rect) = ui_data.tabs_rect {
                     let left_margin = (ui.available_width() - rect.width()).max(0.) / 2.0;
                     Rect {
@@ -100,9 +200,42 @@ impl NewPoll {
                                     }
                                     if ui.add(button).clicked() {
                                         *ui_tab = tab;
+                                        ui_data.preview = None;
                                     }
                                 });
                             }
+                            let previewing = ui_data.preview.is_some();
+                            let mut button = Button::new(
+                                RichText::new("Preview").font(FontId::proportional(17.)),
+                            );
+                            if previewing {
+                                button = button.fill(ui.style().visuals.selection.bg_fill);
+                            }
+                            if ui
+                                .add(button)
+                                .on_hover_text("See the poll as responders will")
+                                .clicked()
+                            {
+                                ui_data.preview = if previewing {
+                                    None
+                                } else {
+                                    Some(poll.init_responses())
+                                };
+                            }
+                            if ui
+                                .add_enabled(!ui_data.undo.is_empty(), Button::new("Undo"))
+                                .on_hover_text("Ctrl+Z")
+                                .clicked()
+                            {
+                                ui_data.undo(poll);
+                            }
+                            if ui
+                                .add_enabled(!ui_data.redo.is_empty(), Button::new("Redo"))
+                                .on_hover_text("Ctrl+Y")
+                                .clicked()
+                            {
+                                ui_data.redo(poll);
+                            }
                         },
                     );
                     ui_data.tabs_rect =
@@ -111,50 +244,88 @@ impl NewPoll {
 
                 ui.separator();
 
-                ScrollArea::vertical()
-                    .id_source("create_poll_scroll")
-                    .show(ui, |ui| {
-                        match ui_tab {
-                            UiTab::Questions => {
-                                Self::show_main_form(ui, poll, ui_data);
-                            }
-                            UiTab::Metrics => {
-                                Self::show_metrics_form(ui, poll, ui_data);
+                if let Some(responses) = &mut ui_data.preview {
+                    ui.weak("Nothing is submitted from this preview");
+                    participation::show_questions(ui, poll, responses, &[], 0, |ui, _| {
+                        ui.add_enabled(false, Button::new("SUBMIT"));
+                    });
+                } else {
+                    let before = poll.clone();
+                    ScrollArea::vertical()
+                        .id_source("create_poll_scroll")
+                        .show(ui, |ui| {
+                            match ui_tab {
+                                UiTab::Questions => {
+                                    Self::show_main_form(ui, poll, ui_data);
+                                }
+                                UiTab::Metrics => {
+                                    Self::show_metrics_form(ui, poll, ui_data);
+                                }
+                                UiTab::Results => {
+                                    Self::show_results_form(ui, poll, ui_data, palette);
+                                }
                             }
-                            UiTab::Results => {
-                                Self::show_results_form(ui, poll, ui_data);
+                            ui.separator();
+                            // The server turns away anything that doesn't validate
+                            if ui
+                                .add_enabled(poll.validate().is_ok(), Button::new("SUBMIT"))
+                                .clicked()
+                            {
+                                next_new_poll_state = Some(NewPoll::Submitting {
+                                    poll: poll.clone(),
+                                    state: None,
+                                });
                             }
-                        }
-                        ui.separator();
-                        if ui.button("SUBMIT").clicked() {
-                            next_new_poll_state = Some(NewPoll::Submitting {
-                                poll: poll.clone(),
-                                state: None,
-                            });
-                        }
-                    });
+                        });
+                    if take_list_changes(ui) {
+                        ui_data.push_undo(before);
+                    }
+                }
                 ui.ctx().request_repaint_after(Duration::from_millis(300));
             }
             NewPoll::Submitting {
                 poll,
                 ref mut state,
             } => {
-                if let Some(submitter) = state {
-                    if let Some(response) = submitter.poll() {
-                        match response {
-                            CreatePollResult::Success { key } => {
-                                next_new_poll_state =
-                                    Some(NewPoll::Submitted { key, copied: false });
-                            }
-                            CreatePollResult::Error => {}
+                ui.label("Your poll is being submitted...");
+                if ui.button("Cancel").clicked() {
+                    // Dropping the submitter drops its in-flight request future
+                    next_new_poll_state = Some(NewPoll::Creating {
+                        ui_data: Default::default(),
+                        ui_tab: Default::default(),
+                    });
+                } else if let Some(submitter) = state {
+                    match submitter.poll() {
+                        Some(Ok(CreatePollResult::Success { key, editor_secret })) => {
+                            editor_secrets.insert(key, editor_secret.clone());
+                            next_new_poll_state = Some(NewPoll::Submitted {
+                                key,
+                                editor_secret,
+                                copied: false,
+                                clear_responses: Default::default(),
+                                backup: None,
+                            });
+                        }
+                        // Sent again on the next frame
+                        Some(Ok(CreatePollResult::Error)) => *state = None,
+                        Some(Err(e)) => {
+                            console_log!("Creating a poll failed: {e}");
+                            *state = None;
                         }
+                        None => {}
                     }
                 } else {
-                    *state = Some(Submitter::new("new_poll", poll.clone()));
+                    *state = Some(ApiCall::new(api::create_poll(poll.clone())));
                 }
                 ui.ctx().request_repaint_after(Duration::from_millis(100));
             }
-            NewPoll::Submitted { key, .. } => {
+            NewPoll::Submitted {
+                key,
+                editor_secret,
+                clear_responses,
+                backup,
+                ..
+            } => {
                 ui.label("Your new poll has been created at:");
                 let mut link = original_url.as_ref().unwrap().clone();
 
@@ -162,6 +333,81 @@ impl NewPoll {
                 link.set_query(Some(&format!("poll_key={key}")));
                 let link = format!("{link}");
                 ui.hyperlink(&link);
+                ui.label("Keep this editor secret to make changes later:");
+                ui.code(editor_secret.as_str());
+                // Fetched rather than linked to, so the secret goes in a header, not the URL
+                match backup {
+                    None => {
+                        if ui.button("Download backup").clicked() {
+                            *backup =
+                                Some(ApiCall::new(api::get_archive(*key, editor_secret.clone())));
+                        }
+                    }
+                    Some(call) => {
+                        ui.spinner();
+                        match call.poll() {
+                            Some(Ok(archive)) => {
+                                download_file(&format!("poll_{key}.ron"), "text/plain", &archive);
+                                *backup = None;
+                            }
+                            Some(Err(e)) => {
+                                console_log!("Downloading a backup of poll {key} failed: {e}");
+                                *backup = None;
+                            }
+                            None => {}
+                        }
+                        ui.ctx().request_repaint_after(Duration::from_millis(100));
+                    }
+                }
+                match clear_responses {
+                    ClearResponses::Idle => {
+                        if ui
+                            .button("Clear all responses")
+                            .on_hover_text("Start over with the same poll and link")
+                            .clicked()
+                        {
+                            *clear_responses = ClearResponses::Confirming;
+                        }
+                    }
+                    ClearResponses::Confirming => {
+                        ui.label("Delete every response? This can't be undone.");
+                        ui.horizontal(|ui| {
+                            if ui.button("Clear").clicked() {
+                                *clear_responses = ClearResponses::Clearing(ApiCall::new(
+                                    api::clear_responses(ClearResponsesRequest {
+                                        poll_key: *key,
+                                        editor_secret: editor_secret.clone(),
+                                    }),
+                                ));
+                            }
+                            if ui.button("Cancel").clicked() {
+                                *clear_responses = ClearResponses::Idle;
+                            }
+                        });
+                    }
+                    ClearResponses::Clearing(submitter) => {
+                        ui.spinner();
+                        match submitter.poll() {
+                            Some(Ok(result)) => *clear_responses = ClearResponses::Done(result),
+                            Some(Err(e)) => {
+                                console_log!("Clearing responses to poll {key} failed: {e}");
+                                *clear_responses =
+                                    ClearResponses::Done(ClearResponsesResult::Error);
+                            }
+                            None => {}
+                        }
+                        ui.ctx().request_repaint_after(Duration::from_millis(100));
+                    }
+                    ClearResponses::Done(result) => {
+                        ui.label(match result {
+                            ClearResponsesResult::Success => "All responses cleared",
+                            ClearResponsesResult::Unauthorized => {
+                                "This editor secret can't clear responses"
+                            }
+                            ClearResponsesResult::Error => "Failed to clear responses",
+                        });
+                    }
+                }
 
                 // Need to enable that one feature for clipboard access I think???
                 // but its conflicting with the per crate compile targets I think
@@ -186,8 +432,182 @@ impl NewPoll {
                 .hint_text("Description (Optional)")
                 .desired_rows(1),
         );
+        let mut announcement = poll.announcement.take().unwrap_or_default();
+        ui.add(
+            TextEdit::multiline(&mut announcement)
+                .hint_text("Announcement shown with the results (Optional)")
+                .desired_rows(1),
+        )
+        .on_hover_text("**bold**, *italics* and [links](https://example.com) are formatted");
+        if !announcement.trim().is_empty() {
+            Frame::group(ui.style()).show(ui, |ui| show_markup(ui, &announcement));
+        }
+        poll.announcement = (!announcement.is_empty()).then_some(announcement);
+        let mut themed = poll.theme.is_some();
+        ui.checkbox(&mut themed, "Custom theme");
+        if themed != poll.theme.is_some() {
+            poll.theme = themed.then(PollTheme::default);
+        }
+        if let Some(theme) = &mut poll.theme {
+            ui.horizontal(|ui| {
+                ui.color_edit_button_srgb(&mut theme.accent_color);
+                ui.add(
+                    TextEdit::singleline(&mut theme.header_emoji)
+                        .hint_text("Header Emoji")
+                        .desired_width(ui.standard_width() / 2.0),
+                );
+            });
+        }
 
+        let mut has_quorum = poll.quorum.is_some();
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut has_quorum, "Require a quorum");
+            if has_quorum != poll.quorum.is_some() {
+                poll.quorum = has_quorum.then_some(1);
+            }
+            if let Some(quorum) = &mut poll.quorum {
+                ui.add(
+                    DragValue::new(quorum)
+                        .clamp_range(1..=u64::MAX)
+                        .suffix(" responses"),
+                );
+            }
+        });
+        let mut has_max_responses = poll.max_responses.is_some();
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut has_max_responses, "Limit the number of responses");
+            if has_max_responses != poll.max_responses.is_some() {
+                poll.max_responses = has_max_responses.then_some(10);
+            }
+            if let Some(max_responses) = &mut poll.max_responses {
+                ui.add(
+                    DragValue::new(max_responses)
+                        .clamp_range(1..=u64::MAX)
+                        .suffix(" spots"),
+                );
+            }
+        });
+        ui.checkbox(
+            &mut poll.reveal_when_closed,
+            "Hide results until the poll closes",
+        );
+        ui.checkbox(
+            &mut poll.randomize_option_order,
+            "Shuffle option order for each responder",
+        )
+        .on_hover_text("So options don't get picked just for being listed first");
+        let mut has_threshold = poll.min_results_threshold.is_some();
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut has_threshold, "Hide results until")
+                .on_hover_text("So a few early answers can't be traced back to who gave them");
+            if has_threshold != poll.min_results_threshold.is_some() {
+                poll.min_results_threshold = has_threshold.then_some(5);
+            }
+            if let Some(threshold) = &mut poll.min_results_threshold {
+                ui.add(
+                    DragValue::new(threshold)
+                        .clamp_range(1..=u64::MAX)
+                        .suffix(" responses"),
+                );
+            }
+        });
+        let mut has_password = poll.view_password.is_some();
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut has_password, "Require a password to view")
+                .on_hover_text("Only people you share the password with can open the poll");
+            if has_password != poll.view_password.is_some() {
+                poll.view_password = has_password.then(String::new);
+            }
+            if let Some(password) = &mut poll.view_password {
+                ui.add(
+                    TextEdit::singleline(password)
+                        .password(true)
+                        .hint_text("Password"),
+                );
+            }
+        });
+        show_deadline_form(
+            ui,
+            "Close poll at",
+            "No responses are accepted after this",
+            &mut poll.expiration,
+        );
+        show_deadline_form(
+            ui,
+            "Lock edits at",
+            "New people can still respond after this, \
+                 but earlier responses can't be changed",
+            &mut poll.edit_deadline,
+        );
+        ui.horizontal(|ui| {
+            ui.label("Identify responses").on_hover_text(
+                "By Name: signing in with the same name edits that response. \
+                     Easy, but anyone can overwrite anyone.\n\
+                     By Device: one response per browser. Names can repeat, \
+                     but responses can't be edited from another device.\n\
+                     Anonymous: every submission counts as a new response \
+                     and can't be edited afterwards.",
+            );
+            ComboBox::from_id_source("dedup_strategy")
+                .selected_text(poll.dedup.to_string())
+                .show_ui(ui, |ui| {
+                    for strategy in DedupStrategy::iter() {
+                        ui.selectable_value(&mut poll.dedup, strategy, strategy.to_string());
+                    }
+                });
+        });
+        // Names are only kept when they identify responses
+        if poll.dedup != DedupStrategy::ByName {
+            poll.show_responder_names = None;
+            poll.show_first_responders = false;
+        }
+        ui.add_enabled_ui(poll.dedup == DedupStrategy::ByName, |ui| {
+            ui.horizontal(|ui| {
+                let mut show_names = poll.show_responder_names.is_some();
+                ui.checkbox(&mut show_names, "Show who gave each answer to")
+                    .on_disabled_hover_text("Only when responses are identified by name");
+                if show_names != poll.show_responder_names.is_some() {
+                    poll.show_responder_names = show_names.then_some(0);
+                }
+                if let Some(index) = &mut poll.show_responder_names {
+                    if !poll.questions.is_empty() {
+                        *index = (*index).min(poll.questions.len() - 1);
+                        ComboBox::from_id_source("responder_names_question").show_index(
+                            ui,
+                            index,
+                            poll.questions.len(),
+                            |i| format!("{i}: {}", limit(&poll.questions[i].prompt)),
+                        );
+                    }
+                }
+            });
+            ui.checkbox(&mut poll.show_first_responders, "Show who responded first")
+                .on_disabled_hover_text("Only when responses are identified by name");
+        });
+        ui.horizontal_wrapped(|ui| {
+            ui.label("One response per");
+            for field in ResponderField::iter() {
+                let constraint = Constraint::UniqueBy(field);
+                let position = poll.constraints.iter().position(|c| *c == constraint);
+                let mut unique = position.is_some();
+                if ui.checkbox(&mut unique, field.to_string()).changed() {
+                    match position {
+                        Some(position) => {
+                            poll.constraints.remove(position);
+                        }
+                        None => poll.constraints.push(constraint),
+                    }
+                }
+            }
+        });
+
+        let earlier_questions = poll
+            .questions
+            .iter()
+            .map(|question| (question.prompt.clone(), question.form.choices()))
+            .collect::<Vec<_>>();
         let mut reset_index = None;
+        let shuffled = poll.randomize_option_order;
         let deleted_index = OrderableList::new(&mut poll.questions, "Question")
             .min_items(1)
             .add_button_is_at_bottom()
@@ -240,6 +660,7 @@ impl NewPoll {
                                         if !selected && response.clicked() {
                                             reset_index = Some(list_state.current_index);
                                             question.form = variant;
+                                            question.pinned_options.clear();
                                             ui.close_menu();
                                         }
                                     }
@@ -247,39 +668,166 @@ impl NewPoll {
                             });
                         });
                     }
-                    let response = ui.add(
-                        TextEdit::multiline(&mut question.prompt)
-                            .desired_rows(1)
-                            .hint_text("Prompt"),
-                    );
+                    // Long prompts scroll instead of pushing the question's controls off screen
+                    let response = ScrollArea::vertical()
+                        .id_source(("prompt", list_state.current_index))
+                        .max_height(ui.text_style_height(&TextStyle::Body) * MAX_PROMPT_ROWS)
+                        .show(ui, |ui| {
+                            ui.add(
+                                TextEdit::multiline(&mut question.prompt)
+                                    .desired_rows(1)
+                                    .hint_text("Prompt"),
+                            )
+                        })
+                        .inner;
                     ui_data.fields_rect = Some(response.rect);
 
-                    match &mut question.form {
-                        Form::OneOrNone { ref mut options }
-                        | Form::One { ref mut options }
-                        | Form::Multiple { ref mut options } => {
-                            ui.separator();
-                            OrderableList::new(options, "Option").min_items(1).show(
-                                ui,
-                                |list_state, ui, option| {
-                                    ui.allocate_ui(ui_data.fields_rect.unwrap().size(), |ui| {
-                                        ui.with_layout(
-                                            Layout::right_to_left(Align::Center),
-                                            |ui| {
-                                                list_state.show_controls(ui);
-                                                ui.add(TextEdit::singleline(option).hint_text(
-                                                    format!(
-                                                        "Option {}",
-                                                        list_state.current_index + 1
-                                                    ),
-                                                ));
-                                            },
-                                        );
+                    let option_lists = match &mut question.form {
+                        Form::OneOrNone {
+                            options,
+                            capacities,
+                        }
+                        | Form::One {
+                            options,
+                            capacities,
+                            ..
+                        }
+                        | Form::Multiple {
+                            options,
+                            capacities,
+                        } => Some((options, Some(capacities))),
+                        Form::RankTopN { options, .. } => Some((options, None)),
+                        Form::YesNoNone | Form::YesNo | Form::Number => None,
+                    };
+                    if let Some((options, capacities)) = option_lists {
+                        ui.separator();
+                        // Capacities and pins ride along with their options so reordering
+                        // and deleting keeps them matched up
+                        let limitable = capacities.is_some();
+                        let duplicates = duplicate_options(options);
+                        let pinned_options = &mut question.pinned_options;
+                        let mut rows = options
+                            .drain(..)
+                            .enumerate()
+                            .map(|(i, option)| {
+                                let capacity = capacities
+                                    .as_ref()
+                                    .and_then(|c| c.get(i).copied().flatten());
+                                (option, capacity, pinned_options.contains(&i))
+                            })
+                            .collect::<Vec<_>>();
+                        OrderableList::new(&mut rows, "Option").min_items(1).show(
+                            ui,
+                            |list_state, ui, (option, capacity, pinned)| {
+                                let row_size = Vec2::new(ui_data.fields_rect.unwrap().width(), 0.);
+                                ui.allocate_ui(row_size, |ui| {
+                                    ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                                        list_state.show_controls(ui);
+                                        if shuffled {
+                                            ui.toggle_value(pinned, "📌")
+                                                .on_hover_text("Keep this option in place when shuffling");
+                                        }
+                                        if limitable {
+                                            if let Some(seats) = capacity {
+                                                ui.add(
+                                                    DragValue::new(seats)
+                                                        .clamp_range(1..=999)
+                                                        .prefix("max "),
+                                                );
+                                            }
+                                            let mut limited = capacity.is_some();
+                                            if ui
+                                                .toggle_value(&mut limited, "🔒")
+                                                .on_hover_text("Limit how many can pick this")
+                                                .changed()
+                                            {
+                                                *capacity = limited.then_some(1);
+                                            }
+                                        }
+                                        let (icon, label) = split_icon(option);
+                                        let mut picked_icon = None;
+                                        ui.menu_button(icon.unwrap_or("☺"), |ui| {
+                                            ui.horizontal_wrapped(|ui| {
+                                                for &icon in OPTION_ICONS {
+                                                    if ui.button(icon).clicked() {
+                                                        picked_icon = Some(icon);
+                                                        ui.close_menu();
+                                                    }
+                                                }
+                                            });
+                                        })
+                                        .response
+                                        .on_hover_text("Pick an Icon");
+                                        if let Some(picked_icon) = picked_icon {
+                                            *option = format!("{picked_icon} {label}");
+                                        }
+                                        if duplicates.contains(&list_state.current_index) {
+                                            ui.colored_label(ui.style().visuals.warn_fg_color, "⚠")
+                                                .on_hover_text(
+                                                    "Same as another option, which splits its votes",
+                                                );
+                                        }
+                                        ui.add(TextEdit::singleline(option).hint_text(format!(
+                                            "Option {}",
+                                            list_state.current_index + 1
+                                        )));
                                     });
-                                },
-                            );
+                                });
+                            },
+                        );
+                        *pinned_options = rows
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, (_, _, pinned))| *pinned)
+                            .map(|(i, _)| i)
+                            .collect();
+                        let (new_options, new_capacities): (Vec<_>, Vec<_>) = rows
+                            .into_iter()
+                            .map(|(option, capacity, _)| (option, capacity))
+                            .unzip();
+                        *options = new_options;
+                        if let Some(capacities) = capacities {
+                            *capacities = if new_capacities.iter().any(Option::is_some) {
+                                new_capacities
+                            } else {
+                                Vec::new()
+                            };
                         }
-                        Form::YesNoNone | Form::YesNo => {}
+                        Self::show_paste_options(
+                            ui,
+                            options,
+                            list_state.current_index,
+                            &mut ui_data.option_import,
+                        );
+                    }
+                    if let Form::One {
+                        none_of_the_above, ..
+                    } = &mut question.form
+                    {
+                        ui.checkbox(none_of_the_above, "Offer \"None of the above\"");
+                    }
+                    if let Form::RankTopN { options, n } = &mut question.form {
+                        ui.horizontal(|ui| {
+                            ui.label("Rank top");
+                            *n = (*n).clamp(1, options.len().max(1));
+                            ui.add(DragValue::new(n).clamp_range(1..=options.len().max(1)));
+                        });
+                    }
+
+                    if !question.form.choices().is_empty() {
+                        ui.checkbox(&mut question.results_public, "Show answers in results");
+                    }
+                    ui.checkbox(&mut question.participant_visible, "Ask responders")
+                        .on_hover_text("Hidden questions are left at their default answer");
+
+                    if list_state.current_index > 0 {
+                        ui.separator();
+                        Self::show_condition_form(
+                            ui,
+                            question,
+                            &earlier_questions[..list_state.current_index],
+                            list_state.current_index,
+                        );
                     }
                 });
                 if list_state.current_index == 0 {
@@ -288,165 +836,578 @@ impl NewPoll {
             });
         if let Some(index) = deleted_index.or(reset_index) {
             Self::cleanup_question_dependents(index, &mut poll.metric_trackers, &mut poll.results);
+            for question in poll.questions.iter_mut() {
+                match &mut question.show_if {
+                    Some((show_if_index, _)) if *show_if_index == index => {
+                        question.show_if = None;
+                    }
+                    Some((show_if_index, _))
+                        if deleted_index.is_some() && *show_if_index > index =>
+                    {
+                        *show_if_index -= 1;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        if let Err(e) = poll.validate() {
+            ui.colored_label(ui.style().visuals.error_fg_color, e.to_string());
+        }
+    }
+
+    fn show_paste_options(
+        ui: &mut Ui,
+        options: &mut Vec<String>,
+        question_index: usize,
+        option_import: &mut Option<(usize, TextFetcher)>,
+    ) {
+        let error_id = ui.make_persistent_id(("import_options_error", question_index));
+        if let Some((index, fetcher)) = option_import {
+            if *index == question_index {
+                match fetcher.poll() {
+                    Some(Ok(text)) => {
+                        let imported = parse_option_list(&text, fetcher.is_csv());
+                        if imported.is_empty() {
+                            ui.data()
+                                .insert_temp(error_id, "No options found there".to_string());
+                        }
+                        add_options(options, imported);
+                        *option_import = None;
+                    }
+                    Some(Err(e)) => {
+                        ui.data().insert_temp(error_id, e);
+                        *option_import = None;
+                    }
+                    None => {
+                        ui.ctx().request_repaint_after(Duration::from_millis(100));
+                    }
+                }
+            }
+        }
+        ui.collapsing("Paste Options", |ui| {
+            let id = ui.make_persistent_id(("paste_options", question_index));
+            let mut text = ui.data().get_temp::<String>(id).unwrap_or_default();
+            ui.add(
+                TextEdit::multiline(&mut text)
+                    .desired_rows(3)
+                    .hint_text("One option per line"),
+            );
+            if ui.button("Add Options").clicked() {
+                let pasted = text
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .map(str::to_string)
+                    .collect::<Vec<_>>();
+                add_options(options, pasted);
+                text.clear();
+            }
+            ui.data().insert_temp(id, text);
+
+            let url_id = ui.make_persistent_id(("import_options_url", question_index));
+            let mut url = ui.data().get_temp::<String>(url_id).unwrap_or_default();
+            ui.horizontal(|ui| {
+                ui.add(
+                    TextEdit::singleline(&mut url)
+                        .hint_text("Or a URL to a list, CSV or JSON array")
+                        .desired_width(ui.standard_width()),
+                );
+                if matches!(option_import, Some((index, _)) if *index == question_index) {
+                    ui.spinner();
+                } else if ui
+                    .add_enabled(!url.trim().is_empty(), Button::new("Import"))
+                    .clicked()
+                {
+                    ui.data().remove::<String>(error_id);
+                    *option_import = Some((question_index, TextFetcher::new(&url)));
+                }
+            });
+            ui.data().insert_temp(url_id, url);
+            let error = ui.data().get_temp::<String>(error_id);
+            if let Some(error) = error {
+                ui.colored_label(ui.style().visuals.error_fg_color, error);
+            }
+        });
+    }
+
+    fn show_condition_form(
+        ui: &mut Ui,
+        question: &mut Question,
+        earlier_questions: &[(String, Vec<(String, Choice)>)],
+        current_index: usize,
+    ) {
+        let mut conditional = question.show_if.is_some();
+        ui.checkbox(&mut conditional, "Only show if...");
+        if conditional != question.show_if.is_some() {
+            question.show_if = if conditional {
+                earlier_questions[0]
+                    .1
+                    .first()
+                    .map(|(_, choice)| (0, choice.clone()))
+            } else {
+                None
+            };
+        }
+        if let Some((show_if_index, show_if_choice)) = &mut question.show_if {
+            let desired_width = ui.standard_width();
+            *show_if_index = (*show_if_index).min(earlier_questions.len() - 1);
+            ComboBox::from_id_source(format!("show_if_question_{current_index}"))
+                .width(desired_width)
+                .show_index(ui, show_if_index, earlier_questions.len(), |i| {
+                    format!("{i}: {}", limit(&earlier_questions[i].0))
+                });
+            let choices = &earlier_questions[*show_if_index].1;
+            let mut selected = choices
+                .iter()
+                .position(|(_, choice)| choice == show_if_choice)
+                .unwrap_or(0);
+            ui.label("was answered");
+            ComboBox::from_id_source(format!("show_if_answer_{current_index}"))
+                .width(desired_width)
+                .show_index(ui, &mut selected, choices.len(), |i| limit(&choices[i].0));
+            if let Some((_, choice)) = choices.get(selected) {
+                *show_if_choice = choice.clone();
+            }
         }
     }
 
     fn cleanup_question_dependents(
         index: usize,
         metrics: &mut Vec<MetricTracker>,
-        results: &mut Vec<PollResult>,
+        results: &mut [PollResult],
     ) {
         let metrics_to_delete = metrics
             .iter()
             .enumerate()
             .filter(|(_, tracker)| match &tracker.metric {
-                Metric::SpecificResponses { question_index, .. } => *question_index == index,
+                Metric::SpecificResponses { question_index, .. }
+                | Metric::MajorityReached { question_index, .. }
+                | Metric::CoOccurrence { question_index, .. }
+                | Metric::NumberHistogram { question_index, .. }
+                | Metric::RankedWinner { question_index, .. }
+                | Metric::Plurality { question_index } => *question_index == index,
+                Metric::CompositeRanking { question_indices } => question_indices.contains(&index),
+                Metric::CompletionRate {} => false,
             })
             .map(|(i, _)| i)
             .collect::<Vec<_>>();
-        for i in metrics_to_delete {
+        // From the back, so the indices left to delete still point at the same metrics
+        for &i in metrics_to_delete.iter().rev() {
             metrics.remove(i);
-            Self::cleanup_metric_dependents(i, results);
         }
+        remap_metric_indices(results, |i| {
+            (!metrics_to_delete.contains(&i)).then(|| {
+                i - metrics_to_delete
+                    .iter()
+                    .filter(|&&deleted| deleted < i)
+                    .count()
+            })
+        });
     }
 
-    fn cleanup_metric_dependents(index: usize, results: &mut Vec<PollResult>) {
-        let results_to_delete = results
-            .iter()
-            .enumerate()
-            .filter(|(_, result)| match result.requirements.get(0).unwrap() {
-                Requirement::AtLeast { metric_index, .. } => *metric_index as usize == index,
-            })
-            .map(|(i, _)| i)
-            .collect::<Vec<_>>();
-        for i in results_to_delete {
-            results.remove(i);
+    fn show_tiebreak_form(ui: &mut Ui, tiebreak: &mut TiebreakRule, metric_index: usize) {
+        ui.label("Break ties by");
+        let seeded = TiebreakRule::Seeded(match tiebreak {
+            TiebreakRule::Seeded(seed) => *seed,
+            // Stored with the poll, so anyone can reproduce the pick
+            _ => (js_sys::Math::random() * u32::MAX as f64) as u64,
+        });
+        ComboBox::from_id_source(format!("tiebreak_{metric_index}"))
+            .selected_text(tiebreak.to_string())
+            .show_ui(ui, |ui| {
+                for rule in [
+                    TiebreakRule::Lexicographic,
+                    seeded,
+                    TiebreakRule::FirstSubmitted,
+                ] {
+                    let text = match rule {
+                        TiebreakRule::Seeded(_) => "Random".to_string(),
+                        rule => rule.to_string(),
+                    };
+                    ui.selectable_value(tiebreak, rule, text);
+                }
+            });
+        if let TiebreakRule::Seeded(seed) = tiebreak {
+            ui.horizontal(|ui| {
+                ui.label("Seed");
+                ui.add(DragValue::new(seed));
+            });
         }
     }
 
     fn show_metrics_form(ui: &mut Ui, poll: &mut Poll, ui_data: &mut CreatingUiData) {
-        let deleted_index =
+        let mut metrics_list =
             OrderableList::new_with_factory(&mut poll.metric_trackers, "Metric", || {
                 MetricTracker::init_from_questions(&poll.questions)
             })
-            .add_button_is_at_bottom()
-            .show(ui, |list_state, ui, metric_tracker| {
-                let response =
-                    ui.group(|ui| {
-                        let label_response =
-                            ui.label(format!("Metric {}", list_state.current_index + 1));
-                        if let Some(fields_rect) = ui_data.fields_rect {
-                            let result_controls_rect = Rect {
-                                min: Pos2 {
-                                    x: label_response.rect.right(),
-                                    y: label_response.rect.top(),
-                                },
-                                max: Pos2 {
-                                    x: fields_rect.right(),
-                                    y: label_response.rect.bottom(),
-                                },
+            .add_button_is_at_bottom();
+        metrics_list.show(ui, |list_state, ui, metric_tracker| {
+            let response = ui.group(|ui| {
+                let label_response = ui.label(format!("Metric {}", list_state.current_index + 1));
+                if let Some(fields_rect) = ui_data.fields_rect {
+                    let result_controls_rect = Rect {
+                        min: Pos2 {
+                            x: label_response.rect.right(),
+                            y: label_response.rect.top(),
+                        },
+                        max: Pos2 {
+                            x: fields_rect.right(),
+                            y: label_response.rect.bottom(),
+                        },
+                    };
+                    ui.allocate_ui_at_rect(result_controls_rect, |ui| {
+                        ui.with_layout(Layout::right_to_left(Align::Min), |ui| {
+                            list_state.show_controls(ui);
+                        });
+                    });
+                }
+
+                let desired_width = ui.standard_width();
+                let field_shape = Vec2::new(desired_width, 0.);
+
+                const MAX_FIELD_LEN: usize = 20;
+                let mut completion = matches!(metric_tracker.metric, Metric::CompletionRate {});
+                if ui
+                    .checkbox(&mut completion, "Count fully completed responses")
+                    .changed()
+                {
+                    if completion {
+                        metric_tracker.metric = Metric::CompletionRate {};
+                    } else if let Some(tracker) =
+                        MetricTracker::init_from_questions(&poll.questions)
+                    {
+                        metric_tracker.metric = tracker.metric;
+                    }
+                }
+                let question_index = match &mut metric_tracker.metric {
+                    Metric::SpecificResponses { question_index, .. }
+                    | Metric::MajorityReached { question_index, .. }
+                    | Metric::CoOccurrence { question_index, .. }
+                    | Metric::NumberHistogram { question_index, .. }
+                    | Metric::RankedWinner { question_index, .. }
+                    | Metric::Plurality { question_index } => question_index,
+                    Metric::CompositeRanking { question_indices } => &mut question_indices[0],
+                    Metric::CompletionRate {} => return,
+                };
+                ui.label("Question");
+                ui.allocate_ui(field_shape, |ui| {
+                    ComboBox::from_id_source(format!(
+                        "selected_question_{}",
+                        list_state.current_index
+                    ))
+                    .width(desired_width)
+                    .show_index(
+                        ui,
+                        question_index,
+                        poll.questions.len(),
+                        |i| format!("{i}: {}", limit(&poll.questions[i].prompt)),
+                    );
+                });
+                let question_index = *question_index;
+
+                // Number questions get a histogram, ranking and pick one questions can
+                // track their top pick, and everything else counts answers
+                let form = &poll.questions[question_index].form;
+                let counted_answers = || Metric::SpecificResponses {
+                    question_index,
+                    choice: form.choices()[0].1.clone(),
+                };
+                let compatible = match (&metric_tracker.metric, form) {
+                    (Metric::NumberHistogram { .. }, Form::Number)
+                    | (
+                        Metric::RankedWinner { .. } | Metric::CompositeRanking { .. },
+                        Form::RankTopN { .. },
+                    )
+                    | (Metric::Plurality { .. }, Form::One { .. } | Form::OneOrNone { .. })
+                    | (Metric::CoOccurrence { .. }, Form::Multiple { .. }) => true,
+                    (Metric::SpecificResponses { .. } | Metric::MajorityReached { .. }, form) => {
+                        *form != Form::Number
+                    }
+                    _ => false,
+                };
+                if !compatible {
+                    metric_tracker.metric = if *form == Form::Number {
+                        Metric::NumberHistogram {
+                            question_index,
+                            buckets: vec![0],
+                        }
+                    } else {
+                        counted_answers()
+                    };
+                }
+                let top_pick_metric = match form {
+                    Form::RankTopN { .. } => Some(Metric::RankedWinner {
+                        question_index,
+                        tiebreak: Default::default(),
+                    }),
+                    Form::One { .. } | Form::OneOrNone { .. } => {
+                        Some(Metric::Plurality { question_index })
+                    }
+                    _ => None,
+                };
+                if let Some(top_pick_metric) = top_pick_metric {
+                    let mut top_pick = matches!(
+                        metric_tracker.metric,
+                        Metric::RankedWinner { .. }
+                            | Metric::Plurality { .. }
+                            | Metric::CompositeRanking { .. }
+                    );
+                    if ui.checkbox(&mut top_pick, "Track the top pick").changed() {
+                        metric_tracker.metric = if top_pick {
+                            top_pick_metric.clone()
+                        } else {
+                            counted_answers()
+                        };
+                    }
+                    if top_pick && matches!(form, Form::RankTopN { .. }) {
+                        let mut combined =
+                            matches!(metric_tracker.metric, Metric::CompositeRanking { .. });
+                        if ui
+                            .checkbox(&mut combined, "Combine with other rankings")
+                            .changed()
+                        {
+                            metric_tracker.metric = if combined {
+                                Metric::CompositeRanking {
+                                    question_indices: vec![question_index],
+                                }
+                            } else {
+                                top_pick_metric
                             };
-                            ui.allocate_ui_at_rect(result_controls_rect, |ui| {
-                                ui.with_layout(Layout::right_to_left(Align::Min), |ui| {
-                                    list_state.show_controls(ui);
-                                });
-                            });
                         }
+                    }
+                }
 
-                        let desired_width = ui.standard_width();
-                        let field_shape = Vec2::new(desired_width, 0.);
+                if let Metric::SpecificResponses { choice, .. }
+                | Metric::MajorityReached { choice, .. } = &metric_tracker.metric
+                {
+                    let mut majority =
+                        matches!(metric_tracker.metric, Metric::MajorityReached { .. });
+                    if ui
+                        .checkbox(&mut majority, "Only whether a majority chose it")
+                        .changed()
+                    {
+                        let choice = match choice {
+                            // A majority can't be reached by skipping
+                            Choice::NoSelection => form.choices()[0].1.clone(),
+                            choice => choice.clone(),
+                        };
+                        metric_tracker.metric = if majority {
+                            Metric::MajorityReached {
+                                question_index,
+                                choice,
+                            }
+                        } else {
+                            Metric::SpecificResponses {
+                                question_index,
+                                choice,
+                            }
+                        };
+                    }
+                }
 
-                        const MAX_FIELD_LEN: usize = 20;
-                        match &mut metric_tracker.metric {
+                if let (
+                    Metric::SpecificResponses { choice, .. }
+                    | Metric::CoOccurrence {
+                        choice_a: choice, ..
+                    },
+                    Form::Multiple { .. },
+                ) = (&metric_tracker.metric, form)
+                {
+                    let mut together = matches!(metric_tracker.metric, Metric::CoOccurrence { .. });
+                    if ui
+                        .checkbox(&mut together, "Only when picked along with another")
+                        .changed()
+                    {
+                        let choice = choice.clone();
+                        metric_tracker.metric = if together {
+                            Metric::CoOccurrence {
+                                question_index,
+                                choice_a: choice.clone(),
+                                choice_b: choice,
+                            }
+                        } else {
                             Metric::SpecificResponses {
                                 question_index,
                                 choice,
-                            } => {
-                                ui.label("Question");
+                            }
+                        };
+                    }
+                }
+
+                let counts_responses =
+                    matches!(metric_tracker.metric, Metric::SpecificResponses { .. });
+                match &mut metric_tracker.metric {
+                    Metric::CoOccurrence {
+                        choice_a, choice_b, ..
+                    } => {
+                        let answers = poll.questions[question_index].form.choices();
+                        for (label, choice) in [("Answer", choice_a), ("Along with", choice_b)] {
+                            let mut selected = answers
+                                .iter()
+                                .position(|(_, answer)| answer == choice)
+                                .unwrap_or(0);
+                            ui.label(label);
+                            ui.allocate_ui(field_shape, |ui| {
+                                ComboBox::from_id_source(format!(
+                                    "selected_answer_{}_{label}",
+                                    list_state.current_index
+                                ))
+                                .show_index(
+                                    ui,
+                                    &mut selected,
+                                    answers.len(),
+                                    |i| format!("{i}: {}", limit(&answers[i].0)),
+                                );
+                            });
+                            if let Some((_, answer)) = answers.get(selected) {
+                                *choice = answer.clone();
+                            }
+                        }
+                    }
+                    Metric::SpecificResponses { choice, .. }
+                    | Metric::MajorityReached { choice, .. } => {
+                        let form = &poll.questions[question_index].form;
+                        let answers = if counts_responses {
+                            form.countable_choices()
+                        } else {
+                            form.choices()
+                        };
+                        match form {
+                            Form::OneOrNone { .. }
+                            | Form::One { .. }
+                            | Form::Multiple { .. }
+                            | Form::RankTopN { .. } => {
+                                let mut selected = answers
+                                    .iter()
+                                    .position(|(_, answer)| answer == choice)
+                                    .unwrap_or(0);
+                                ui.label("Answer");
                                 ui.allocate_ui(field_shape, |ui| {
                                     ComboBox::from_id_source(format!(
-                                        "selected_question_{}",
+                                        "selected_answer_{}",
                                         list_state.current_index
                                     ))
-                                    .width(desired_width)
                                     .show_index(
                                         ui,
-                                        question_index,
-                                        poll.questions.len(),
-                                        |i| format!("{i}: {}", limit(&poll.questions[i].prompt)),
+                                        &mut selected,
+                                        answers.len(),
+                                        |i| format!("{i}: {}", limit(&answers[i].0)),
                                     );
                                 });
 
-                                match &poll.questions[*question_index].form {
-                                    Form::OneOrNone { options }
-                                    | Form::One { options }
-                                    | Form::Multiple { options } => {
-                                        let mut selected =
-                                            if let Some(&selected) = choice.as_index() {
-                                                selected as usize
-                                            } else {
-                                                return;
-                                            };
-                                        ui.label("Answer");
-                                        ui.allocate_ui(field_shape, |ui| {
-                                            ComboBox::from_id_source(format!(
-                                                "selected_answer_{}",
-                                                list_state.current_index
-                                            ))
-                                            .show_index(ui, &mut selected, options.len(), |i| {
-                                                format!("{i}: {}", limit(&options[i]))
-                                            });
-                                        });
-
-                                        *choice = Choice::Index(selected as u8);
-                                    }
-                                    Form::YesNoNone | Form::YesNo => {
-                                        let mut selected =
-                                            if let Some(&selected) = choice.as_yes_or_no() {
-                                                if selected {
-                                                    1
-                                                } else {
-                                                    0
-                                                }
-                                            } else {
-                                                // could probably remove this branch
-                                                return;
-                                            };
-                                        ui.label("Answer");
-                                        ui.allocate_ui(field_shape, |ui| {
-                                            ComboBox::from_id_source(format!(
-                                                "selected_answer_{}",
-                                                list_state.current_index
-                                            ))
-                                            .show_index(ui, &mut selected, 2, |i| {
-                                                if i == 0 { "No" } else { "Yes" }.to_string()
-                                            });
-                                        });
+                                if let Some((_, answer)) = answers.get(selected) {
+                                    *choice = answer.clone();
+                                }
+                            }
+                            Form::YesNoNone | Form::YesNo => {
+                                let mut selected = if let Some(selected) =
+                                    answers.iter().position(|(_, answer)| answer == choice)
+                                {
+                                    selected
+                                } else {
+                                    // could probably remove this branch
+                                    return;
+                                };
+                                ui.label("Answer");
+                                ui.allocate_ui(field_shape, |ui| {
+                                    ComboBox::from_id_source(format!(
+                                        "selected_answer_{}",
+                                        list_state.current_index
+                                    ))
+                                    .show_index(
+                                        ui,
+                                        &mut selected,
+                                        answers.len(),
+                                        |i| answers[i].0.clone(),
+                                    );
+                                });
 
-                                        *choice = Choice::YesOrNo(selected != 0);
+                                *choice = answers[selected].1.clone();
+                            }
+                            Form::Number => {}
+                        }
+                    }
+                    Metric::RankedWinner { tiebreak, .. } => {
+                        Self::show_tiebreak_form(ui, tiebreak, list_state.current_index);
+                    }
+                    Metric::Plurality { .. } | Metric::CompletionRate {} => {}
+                    Metric::CompositeRanking { question_indices } => {
+                        for (i, question) in poll.questions.iter().enumerate() {
+                            if i == question_index
+                                || !matches!(question.form, Form::RankTopN { .. })
+                            {
+                                continue;
+                            }
+                            let position = question_indices.iter().position(|&index| index == i);
+                            let mut included = position.is_some();
+                            if ui
+                                .checkbox(&mut included, limit(&question.prompt))
+                                .changed()
+                            {
+                                match position {
+                                    Some(position) => {
+                                        question_indices.remove(position);
                                     }
+                                    None => question_indices.push(i),
                                 }
                             }
                         }
-                        ui.checkbox(
-                            &mut metric_tracker.publicly_visible,
-                            "Show progress publicly",
-                        );
-                    });
-                if list_state.current_index == 0 {
-                    ui_data.question_group_rect = Some(response.response.rect);
+                    }
+                    Metric::NumberHistogram { buckets, .. } => {
+                        ui.label("Bucket boundaries");
+                        ui.horizontal_wrapped(|ui| {
+                            let mut removed = None;
+                            for (i, boundary) in buckets.iter_mut().enumerate() {
+                                ui.add(DragValue::new(boundary));
+                                if ui
+                                    .small_button("🗑")
+                                    .on_hover_text("Delete Boundary")
+                                    .clicked()
+                                {
+                                    removed = Some(i);
+                                }
+                            }
+                            if let Some(i) = removed {
+                                buckets.remove(i);
+                            }
+                            if ui.small_button("Add Boundary").clicked() {
+                                buckets.push(buckets.last().map_or(0, |last| last + 1));
+                            }
+                        });
+                    }
                 }
+                ui.checkbox(
+                    &mut metric_tracker.publicly_visible,
+                    "Show progress publicly",
+                );
             });
-        if let Some(index) = deleted_index {
-            Self::cleanup_metric_dependents(index, &mut poll.results);
-        }
+            if list_state.current_index == 0 {
+                ui_data.question_group_rect = Some(response.response.rect);
+            }
+        });
+        // Requirements point at metrics by position
+        remap_metric_indices(&mut poll.results, |i| metrics_list.new_position(i));
     }
 
-    fn show_results_form(ui: &mut Ui, poll: &mut Poll, ui_data: &mut CreatingUiData) {
+    fn show_results_form(
+        ui: &mut Ui,
+        poll: &mut Poll,
+        ui_data: &mut CreatingUiData,
+        palette: Palette,
+    ) {
         if poll.metric_trackers.is_empty() {
             ui.label("Before you can add a result, you need to add at least one metric.");
             return;
         }
+        // Requirements compare counts, so other metrics can't back a result
+        if !poll
+            .metric_trackers
+            .iter()
+            .any(|metric_tracker| counts_responses(&metric_tracker.metric))
+        {
+            ui.label("Add a metric that counts responses first.");
+            return;
+        }
 
         OrderableList::new(&mut poll.results, "Result")
             .add_button_is_at_bottom()
@@ -478,12 +1439,21 @@ impl NewPoll {
                     );
                     ui_data.fields_rect = Some(response.rect);
                     let field_shape = Vec2::new(response.rect.width(), 0.);
+                    if result.disabled {
+                        // Left alone until then, rather than quietly pointed at another metric
+                        ui.label("Its metric was deleted, so this result is disabled.");
+                        if ui.button("Choose a New Metric").clicked() {
+                            result.disabled = false;
+                        }
+                        return;
+                    }
 
                     let mut selected = match &result.requirements[0] {
                         Requirement::AtLeast { .. } => 0,
+                        Requirement::Exactly { .. } => 1,
                     };
                     let selected_before = selected;
-                    const TYPES: &[&str] = &["At Least X"];
+                    const TYPES: &[&str] = &["At Least X", "Exactly X"];
                     ui.label("Requirements Type");
                     ui.allocate_ui(field_shape, |ui| {
                         ComboBox::from_id_source(format!(
@@ -491,96 +1461,227 @@ impl NewPoll {
                             list_state.current_index
                         ))
                         .width(ui.standard_width())
-                        .show_index(ui, &mut selected, 1, |i| TYPES[i].to_string());
+                        .show_index(ui, &mut selected, TYPES.len(), |i| TYPES[i].to_string());
                     });
                     if selected != selected_before {
+                        let (Requirement::AtLeast { metric_index, .. }
+                        | Requirement::Exactly { metric_index, .. }) = result.requirements[0];
                         result.requirements[0] = match selected {
                             0 => Requirement::AtLeast {
                                 minimum: 1,
-                                metric_index: 0,
+                                metric_index,
+                            },
+                            1 => Requirement::Exactly {
+                                count: 1,
+                                metric_index,
                             },
                             _ => unreachable!(),
                         };
                     }
 
                     const MAX_FIELD_LEN: usize = 20;
-                    match &mut result.requirements[0] {
-                        Requirement::AtLeast {
-                            minimum,
-                            metric_index,
-                        } => {
-                            *metric_index = {
-                                let compatible_metrics = poll
-                                    .metric_trackers
-                                    .iter()
-                                    .enumerate()
-                                    .filter(|(_, metric_tracker)| match metric_tracker.metric {
-                                        Metric::SpecificResponses { .. } => true,
-                                    })
-                                    .collect::<Vec<_>>();
-                                let mut sub_index = compatible_metrics
-                                    .iter()
-                                    .map(|(i, _)| *i)
-                                    .find(|i| *i == *metric_index as usize)
-                                    .unwrap_or(0);
-                                ui.label("Metric");
-                                ui.allocate_ui(field_shape, |ui| {
-                                    ComboBox::from_id_source(format!(
-                                        "selected_metric_{}",
-                                        list_state.current_index
-                                    ))
-                                    .show_index(
-                                        ui,
-                                        &mut sub_index,
-                                        compatible_metrics.len(),
-                                        |i| {
-                                            format!(
-                                                "{}: {}",
-                                                &compatible_metrics[i].0,
-                                                limit(
-                                                    &compatible_metrics[i]
-                                                        .1
-                                                        .metric
-                                                        .render(&poll.questions)
-                                                )
-                                            )
-                                        },
-                                    );
-                                });
-                                compatible_metrics[sub_index].0 as u16
-                            };
+                    let (metric_index, value, value_label, lowest) =
+                        match &mut result.requirements[0] {
+                            Requirement::AtLeast {
+                                minimum,
+                                metric_index,
+                            } => (metric_index, minimum, "Minimum", 1),
+                            Requirement::Exactly {
+                                count,
+                                metric_index,
+                            } => (metric_index, count, "Count", 0),
+                        };
+                    *metric_index = {
+                        let compatible_metrics = poll
+                            .metric_trackers
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, metric_tracker)| counts_responses(&metric_tracker.metric))
+                            .collect::<Vec<_>>();
+                        let mut sub_index = compatible_metrics
+                            .iter()
+                            .map(|(i, _)| *i)
+                            .find(|i| *i == *metric_index as usize)
+                            .unwrap_or(0);
+                        ui.label("Metric");
+                        ui.allocate_ui(field_shape, |ui| {
+                            ComboBox::from_id_source(format!(
+                                "selected_metric_{}",
+                                list_state.current_index
+                            ))
+                            .show_index(
+                                ui,
+                                &mut sub_index,
+                                compatible_metrics.len(),
+                                |i| {
+                                    format!(
+                                        "{}: {}",
+                                        &compatible_metrics[i].0,
+                                        limit(
+                                            &compatible_metrics[i].1.metric.render(&poll.questions)
+                                        )
+                                    )
+                                },
+                            );
+                        });
+                        compatible_metrics[sub_index].0 as u16
+                    };
 
-                            ui.label("Minimum");
-                            let mut minimum_usize = *minimum as usize - 1;
-                            ui.allocate_ui(field_shape, |ui| {
-                                ComboBox::from_id_source(format!(
-                                    "minimum_{}",
-                                    list_state.current_index
-                                ))
-                                .show_index(
-                                    ui,
-                                    &mut minimum_usize,
-                                    30,
-                                    |i| (i + 1).to_string(),
-                                );
-                            });
-                            *minimum = minimum_usize as u64 + 1;
-                        }
-                    }
+                    ui.label(value_label);
+                    *value = (*value).max(lowest);
+                    ui.allocate_ui(field_shape, |ui| {
+                        ui.add(
+                            DragValue::new(value)
+                                .clamp_range(lowest..=u64::MAX)
+                                .speed(0.1),
+                        );
+                    });
                 });
                 if list_state.current_index == 0 {
                     ui_data.question_group_rect = Some(response.response.rect);
                 }
             });
+
+        ui.separator();
+        Self::show_simulation(ui, poll, ui_data, palette);
+    }
+
+    fn show_simulation(ui: &mut Ui, poll: &Poll, ui_data: &mut CreatingUiData, palette: Palette) {
+        if ui_data.simulated_responders == 0 {
+            ui_data.simulated_responders = 10;
+        }
+        ui.horizontal(|ui| {
+            if ui.button("Simulate").clicked() {
+                let responses = (0..ui_data.simulated_responders)
+                    .map(|i| {
+                        let responses = poll.questions.iter().map(simulate_response).collect();
+                        (format!("Simulated {i}"), responses)
+                    })
+                    .collect::<Vec<_>>();
+                ui_data.simulation = Some(ResultsUi {
+                    poll_progress: Some(poll.snapshot(&responses)),
+                    ..Default::default()
+                });
+            }
+            ui.add(
+                DragValue::new(&mut ui_data.simulated_responders)
+                    .clamp_range(1..=1000)
+                    .suffix(" responses"),
+            );
+            if ui_data.simulation.is_some() && ui.button("Clear").clicked() {
+                ui_data.simulation = None;
+            }
+        })
+        .response
+        .on_hover_text("Preview results with random responses. Nothing is sent anywhere.");
+        if let Some(simulation) = &mut ui_data.simulation {
+            simulation.show(ui, poll, None, palette);
+        }
+    }
+}
+
+/// Adds options to a question's, replacing the blank placeholder a new question starts with
+fn add_options(options: &mut Vec<String>, new_options: Vec<String>) {
+    if !new_options.is_empty() && options.len() == 1 && options[0].trim().is_empty() {
+        options.clear();
+    }
+    options.extend(new_options);
+}
+
+fn random_below(n: usize) -> usize {
+    (js_sys::Math::random() * n as f64) as usize
+}
+
+/// A random, valid response to `question`
+fn simulate_response(question: &Question) -> FormResponse {
+    let choices = question.form.choices();
+    let random_choice = || choices[random_below(choices.len())].1.clone();
+    match &question.form {
+        Form::OneOrNone { .. } | Form::YesNoNone => {
+            FormResponse::ChooseOneOrNone((random_below(choices.len() + 1) > 0).then(random_choice))
+        }
+        Form::One { .. } | Form::YesNo => FormResponse::ChooseOne(random_choice()),
+        Form::Multiple { .. } => FormResponse::ChooseMultiple(
+            choices
+                .iter()
+                .filter(|_| random_below(2) == 0)
+                .map(|(_, choice)| choice.clone())
+                .collect(),
+        ),
+        Form::RankTopN { n, .. } => {
+            let mut remaining = choices.iter().map(|(_, c)| c.clone()).collect::<Vec<_>>();
+            let mut ranking = Vec::new();
+            while ranking.len() < *n && !remaining.is_empty() {
+                ranking.push(remaining.remove(random_below(remaining.len())));
+            }
+            FormResponse::RankedChoice(ranking)
+        }
+        Form::Number => FormResponse::Number(random_below(11) as i64),
     }
 }
 
 const MAX_FIELD_LEN: usize = 30;
+/// How tall a prompt editor grows before it scrolls
+const MAX_PROMPT_ROWS: f32 = 6.0;
+
+const OPTION_ICONS: &[&str] = &[
+    "✅", "❌", "❓", "⭐", "❤", "🎉", "🍕", "🍔", "🌮", "☕", "🍺", "🚗", "🚶", "🚲", "🏠", "🌞",
+];
+
+/// Whether a result's requirement can compare against the metric's count
+fn counts_responses(metric: &Metric) -> bool {
+    match metric {
+        Metric::SpecificResponses { .. }
+        | Metric::MajorityReached { .. }
+        | Metric::CoOccurrence { .. }
+        | Metric::CompletionRate {} => true,
+        Metric::NumberHistogram { .. }
+        | Metric::RankedWinner { .. }
+        | Metric::Plurality { .. }
+        | Metric::CompositeRanking { .. } => false,
+    }
+}
 
 fn limit(s: &str) -> String {
-    if s.len() > MAX_FIELD_LEN {
-        format!("{}...", s.get(..(MAX_FIELD_LEN - 3)).unwrap())
+    if s.chars().count() > MAX_FIELD_LEN {
+        format!(
+            "{}...",
+            s.chars().take(MAX_FIELD_LEN - 3).collect::<String>()
+        )
     } else {
         s.to_string()
     }
 }
+
+/// A checkbox to turn the deadline on, then its date and local time
+fn show_deadline_form(
+    ui: &mut Ui,
+    label: &str,
+    hover_text: &str,
+    deadline: &mut Option<DateTime<Utc>>,
+) {
+    ui.horizontal(|ui| {
+        let mut has_deadline = deadline.is_some();
+        ui.checkbox(&mut has_deadline, label)
+            .on_hover_text(hover_text);
+        if has_deadline != deadline.is_some() {
+            *deadline = has_deadline.then(|| Utc::now() + chrono::Duration::days(1));
+        }
+        if let Some(deadline) = deadline {
+            let local = deadline.with_timezone(&Local);
+            let (mut date, mut hour, mut minute) =
+                (local.date_naive(), local.hour(), local.minute());
+            ui.add(DatePickerButton::new(&mut date).id_source(label));
+            ui.add(DragValue::new(&mut hour).clamp_range(0..=23).suffix("h"));
+            ui.add(DragValue::new(&mut minute).clamp_range(0..=59).suffix("m"));
+            if (date, hour, minute) != (local.date_naive(), local.hour(), local.minute()) {
+                if let Some(changed) = date
+                    .and_hms_opt(hour, minute, 0)
+                    .and_then(|time| Local.from_local_datetime(&time).earliest())
+                {
+                    *deadline = changed.with_timezone(&Utc);
+                }
+            }
+        }
+    });
+}
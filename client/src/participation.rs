@@ -1,12 +1,27 @@
-use std::time::Duration;
+use std::{collections::HashSet, hash::Hash, time::Duration};
 
-use crate::{app::SignInData, misc::Submitter, toggle_switch::toggle_ui};
-use areyougoing_shared::{Choice, Form, FormResponse, Poll, PollResponse, PollSubmissionResult};
+use crate::{
+    api::{self, ApiCall},
+    app::SignInData,
+    misc::{console_log, option_button, option_text},
+    results_ui::ResultsUi,
+    toggle_switch::toggle_ui,
+};
+use areyougoing_shared::{
+    shown_questions, stable_hash, Choice, DedupStrategy, Form, FormResponse, HasRespondedResult,
+    Poll, PollResponse, PollSubmissionResult, NONE_OF_THE_ABOVE,
+};
 use derivative::Derivative;
-use egui::{Button, ScrollArea, TextEdit, Ui};
+use egui::{
+    Align, Button, Color32, DragValue, Grid, Id, Layout, ProgressBar, ScrollArea, TextEdit,
+    TextStyle, Ui, WidgetText,
+};
 use serde::{Deserialize, Serialize};
 
 const SIGN_IN_TEXT: &str = "SIGN IN";
+/// Narrower than this is laid out for phones
+pub const COMPACT_WIDTH: f32 = 500.0;
+const TAP_TARGET_HEIGHT: f32 = 44.0;
 
 #[derive(Derivative)]
 #[derivative(PartialEq)]
@@ -15,36 +30,112 @@ pub enum ParticipationState {
     SignedIn {
         user: String,
         question_responses: Vec<FormResponse>,
+        /// The answers just submitted, when coming back to them. SUBMIT waits for an
+        /// explicit Edit while they're unchanged, so going back can't send them twice.
+        #[serde(default)]
+        submitted: Option<Vec<FormResponse>>,
     },
     SignIn,
+    CheckingResponse {
+        user: String,
+        #[serde(skip)]
+        #[derivative(PartialEq = "ignore")]
+        state: Option<ApiCall<HasRespondedResult>>,
+    },
+    AlreadyResponded {
+        user: String,
+    },
     Submitting {
         response: PollResponse,
         #[serde(skip)]
         #[derivative(PartialEq = "ignore")]
-        state: Option<Submitter<PollResponse, PollSubmissionResult>>,
+        state: Option<ApiCall<PollSubmissionResult>>,
+    },
+    SubmitConfirmation {
+        response: PollResponse,
     },
-    SubmitConfirmation,
+}
+
+/// A response being filled in or just submitted, to pick up again once the poll is
+/// fetched again
+#[derive(Clone, PartialEq, Debug)]
+pub struct Resume {
+    pub user: String,
+    pub question_responses: Vec<FormResponse>,
+    pub submitted: Option<Vec<FormResponse>>,
+}
+
+/// Whether the answers being filled in have been written to browser storage yet, which
+/// happens on eframe's next save
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub enum LocalSave {
+    #[default]
+    Unchanged,
+    Pending,
+    Saved,
 }
 
 impl ParticipationState {
+    /// What to pick up again once `poll` is fetched again. Like "Back to My Answers", a
+    /// submitted response only goes back to being edited if resubmitting replaces it.
+    pub fn resume(&self, poll: &Poll) -> Option<Resume> {
+        match self {
+            ParticipationState::SignedIn {
+                user,
+                question_responses,
+                submitted,
+            } => Some(Resume {
+                user: user.clone(),
+                question_responses: question_responses.clone(),
+                submitted: submitted.clone(),
+            }),
+            ParticipationState::Submitting { response, .. } => Some(Resume {
+                user: response.user.clone(),
+                question_responses: response.responses.clone(),
+                submitted: None,
+            }),
+            ParticipationState::SubmitConfirmation { response }
+                if poll.dedup != DedupStrategy::None =>
+            {
+                Some(Resume {
+                    user: response.user.clone(),
+                    question_responses: response.responses.clone(),
+                    submitted: Some(response.responses.clone()),
+                })
+            }
+            _ => None,
+        }
+    }
+
     pub fn process(
         &mut self,
         ui: &mut Ui,
         sign_in_data: &mut SignInData,
         key: u64,
+        password: Option<&str>,
         poll: &Poll,
-        stale: &mut bool,
+        results_ui: &mut ResultsUi,
     ) {
+        let ResultsUi {
+            poll_progress,
+            stale,
+            ..
+        } = results_ui;
+        let seats_taken = poll_progress
+            .as_ref()
+            .map(|p| p.seats_taken.as_slice())
+            .unwrap_or_default();
         let mut next_participation_state = None;
         match self {
             ParticipationState::SignIn => {
                 const SIGN_IN_HINT: &str = "Type a name";
-                ui.label("Participate in this poll?");
-                ui.add(TextEdit::singleline(&mut sign_in_data.user_entry).hint_text(SIGN_IN_HINT));
+                let prompt = ui.label("Participate in this poll?");
+                ui.add(TextEdit::singleline(&mut sign_in_data.user_entry).hint_text(SIGN_IN_HINT))
+                    .labelled_by(prompt.id);
                 if ui.button(SIGN_IN_TEXT).clicked() {
-                    next_participation_state = Some(ParticipationState::SignedIn {
+                    next_participation_state = Some(ParticipationState::CheckingResponse {
                         user: sign_in_data.user_entry.clone(),
-                        question_responses: Vec::new(),
+                        state: None,
                     });
                     if !sign_in_data.old_names.contains(&sign_in_data.user_entry) {
                         sign_in_data.old_names.push(sign_in_data.user_entry.clone());
@@ -58,150 +149,149 @@ impl ParticipationState {
                         .id_source("name_scroll")
                         .show(ui, |ui| {
                             for name in sign_in_data.old_names.iter().rev() {
-                                if ui.button(name).clicked() {
-                                    sign_in_data.user_entry = name.to_string();
-                                }
+                                ui.horizontal(|ui| {
+                                    if ui.button(name).clicked() {
+                                        sign_in_data.user_entry = name.to_string();
+                                    }
+                                    let answered = sign_in_data
+                                        .answered_polls
+                                        .get(name)
+                                        .map_or(0, HashSet::len);
+                                    if answered > 0 {
+                                        ui.weak(format!(
+                                            "{answered} {} answered",
+                                            if answered == 1 { "poll" } else { "polls" }
+                                        ));
+                                    }
+                                });
                             }
                         });
                 }
             }
+            ParticipationState::CheckingResponse {
+                user,
+                ref mut state,
+            } => {
+                ui.spinner();
+                if let Some(checker) = state {
+                    match checker.poll() {
+                        Some(Ok(result)) => {
+                            next_participation_state = Some(match result {
+                                HasRespondedResult::Success { responded: true } => {
+                                    ParticipationState::AlreadyResponded { user: user.clone() }
+                                }
+                                HasRespondedResult::Success { responded: false }
+                                | HasRespondedResult::Error => ParticipationState::SignedIn {
+                                    user: user.clone(),
+                                    question_responses: Vec::new(),
+                                    submitted: None,
+                                },
+                            })
+                        }
+                        Some(Err(e)) => {
+                            console_log!("Checking for a response to poll {key} failed: {e}");
+                            *state = None;
+                        }
+                        None => {}
+                    }
+                } else {
+                    if poll.dedup != DedupStrategy::None {
+                        *state = Some(ApiCall::new(api::has_responded(
+                            key,
+                            user.clone(),
+                            sign_in_data.device_key.clone(),
+                            password.map(str::to_string),
+                        )));
+                    } else {
+                        // Every submission is a new response, so there's nothing to edit
+                        next_participation_state = Some(ParticipationState::SignedIn {
+                            user: user.clone(),
+                            question_responses: Vec::new(),
+                            submitted: None,
+                        });
+                    }
+                }
+                ui.ctx().request_repaint_after(Duration::from_millis(100));
+            }
+            ParticipationState::AlreadyResponded { user } => {
+                ui.label(format!("You've already responded to this poll as {user}."));
+                if ui.button("EDIT RESPONSE").clicked() {
+                    next_participation_state = Some(ParticipationState::SignedIn {
+                        user: user.clone(),
+                        question_responses: Vec::new(),
+                        submitted: None,
+                    });
+                }
+                if ui.button("Sign Out").clicked() {
+                    next_participation_state = Some(ParticipationState::SignIn);
+                }
+            }
             ParticipationState::SignedIn {
                 user,
                 ref mut question_responses,
+                submitted,
             } => {
                 if question_responses.is_empty() {
                     *question_responses = poll.init_responses();
                 }
-                ScrollArea::vertical()
-                    .id_source("participation_scroll")
-                    .show(ui, |ui| {
-                        for (question, mut question_response) in
-                            poll.questions.iter().zip(question_responses.iter_mut())
-                        {
-                            ui.group(|ui| {
-                                ui.label(&question.prompt);
-                                match (&question.form, &mut question_response) {
-                                    (
-                                        Form::OneOrNone { options },
-                                        FormResponse::ChooseOneOrNone(choice),
-                                    ) => {
-                                        for (i, option) in options.iter().enumerate() {
-                                            let selected = choice.is_some()
-                                                && *choice.as_ref().unwrap().as_index().unwrap()
-                                                    == i as u8;
-                                            let mut button = Button::new(option);
-                                            if selected {
-                                                button = button.fill(
-                                                    ui.ctx().style().visuals.selection.bg_fill,
-                                                );
-                                            }
-                                            let response = ui.add(button);
-                                            if response.clicked() {
-                                                *choice = if selected {
-                                                    None
-                                                } else {
-                                                    Some(Choice::Index(i as u8))
-                                                };
-                                            }
-                                        }
-                                    }
-                                    (Form::One { options }, FormResponse::ChooseOne(choice)) => {
-                                        for (i, option) in options.iter().enumerate() {
-                                            let selected = *choice.as_index().unwrap() == i as u8;
-                                            let mut button = Button::new(option);
-                                            if selected {
-                                                button = button.fill(
-                                                    ui.ctx().style().visuals.selection.bg_fill,
-                                                );
-                                            }
-                                            let response = ui.add(button);
-                                            if response.clicked() {
-                                                *choice = Choice::Index(i as u8);
-                                            }
-                                        }
-                                    }
-                                    (
-                                        Form::Multiple { options },
-                                        FormResponse::ChooseMultiple(choices),
-                                    ) => {
-                                        for (i, option) in options.iter().enumerate() {
-                                            let choice_index = choices
-                                                .iter()
-                                                .enumerate()
-                                                .filter(|(_, c)| {
-                                                    *c.as_index().unwrap() as usize == i
-                                                })
-                                                .map(|(i, _)| i)
-                                                .next();
-                                            let mut button = Button::new(option);
-                                            if choice_index.is_some() {
-                                                button = button.fill(
-                                                    ui.ctx().style().visuals.selection.bg_fill,
-                                                );
-                                            }
-                                            let response = ui.add(button);
-                                            if response.clicked() {
-                                                if let Some(index) = choice_index {
-                                                    choices.remove(index);
-                                                } else {
-                                                    choices.push(Choice::Index(i as u8));
-                                                };
-                                            }
-                                        }
-                                    }
-                                    (Form::YesNoNone, FormResponse::ChooseOneOrNone(choice)) => {
-                                        let mut yes_button = Button::new("Yes");
-                                        let mut no_button = Button::new("No");
-
-                                        if let Some(Choice::YesOrNo(yes)) = choice {
-                                            let selected_fill =
-                                                ui.ctx().style().visuals.selection.bg_fill;
-                                            if *yes {
-                                                yes_button = yes_button.fill(selected_fill);
-                                            } else {
-                                                no_button = no_button.fill(selected_fill);
-                                            }
-                                        }
-                                        let yes_response = ui.add(yes_button);
-                                        let no_response = ui.add(no_button);
-                                        if let Some(Choice::YesOrNo(yes)) = choice {
-                                            if yes_response.clicked() {
-                                                if *yes {
-                                                    *choice = None;
-                                                } else {
-                                                    *choice = Some(Choice::YesOrNo(true));
-                                                }
-                                            } else if no_response.clicked() {
-                                                if *yes {
-                                                    *choice = Some(Choice::YesOrNo(false));
-                                                } else {
-                                                    *choice = None;
-                                                }
-                                            }
-                                        } else if yes_response.clicked() {
-                                            *choice = Some(Choice::YesOrNo(true));
-                                        } else if no_response.clicked() {
-                                            *choice = Some(Choice::YesOrNo(false));
-                                        }
-                                    }
-                                    (Form::YesNo, FormResponse::ChooseOne(choice)) => {
-                                        toggle_ui(ui, choice.as_yes_or_no_mut().unwrap());
-                                    }
-                                    _ => unreachable!(),
+                let previous_responses = question_responses.clone();
+                show_questions(
+                    ui,
+                    poll,
+                    question_responses,
+                    seats_taken,
+                    shuffle_seed(&sign_in_data.device_key),
+                    |ui, question_responses| {
+                        let full_message = ui.data().get_temp::<String>(full_message_id());
+                        if let Some(message) = full_message {
+                            ui.colored_label(ui.visuals().warn_fg_color, message);
+                        }
+                        ui.add(
+                            TextEdit::singleline(&mut sign_in_data.email)
+                                .hint_text("Email for a confirmation (optional)"),
+                        );
+                        let resubmitting = submitted.as_deref() == Some(question_responses);
+                        if resubmitting {
+                            ui.horizontal(|ui| {
+                                ui.label("Already submitted");
+                                if ui.button("Edit").clicked() {
+                                    *submitted = None;
                                 }
                             });
                         }
-                        if ui.button("SUBMIT").clicked() {
+                        if ui
+                            .add_enabled(!resubmitting, Button::new("SUBMIT"))
+                            .clicked()
+                        {
+                            ui.data().remove::<String>(full_message_id());
                             next_participation_state = Some(ParticipationState::Submitting {
                                 response: PollResponse {
                                     poll_id: key,
                                     user: user.to_string(),
-                                    responses: question_responses.clone(),
+                                    device_key: sign_in_data.device_key.clone(),
+                                    email: Some(sign_in_data.email.trim().to_string())
+                                        .filter(|email| !email.is_empty()),
+                                    responses: question_responses.to_vec(),
                                 },
                                 state: None,
                             });
                         }
-                    });
+                        match sign_in_data.local_save {
+                            LocalSave::Unchanged => {}
+                            LocalSave::Pending => {
+                                ui.weak("Saving…");
+                            }
+                            LocalSave::Saved => {
+                                ui.weak("✔ Saved locally").on_hover_text(
+                                    "Your answers are kept in this browser until you submit",
+                                );
+                            }
+                        }
+                    },
+                );
+                if *question_responses != previous_responses {
+                    sign_in_data.local_save = LocalSave::Pending;
+                }
             }
             ParticipationState::Submitting {
                 response,
@@ -209,27 +299,106 @@ impl ParticipationState {
             } => {
                 ui.label("Your response is being submitted...");
                 if let Some(submitter) = state {
-                    if let Some(response) = submitter.poll() {
-                        *stale = true;
-                        match response {
-                            PollSubmissionResult::Success => {
-                                next_participation_state =
-                                    Some(ParticipationState::SubmitConfirmation);
+                    match submitter.poll() {
+                        Some(Ok(result)) => {
+                            *stale = true;
+                            match result {
+                                PollSubmissionResult::Success => {
+                                    sign_in_data.local_save = LocalSave::Unchanged;
+                                    sign_in_data
+                                        .answered_polls
+                                        .entry(response.user.clone())
+                                        .or_default()
+                                        .insert(key);
+                                    next_participation_state =
+                                        Some(ParticipationState::SubmitConfirmation {
+                                            response: response.clone(),
+                                        });
+                                }
+                                PollSubmissionResult::Full { options } => {
+                                    ui.data().insert_temp(
+                                        full_message_id(),
+                                        format!("Sorry, {} filled up first.", options.join(", ")),
+                                    );
+                                    next_participation_state = Some(ParticipationState::SignedIn {
+                                        user: response.user.clone(),
+                                        question_responses: response.responses.clone(),
+                                        submitted: None,
+                                    });
+                                }
+                                PollSubmissionResult::NoSpotsLeft => {
+                                    ui.data().insert_temp(
+                                        full_message_id(),
+                                        "Sorry, every spot has been taken.".to_string(),
+                                    );
+                                    next_participation_state = Some(ParticipationState::SignedIn {
+                                        user: response.user.clone(),
+                                        question_responses: response.responses.clone(),
+                                        submitted: None,
+                                    });
+                                }
+                                PollSubmissionResult::EditWindowClosed => {
+                                    ui.data().insert_temp(
+                                        full_message_id(),
+                                        "Responses can't be changed anymore. \
+                                         Your earlier answers still count."
+                                            .to_string(),
+                                    );
+                                    next_participation_state = Some(ParticipationState::SignedIn {
+                                        user: response.user.clone(),
+                                        question_responses: response.responses.clone(),
+                                        submitted: None,
+                                    });
+                                }
+                                PollSubmissionResult::ConstraintViolated { reason } => {
+                                    ui.data().insert_temp(full_message_id(), reason);
+                                    next_participation_state = Some(ParticipationState::SignedIn {
+                                        user: response.user.clone(),
+                                        question_responses: response.responses.clone(),
+                                        submitted: None,
+                                    });
+                                }
+                                // Sent again on the next frame
+                                PollSubmissionResult::Error => *state = None,
                             }
-                            PollSubmissionResult::Error => {}
                         }
+                        Some(Err(e)) => {
+                            console_log!("Submitting to poll {key} failed: {e}");
+                            *state = None;
+                        }
+                        None => {}
                     }
                 } else {
-                    *state = Some(Submitter::new("submit", response.clone()));
+                    *state = Some(ApiCall::new(api::submit_response(
+                        response.clone(),
+                        password.map(str::to_string),
+                    )));
                 }
                 ui.ctx().request_repaint_after(Duration::from_millis(100));
             }
-            ParticipationState::SubmitConfirmation => {
+            ParticipationState::SubmitConfirmation { response } => {
                 ui.label("Your response has been submitted! Thanks!");
-                ui.label("To change your response, sign in with the exact same name again.");
+                match poll.dedup {
+                    DedupStrategy::ByName => {
+                        ui.label(
+                            "To change your response, sign in with the exact same name again.",
+                        );
+                    }
+                    DedupStrategy::ByDevice => {
+                        ui.label("To change your response, sign in again from this device.");
+                    }
+                    DedupStrategy::None => {}
+                }
                 if ui.button(SIGN_IN_TEXT).clicked() {
                     next_participation_state = Some(ParticipationState::SignIn);
                 }
+                if poll.dedup != DedupStrategy::None && ui.button("Back to My Answers").clicked() {
+                    next_participation_state = Some(ParticipationState::SignedIn {
+                        user: response.user.clone(),
+                        question_responses: response.responses.clone(),
+                        submitted: Some(response.responses.clone()),
+                    });
+                }
             }
         }
         if let Some(state) = next_participation_state {
@@ -237,3 +406,411 @@ impl ParticipationState {
         }
     }
 }
+
+/// Renders `poll`'s questions the way responders see them, answered into
+/// `question_responses`. `add_contents` goes below them, e.g. for the submit button.
+/// `shuffle_seed` picks the option order when the poll shuffles it.
+pub fn show_questions(
+    ui: &mut Ui,
+    poll: &Poll,
+    question_responses: &mut [FormResponse],
+    seats_taken: &[Option<Vec<u64>>],
+    shuffle_seed: u64,
+    add_contents: impl FnOnce(&mut Ui, &[FormResponse]),
+) {
+    let shown = shown_questions(&poll.questions, question_responses);
+    let shown_count = shown.iter().filter(|&&shown| shown).count();
+    let answered_count = question_responses
+        .iter()
+        .zip(&shown)
+        .filter(|(response, &shown)| shown && response.is_answered())
+        .count();
+    ui.add(
+        ProgressBar::new(answered_count as f32 / shown_count.max(1) as f32)
+            .text(format!("{answered_count} / {shown_count} answered")),
+    );
+    ScrollArea::vertical()
+        .id_source("participation_scroll")
+        .show(ui, |ui| {
+            // Phones get full width questions and bigger buttons
+            let compact = ui.available_width() < COMPACT_WIDTH;
+            let layout = if compact {
+                ui.spacing_mut().interact_size.y = TAP_TARGET_HEIGHT;
+                Layout::top_down_justified(Align::Min)
+            } else {
+                Layout::top_down(Align::Min)
+            };
+            ui.with_layout(layout, |ui| {
+                for (question_index, ((question, mut question_response), shown)) in poll
+                    .questions
+                    .iter()
+                    .zip(question_responses.iter_mut())
+                    .zip(shown)
+                    .enumerate()
+                {
+                    if !shown {
+                        *question_response = question.init_response();
+                        continue;
+                    }
+                    let seats = seats_taken.get(question_index).cloned().flatten();
+                    let order = poll.option_order(question_index, shuffle_seed);
+                    let full = |i: usize, selected: bool| {
+                        if selected {
+                            None
+                        } else {
+                            full_text(&question.form, seats.as_deref(), i)
+                        }
+                    };
+                    let group = ui.group(|ui| {
+                        let prompt = ui.label(&question.prompt);
+                        match (&question.form, &mut question_response) {
+                            (
+                                Form::OneOrNone { options, .. },
+                                FormResponse::ChooseOneOrNone(choice),
+                            ) => {
+                                let labels = widest_labels(options, full);
+                                option_grid(
+                                    ui,
+                                    question_index,
+                                    &labels,
+                                    &order,
+                                    compact,
+                                    |ui, i| {
+                                        let option = &options[i];
+                                        let selected = *choice == Some(Choice::Index(i as u8));
+                                        let full = full(i, selected);
+                                        let text = option_or_full_text(ui, option, &full);
+                                        let response = option_button(
+                                            ui,
+                                            text,
+                                            option,
+                                            selected,
+                                            full.is_none(),
+                                        );
+                                        if response.clicked() {
+                                            *choice = if selected {
+                                                None
+                                            } else {
+                                                Some(Choice::Index(i as u8))
+                                            };
+                                        }
+                                    },
+                                );
+                            }
+                            (
+                                Form::One {
+                                    options,
+                                    none_of_the_above,
+                                    ..
+                                },
+                                FormResponse::ChooseOne(choice),
+                            ) => {
+                                let mut labels = widest_labels(options, full);
+                                let mut order = order.clone();
+                                if *none_of_the_above {
+                                    // Always last, however the rest are shuffled
+                                    order.push(labels.len());
+                                    labels.push(NONE_OF_THE_ABOVE.to_string());
+                                }
+                                option_grid(
+                                    ui,
+                                    question_index,
+                                    &labels,
+                                    &order,
+                                    compact,
+                                    |ui, i| {
+                                        let Some(option) = options.get(i) else {
+                                            let selected = *choice == Choice::NoneOfTheAbove;
+                                            let text = option_text(ui, NONE_OF_THE_ABOVE);
+                                            if option_button(
+                                                ui,
+                                                text,
+                                                NONE_OF_THE_ABOVE,
+                                                selected,
+                                                true,
+                                            )
+                                            .clicked()
+                                            {
+                                                *choice = Choice::NoneOfTheAbove;
+                                            }
+                                            return;
+                                        };
+                                        let selected = *choice == Choice::Index(i as u8);
+                                        let full = full(i, selected);
+                                        let text = option_or_full_text(ui, option, &full);
+                                        let response = option_button(
+                                            ui,
+                                            text,
+                                            option,
+                                            selected,
+                                            full.is_none(),
+                                        );
+                                        if response.clicked() {
+                                            *choice = Choice::Index(i as u8);
+                                        }
+                                    },
+                                );
+                            }
+                            (
+                                Form::Multiple { options, .. },
+                                FormResponse::ChooseMultiple(choices),
+                            ) => {
+                                let labels = widest_labels(options, full);
+                                option_grid(
+                                    ui,
+                                    question_index,
+                                    &labels,
+                                    &order,
+                                    compact,
+                                    |ui, i| {
+                                        let option = &options[i];
+                                        let choice_index = choices
+                                            .iter()
+                                            .enumerate()
+                                            .filter(|(_, c)| *c.as_index().unwrap() as usize == i)
+                                            .map(|(i, _)| i)
+                                            .next();
+                                        let full = full(i, choice_index.is_some());
+                                        let text = option_or_full_text(ui, option, &full);
+                                        let response = option_button(
+                                            ui,
+                                            text,
+                                            option,
+                                            choice_index.is_some(),
+                                            full.is_none(),
+                                        );
+                                        if response.clicked() {
+                                            if let Some(index) = choice_index {
+                                                choices.remove(index);
+                                            } else {
+                                                choices.push(Choice::Index(i as u8));
+                                            };
+                                        }
+                                    },
+                                );
+                            }
+                            (Form::YesNoNone, FormResponse::ChooseOneOrNone(choice)) => {
+                                let answers = [
+                                    ("Yes", Choice::YesOrNo(true)),
+                                    ("No", Choice::YesOrNo(false)),
+                                    ("Maybe", Choice::Maybe),
+                                ];
+                                let labels = answers.each_ref().map(|(label, _)| label.to_string());
+                                option_grid(
+                                    ui,
+                                    question_index,
+                                    &labels,
+                                    &[0, 1, 2],
+                                    compact,
+                                    |ui, i| {
+                                        let (label, option) = &answers[i];
+                                        let selected = choice.as_ref() == Some(option);
+                                        if option_button(ui, *label, label, selected, true)
+                                            .clicked()
+                                        {
+                                            *choice =
+                                                if selected { None } else { Some(option.clone()) };
+                                        }
+                                    },
+                                );
+                            }
+                            (Form::YesNo, FormResponse::ChooseOne(choice)) => {
+                                toggle_ui(ui, choice.as_yes_or_no_mut().unwrap());
+                            }
+                            (
+                                Form::RankTopN { options, n },
+                                FormResponse::RankedChoice(ranking),
+                            ) => {
+                                ui.label(format!("Pick your top {n}, in order"));
+                                for i in order {
+                                    let option = &options[i];
+                                    let rank = ranking
+                                        .iter()
+                                        .position(|c| *c.as_index().unwrap() as usize == i);
+                                    let text = match rank {
+                                        Some(rank) => format!("{}. {option}", rank + 1),
+                                        None => option.to_string(),
+                                    };
+                                    ui.horizontal(|ui| {
+                                        let response = option_button(
+                                            ui,
+                                            text.clone(),
+                                            &text,
+                                            rank.is_some(),
+                                            rank.is_some() || ranking.len() < *n,
+                                        );
+                                        if response.clicked() {
+                                            toggle_pick(ranking, i);
+                                            return;
+                                        }
+                                        // Reordering picks without unpicking them
+                                        let Some(rank) = rank else { return };
+                                        if ui
+                                            .add_enabled(rank > 0, Button::new("⬆").small())
+                                            .on_hover_text("Move Up")
+                                            .clicked()
+                                        {
+                                            move_pick(ranking, rank, true);
+                                        }
+                                        if ui
+                                            .add_enabled(
+                                                rank + 1 < ranking.len(),
+                                                Button::new("⬇").small(),
+                                            )
+                                            .on_hover_text("Move Down")
+                                            .clicked()
+                                        {
+                                            move_pick(ranking, rank, false);
+                                        }
+                                    });
+                                }
+                            }
+                            (Form::Number, FormResponse::Number(value)) => {
+                                ui.add(DragValue::new(value).speed(0.1));
+                            }
+                            _ => unreachable!(),
+                        }
+                        prompt.id
+                    });
+                    group.response.labelled_by(group.inner);
+                }
+                add_contents(ui, question_responses);
+            });
+        });
+}
+
+/// Tapping option `i` picks it next, or unpicks it if it was already picked
+fn toggle_pick(ranking: &mut Vec<Choice>, i: usize) {
+    match ranking
+        .iter()
+        .position(|c| c.as_index() == Some(&(i as u8)))
+    {
+        Some(rank) => {
+            ranking.remove(rank);
+        }
+        None => ranking.push(Choice::Index(i as u8)),
+    }
+}
+
+/// Swaps the pick at `rank` with the one before it, or after it if not `earlier`
+fn move_pick(ranking: &mut [Choice], rank: usize, earlier: bool) {
+    let other = if earlier {
+        rank.checked_sub(1)
+    } else {
+        Some(rank + 1)
+    };
+    if let Some(other) = other.filter(|&other| other < ranking.len()) {
+        ranking.swap(rank, other);
+    }
+}
+
+/// A stable seed per device, so a responder sees the same option order every visit
+fn shuffle_seed(device_key: &str) -> u64 {
+    stable_hash(device_key.as_bytes())
+}
+
+fn full_message_id() -> Id {
+    Id::new("full_options_message")
+}
+
+/// Each option as it would read when full, which is the widest it gets
+fn widest_labels(options: &[String], full: impl Fn(usize, bool) -> Option<String>) -> Vec<String> {
+    options
+        .iter()
+        .enumerate()
+        .map(|(i, option)| match full(i, false) {
+            Some(full) => format!("{option} ({full})"),
+            None => option.clone(),
+        })
+        .collect()
+}
+
+/// Calls `add_button` with the index of each of `labels`, in `order`, laying the buttons
+/// out in as many columns as the widest label leaves room for. Compact layouts keep one
+/// full width button per line.
+fn option_grid(
+    ui: &mut Ui,
+    id_source: impl Hash,
+    labels: &[String],
+    order: &[usize],
+    compact: bool,
+    mut add_button: impl FnMut(&mut Ui, usize),
+) {
+    if compact {
+        order.iter().for_each(|&i| add_button(ui, i));
+        return;
+    }
+    let font_id = TextStyle::Button.resolve(ui.style());
+    let widest = labels
+        .iter()
+        .map(|label| {
+            ui.fonts()
+                .layout_no_wrap(label.clone(), font_id.clone(), Color32::WHITE)
+                .size()
+                .x
+        })
+        .fold(0.0, f32::max);
+    let spacing = ui.spacing();
+    let button_width = widest + 2.0 * spacing.button_padding.x + spacing.item_spacing.x;
+    let columns = ((ui.available_width() / button_width) as usize).clamp(1, labels.len().max(1));
+    Grid::new(("option_grid", id_source)).show(ui, |ui| {
+        for (position, &i) in order.iter().enumerate() {
+            add_button(ui, i);
+            if (position + 1) % columns == 0 {
+                ui.end_row();
+            }
+        }
+    });
+}
+
+/// "Full — taken/capacity" if no one else can pick option `i`
+fn full_text(form: &Form, seats_taken: Option<&[u64]>, i: usize) -> Option<String> {
+    let capacity = form.capacities().get(i).copied().flatten()?;
+    let taken = *seats_taken?.get(i)?;
+    (taken >= capacity).then(|| format!("Full — {taken}/{capacity}"))
+}
+
+fn option_or_full_text(ui: &Ui, option: &str, full: &Option<String>) -> WidgetText {
+    match full {
+        Some(full) => format!("{option} ({full})").into(),
+        None => option_text(ui, option),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    fn picks_can_be_reordered() {
+        let mut ranking = Vec::new();
+        for i in [2, 0, 1] {
+            toggle_pick(&mut ranking, i);
+        }
+        assert_eq!(
+            ranking,
+            [Choice::Index(2), Choice::Index(0), Choice::Index(1)]
+        );
+        move_pick(&mut ranking, 2, true);
+        assert_eq!(
+            ranking,
+            [Choice::Index(2), Choice::Index(1), Choice::Index(0)]
+        );
+        move_pick(&mut ranking, 0, false);
+        assert_eq!(
+            ranking,
+            [Choice::Index(1), Choice::Index(2), Choice::Index(0)]
+        );
+        // Nowhere to go past either end
+        move_pick(&mut ranking, 0, true);
+        move_pick(&mut ranking, 2, false);
+        assert_eq!(
+            ranking,
+            [Choice::Index(1), Choice::Index(2), Choice::Index(0)]
+        );
+        // Unpicking closes the gap
+        toggle_pick(&mut ranking, 2);
+        assert_eq!(ranking, [Choice::Index(1), Choice::Index(0)]);
+    }
+}
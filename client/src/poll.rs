@@ -1,10 +1,15 @@
 use crate::{
-    app::SignInData, misc::UrlExt, new_poll::NewPoll, participation::ParticipationState,
-    results_ui::ResultsUi, retrieve::RetrievingState,
+    app::SignInData,
+    misc::UrlExt,
+    new_poll::NewPoll,
+    participation::{ParticipationState, Resume},
+    results_ui::{skeleton_block, Palette, ResultsUi},
+    retrieve::RetrievingState,
 };
 use areyougoing_shared::Poll;
+use chrono::Utc;
 use derivative::Derivative;
-use egui::Ui;
+use egui::{vec2, Color32, Key, RichText, TextEdit, TextStyle, Ui};
 use serde::{Deserialize, Serialize};
 use std::time::Duration;
 use url::Url;
@@ -20,12 +25,21 @@ pub enum PollState {
     },
     Retrieving {
         key: u64,
+        /// For polls that need one to view, once it's been entered
+        #[serde(skip)]
+        password: Option<String>,
+        /// A signed in user and their answers so far, to carry on with once it's found
+        #[serde(skip)]
+        resume: Option<Resume>,
         #[serde(skip)]
         #[derivative(PartialEq = "ignore")]
         state: RetrievingState,
     },
     Found {
         key: u64,
+        /// The view password the poll was opened with, sent with everything about it
+        #[serde(skip)]
+        password: Option<String>,
         poll: Poll,
         participation_state: ParticipationState,
         results_ui: ResultsUi,
@@ -33,6 +47,13 @@ pub enum PollState {
     NotFound {
         key: u64,
     },
+    PasswordRequired {
+        key: u64,
+        #[serde(skip)]
+        password_entry: String,
+        /// Whether a password was already tried and didn't work
+        wrong: bool,
+    },
 }
 
 impl Default for PollState {
@@ -42,12 +63,29 @@ impl Default for PollState {
 }
 
 impl PollState {
+    /// A new poll to edit, starting from `poll`'s setup, e.g. for a weekly event
+    pub fn cloned_from(poll: &Poll) -> Self {
+        PollState::NewPoll {
+            state: NewPoll::Creating {
+                ui_data: Default::default(),
+                ui_tab: Default::default(),
+            },
+            poll: Poll {
+                expiration: None,
+                edit_deadline: None,
+                status: Default::default(),
+                ..poll.clone()
+            },
+        }
+    }
+
     pub fn process(
         &mut self,
         ui: &mut Ui,
         next_poll_state: &mut Option<PollState>,
         original_url: &Option<Url>,
         sign_in_data: &mut SignInData,
+        palette: Palette,
     ) {
         ui.vertical_centered(|ui| match self {
             PollState::None => {
@@ -60,52 +98,153 @@ impl PollState {
                 });
             }
             PollState::NewPoll { poll, state } => {
-                state.process(ui, poll, original_url);
+                state.process(
+                    ui,
+                    poll,
+                    original_url,
+                    palette,
+                    &mut sign_in_data.editor_secrets,
+                );
             }
-            PollState::Retrieving { key, ref mut state } => {
-                ui.label(format!("Retreiving Poll #{key}"));
-                state.process(next_poll_state, *key);
+            PollState::Retrieving {
+                key,
+                password,
+                resume,
+                ref mut state,
+            } => {
+                // Roughly the shape of a poll's title and first questions
+                let width = ui.available_width();
+                let line_height = ui.text_style_height(&TextStyle::Body);
+                skeleton_block(
+                    ui,
+                    vec2(width / 2.0, ui.text_style_height(&TextStyle::Heading)),
+                )
+                .on_hover_text(format!("Retrieving Poll #{key}"));
+                for _ in 0..3 {
+                    ui.add_space(line_height);
+                    skeleton_block(ui, vec2(width, line_height * 3.0));
+                }
+                state.process(next_poll_state, *key, password, resume);
                 // Make sure the UI keeps updating in order to keep polling the fetch process
                 ui.ctx().request_repaint_after(Duration::from_millis(100));
             }
             PollState::Found {
                 key,
+                password,
                 poll,
                 ref mut participation_state,
                 ref mut results_ui,
             } => {
-                ui.heading(format!("{} (#{key})", poll.title));
+                if let Some(theme) = &poll.theme {
+                    let [r, g, b] = theme.accent_color;
+                    let accent_color = Color32::from_rgb(r, g, b);
+                    ui.visuals_mut().selection.bg_fill = accent_color;
+                    ui.label(
+                        RichText::new(format!("{} {} (#{key})", theme.header_emoji, poll.title))
+                            .heading()
+                            .color(accent_color),
+                    );
+                } else {
+                    ui.heading(format!("{} (#{key})", poll.title));
+                }
                 ui.label(&poll.description);
+                show_countdowns(ui, poll);
+                if ui
+                    .small_button("Clone Poll")
+                    .on_hover_text("Start a new poll with these questions")
+                    .clicked()
+                {
+                    *next_poll_state = Some(PollState::cloned_from(poll));
+                }
                 ui.separator();
-                results_ui.process(ui, poll, *key);
-                participation_state.process(ui, sign_in_data, *key, poll, &mut results_ui.stale);
+                results_ui.process(
+                    ui,
+                    poll,
+                    *key,
+                    password.as_deref(),
+                    sign_in_data.editor_secrets.get(key).map(String::as_str),
+                    palette,
+                );
+                if results_ui.poll_changed {
+                    *next_poll_state = Some(PollState::Retrieving {
+                        key: *key,
+                        password: password.clone(),
+                        resume: participation_state.resume(poll),
+                        state: Default::default(),
+                    });
+                }
+                participation_state.process(
+                    ui,
+                    sign_in_data,
+                    *key,
+                    password.as_deref(),
+                    poll,
+                    results_ui,
+                );
             }
             PollState::NotFound { key } => {
                 ui.label(format!("No poll with ID #{key} was found 😥"));
             }
-        });
-        if let Some(mut state) = next_poll_state.take() {
-            {
-                use PollState::*;
-                match &mut state {
-                    NewPoll { .. } => {
-                        original_url.with_query(Option::None).push_to_window();
-                    }
-                    Found {
-                        participation_state:
-                            ParticipationState::SignedIn {
-                                ref mut question_responses,
-                                ..
-                            },
-                        ..
-                    } => {
-                        // Temporary for debugging, with changing polls as we go
-                        *question_responses = Default::default();
-                    }
-                    _ => {}
+            PollState::PasswordRequired {
+                key,
+                password_entry,
+                wrong,
+            } => {
+                ui.label(format!("Poll #{key} needs a password to view"));
+                if *wrong {
+                    ui.colored_label(ui.visuals().warn_fg_color, "That password didn't work");
                 }
+                let response = ui.add(
+                    TextEdit::singleline(password_entry)
+                        .password(true)
+                        .hint_text("Password"),
+                );
+                let entered = response.lost_focus() && ui.input().key_pressed(Key::Enter);
+                if ui.button("Open").clicked() || entered {
+                    *next_poll_state = Some(PollState::Retrieving {
+                        key: *key,
+                        password: Some(password_entry.clone()),
+                        resume: None,
+                        state: Default::default(),
+                    });
+                }
+            }
+        });
+        if let Some(state) = next_poll_state.take() {
+            if let PollState::NewPoll { .. } = state {
+                original_url.with_query(Option::None).push_to_window();
             }
             *self = state;
         }
     }
 }
+
+/// How long until each of the poll's deadlines, ticking while any are ahead
+fn show_countdowns(ui: &mut Ui, poll: &Poll) {
+    let now = Utc::now();
+    for (deadline, ahead, passed) in [
+        (poll.expiration, "Closes in", "Closed"),
+        (poll.edit_deadline, "Edits lock in", "Edits locked"),
+    ] {
+        let Some(deadline) = deadline else { continue };
+        if deadline > now {
+            ui.label(format!("{ahead} {}", format_remaining(deadline - now)));
+            ui.ctx().request_repaint_after(Duration::from_secs(1));
+        } else {
+            ui.weak(passed);
+        }
+    }
+}
+
+/// The two largest units, like "2d 3h" or "5m 12s"
+fn format_remaining(remaining: chrono::Duration) -> String {
+    let seconds = remaining.num_seconds();
+    let (days, hours, minutes) = (seconds / 86400, seconds / 3600 % 24, seconds / 60 % 60);
+    if days > 0 {
+        format!("{days}d {hours}h")
+    } else if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else {
+        format!("{minutes}m {}s", seconds % 60)
+    }
+}
@@ -1,15 +1,31 @@
 use crate::{
-    misc::{Submitter, UiExt},
+    api::{self, ApiCall},
+    misc::{
+        console_log, download_file, notification_permission, prefers_reduced_motion,
+        request_notification_permission, show_markup, show_notification, UiExt,
+    },
+    participation::COMPACT_WIDTH,
     time::Instant,
 };
-use areyougoing_shared::{Poll, PollProgress, Progress, ProgressReportResult, Requirement};
+use areyougoing_shared::{
+    format_count, Metric, Poll, PollProgress, Progress, ProgressReportResult, ReactRequest,
+    ReactResult, Requirement, REACTIONS,
+};
+use chrono::Utc;
 use derivative::Derivative;
 use egui::{
-    pos2, vec2, Align, Color32, Frame, Label, Layout, Rect, RichText, ScrollArea, Stroke,
-    TextStyle, Ui,
+    pos2, vec2, Align, Button, Checkbox, CollapsingHeader, Color32, Frame, Id, Label, LayerId,
+    Layout, Order, ProgressBar, Rect, Response, Rgba, RichText, ScrollArea, Sense, Stroke,
+    TextStyle, Ui, Vec2,
 };
+use enum_iterator::Sequence;
 use serde::{Deserialize, Serialize};
-use std::time::Duration;
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+    time::Duration,
+};
+use web_sys::NotificationPermission;
 
 #[derive(Derivative)]
 #[derivative(PartialEq)]
@@ -20,9 +36,27 @@ pub struct ResultsUi {
     pub last_fetch: Option<Instant>,
     #[serde(skip)]
     #[derivative(PartialEq = "ignore")]
-    pub poll_progress_fetch: Option<Submitter<u64, ProgressReportResult>>,
+    pub poll_progress_fetch: Option<ApiCall<ProgressReportResult>>,
+    /// Hash of the last fetched progress, to notice when nothing is changing
+    #[serde(skip)]
+    #[derivative(PartialEq = "ignore")]
+    pub last_progress_hash: Option<u64>,
+    #[serde(skip)]
+    #[derivative(PartialEq = "ignore")]
+    pub last_change: Option<Instant>,
+    /// Set when the server's poll no longer matches this one, e.g. after it was edited, so
+    /// it gets retrieved again
+    #[serde(skip)]
+    #[derivative(PartialEq = "ignore")]
+    pub poll_changed: bool,
     pub poll_progress: Option<PollProgress>,
     pub stale: bool,
+    /// Indices of results to show a browser notification for once they're met
+    #[serde(default)]
+    pub subscribed_results: Vec<usize>,
+    #[serde(skip)]
+    #[derivative(PartialEq = "ignore")]
+    pub reaction: Option<ApiCall<ReactResult>>,
     pub ui_state: ResultsUiState,
 }
 
@@ -31,8 +65,13 @@ impl Default for ResultsUi {
         Self {
             poll_progress_fetch: None,
             last_fetch: None,
+            last_progress_hash: None,
+            last_change: None,
+            poll_changed: false,
             poll_progress: None,
             stale: true,
+            subscribed_results: Vec::new(),
+            reaction: None,
             ui_state: Default::default(),
         }
     }
@@ -47,19 +86,471 @@ pub struct ResultsUiState {
     metrics_heading_rect: Option<Rect>,
     results_heading_rect: Option<Rect>,
     bottom: Option<f32>,
+    /// Results that were just met while being watched, with when (in egui time) that was seen
+    #[serde(skip)]
+    celebrations: Vec<(usize, f64)>,
+}
+
+const ANIMATION_TIME: f32 = 0.4;
+const CELEBRATION_TIME: f32 = 1.5;
+const CONFETTI_PIECES: usize = 24;
+
+impl ResultsUiState {
+    /// Flashes result `i` (at `rect`) and throws confetti from it, if it was just met.
+    /// Confetti is skipped for users who prefer reduced motion.
+    fn show_celebration(&mut self, ui: &Ui, i: usize, rect: Rect) {
+        let Some(index) = self
+            .celebrations
+            .iter()
+            .position(|&(result, _)| result == i)
+        else {
+            return;
+        };
+        let elapsed = (ui.input().time - self.celebrations[index].1) as f32;
+        if elapsed > CELEBRATION_TIME {
+            self.celebrations.remove(index);
+            return;
+        }
+        let fade = 1.0 - elapsed / CELEBRATION_TIME;
+        let accent = ui.style().visuals.selection.bg_fill;
+        let painter = ui
+            .ctx()
+            .layer_painter(LayerId::new(Order::Foreground, Id::new("celebration")));
+        painter.rect_filled(rect, 3.0, accent.linear_multiply(fade * 0.6));
+        if !prefers_reduced_motion() {
+            const GRAVITY: f32 = 400.0;
+            const COLORS: [Color32; 4] = [
+                Color32::GOLD,
+                Color32::LIGHT_BLUE,
+                Color32::LIGHT_RED,
+                Color32::LIGHT_GREEN,
+            ];
+            for piece in 0..CONFETTI_PIECES {
+                // Spread pieces evenly around the circle, mostly upward, at a few speeds
+                let angle = piece as f32 * 2.4;
+                let speed = 120.0 + (piece * 37 % 80) as f32;
+                let velocity = vec2(angle.cos(), angle.sin() - 1.0) * speed;
+                let position = rect.center()
+                    + velocity * elapsed
+                    + vec2(0.0, GRAVITY * elapsed * elapsed / 2.0);
+                painter.rect_filled(
+                    Rect::from_center_size(position, vec2(5.0, 5.0)),
+                    1.0,
+                    COLORS[piece % COLORS.len()].linear_multiply(fade),
+                );
+            }
+        }
+        ui.ctx().request_repaint();
+    }
+}
+
+/// Back off from polling the server while the counts sit still
+fn refresh_interval(last_change: &Option<Instant>) -> Duration {
+    let quiet_for = last_change.as_ref().map(|t| t.elapsed()).unwrap_or_default();
+    if quiet_for < Duration::from_secs(15) {
+        Duration::from_secs_f32(1.5)
+    } else if quiet_for < Duration::from_secs(60) {
+        Duration::from_secs(5)
+    } else {
+        Duration::from_secs(15)
+    }
 }
 
-#[inline]
-fn choose_color(met: bool) -> Color32 {
+fn progress_hash(progress: &PollProgress) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    progress.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Eases from the previously shown value to the new one. egui shows the target value
+/// immediately the first time an id is animated, so nothing animates on first load.
+fn animated_count(ui: &Ui, metric_index: usize, count: u64) -> String {
+    let shown = ui.ctx().animate_value_with_time(
+        Id::new(("metric_progress", metric_index)),
+        count as f32,
+        ANIMATION_TIME,
+    );
+    format_count(shown.round() as u64, true)
+}
+
+fn show_progress(ui: &mut Ui, metric_index: usize, progress: &Progress) {
+    match progress {
+        Progress::Count(count) => {
+            ui.label(animated_count(ui, metric_index, *count))
+                .on_hover_text(progress.render());
+        }
+        Progress::Histogram(counts) => {
+            const BAR_WIDTH: f32 = 8.0;
+            let height = ui.text_style_height(&TextStyle::Body);
+            let (rect, response) = ui.allocate_exact_size(
+                vec2(BAR_WIDTH * counts.len() as f32, height),
+                Sense::hover(),
+            );
+            let max = counts.iter().max().copied().unwrap_or(0).max(1);
+            let color = ui.visuals().strong_text_color();
+            for (i, count) in counts.iter().enumerate() {
+                let bar_height = height * *count as f32 / max as f32;
+                let left = rect.left() + BAR_WIDTH * i as f32;
+                let bar = Rect::from_min_max(
+                    pos2(left + 1.0, rect.bottom() - bar_height),
+                    pos2(left + BAR_WIDTH - 1.0, rect.bottom()),
+                );
+                ui.painter().rect_filled(bar, 1.0, color);
+            }
+            response.on_hover_text(progress.render());
+        }
+        Progress::Winner { tiebreak, .. } => {
+            let response = ui.label(format!("Leading: {}", progress.render()));
+            if let Some(tiebreak) = tiebreak {
+                response.on_hover_text(format!("Tie broken by rule: {tiebreak}"));
+            }
+        }
+        Progress::Standings(standings) => {
+            let response = ui.label(format!("Leading: {}", progress.render()));
+            if standings.len() > 1 {
+                response.on_hover_text(
+                    standings
+                        .iter()
+                        .enumerate()
+                        .map(|(i, (label, score))| format!("{}. {label} ({score})", i + 1))
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                );
+            }
+        }
+    }
+}
+
+/// Colors for met and unmet results. Blue and orange by default, since red and green
+/// look alike to many color-blind people.
+#[derive(Deserialize, Serialize, Sequence, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Palette {
+    #[default]
+    BlueOrange,
+    GreenRed,
+}
+
+impl Palette {
+    pub fn label(self) -> &'static str {
+        match self {
+            Palette::BlueOrange => "Blue / Orange",
+            Palette::GreenRed => "Green / Red",
+        }
+    }
+
+    #[inline]
+    fn color(self, met: bool) -> Color32 {
+        match (self, met) {
+            (Palette::BlueOrange, true) => Color32::from_rgb(0, 90, 160),
+            (Palette::BlueOrange, false) => Color32::from_rgb(170, 85, 0),
+            (Palette::GreenRed, true) => Color32::DARK_GREEN,
+            (Palette::GreenRed, false) => Color32::DARK_RED,
+        }
+    }
+}
+
+/// Shown alongside the color, so met and unmet don't rely on color alone
+fn met_icon(met: bool) -> &'static str {
     if met {
-        Color32::DARK_GREEN
+        "✓"
+    } else {
+        "✗"
+    }
+}
+
+/// Plain text summary for pasting into a group chat, e.g.
+/// "Party! — Yes to Are you going? (12); The party happens ✅"
+fn summary_text(poll: &Poll, poll_progress: &PollProgress) -> String {
+    let metrics = poll
+        .metric_trackers
+        .iter()
+        .zip(poll_progress.metric_progresses.iter())
+        .filter_map(|(metric_tracker, progress)| {
+            progress.as_ref().map(|progress| {
+                format!(
+                    "{} ({})",
+                    metric_tracker.metric.render(&poll.questions),
+                    progress.render()
+                )
+            })
+        })
+        .collect::<Vec<_>>();
+    let results = poll
+        .results
+        .iter()
+        .zip(poll_progress.result_states.iter())
+        .map(|(result, state)| {
+            format!(
+                "{} {}",
+                result.desc,
+                if state.overall_met { "✅" } else { "❌" }
+            )
+        })
+        .collect::<Vec<_>>();
+    let sections = [metrics, results]
+        .iter()
+        .filter(|section| !section.is_empty())
+        .map(|section| section.join(", "))
+        .collect::<Vec<_>>();
+    if sections.is_empty() {
+        poll.title.clone()
+    } else {
+        format!("{} — {}", poll.title, sections.join("; "))
+    }
+}
+
+/// Stays the same for a poll so re-downloading updates the calendar entry instead of duplicating it
+fn calendar_uid(key: u64) -> String {
+    format!("poll-{key}@areyougoing")
+}
+
+/// Longest text shown in a results column before it's cut off
+const MAX_LABEL_CHARS: usize = 120;
+
+/// A wrapped label that cuts off overly long text with an ellipsis, showing all of it on hover
+fn clipped_label(ui: &mut Ui, text: &str, strong: bool) -> Response {
+    let clipped = text.chars().count() > MAX_LABEL_CHARS;
+    let mut rich_text = if clipped {
+        RichText::new(format!(
+            "{}…",
+            text.chars().take(MAX_LABEL_CHARS - 1).collect::<String>()
+        ))
+    } else {
+        RichText::new(text)
+    };
+    if strong {
+        rich_text = rich_text.strong();
+    }
+    let response = ui.add(Label::new(rich_text).wrap(true));
+    if clipped {
+        response.on_hover_text(text)
+    } else {
+        response
+    }
+}
+
+/// A grey stand-in for content that's still loading
+pub fn skeleton_block(ui: &mut Ui, size: Vec2) -> Response {
+    let (rect, response) = ui.allocate_exact_size(size, Sense::hover());
+    ui.painter()
+        .rect_filled(rect, 3.0, ui.style().visuals.widgets.inactive.bg_fill);
+    response
+}
+
+/// Placeholder metric and result cards for the first fetch, sized like the real ones so
+/// nothing jumps when the progress arrives
+fn show_skeleton(ui: &mut Ui, poll: &Poll, column_widths: &[f32]) {
+    // A card is a line of text inside the results frame's margin and stroke
+    let card_height = ui.text_style_height(&TextStyle::Body) + 4.0;
+    ui.unequal_columns(column_widths, |columns| {
+        for (column, heading, cards) in [
+            (0, "Metrics", poll.metric_trackers.len()),
+            (2, "Results", poll.results.len()),
+        ] {
+            if cards == 0 {
+                continue;
+            }
+            let ui = &mut columns[column];
+            ui.vertical_centered(|ui| {
+                ui.label(RichText::new(heading).underline().strong());
+            });
+            for _ in 0..cards {
+                skeleton_block(ui, vec2(ui.available_width(), card_height));
+            }
+        }
+    });
+}
+
+fn results_banner(ui: &mut Ui, text: String, met: bool, palette: Palette) {
+    Frame::none()
+        .inner_margin(vec2(6.0, 2.0))
+        .rounding(3.)
+        .fill(palette.color(met))
+        .show(ui, |ui| {
+            ui.label(RichText::new(text).strong().heading());
+        });
+}
+
+/// "17 / 25 spots filled", turning toward the warning color as the poll fills up
+fn show_capacity_gauge(ui: &mut Ui, response_count: u64, max_responses: u64) {
+    let filled = (response_count as f32 / max_responses as f32).min(1.0);
+    let text = format!(
+        "{} / {} spots filled",
+        format_count(response_count, false),
+        format_count(max_responses, false)
+    );
+    ui.scope(|ui| {
+        let visuals = ui.visuals_mut();
+        visuals.selection.bg_fill = egui::lerp(
+            Rgba::from(visuals.selection.bg_fill)..=Rgba::from(visuals.warn_fg_color),
+            filled,
+        )
+        .into();
+        ui.add(ProgressBar::new(filled).text(text));
+    });
+}
+
+/// Each result's bottom line, e.g. "✓ Party's on  12 / ≥10", above the full breakdown
+fn show_summary_card(ui: &mut Ui, poll: &Poll, poll_progress: &PollProgress, palette: Palette) {
+    Frame::group(ui.style()).show(ui, |ui| {
+        for (result, state) in poll.results.iter().zip(&poll_progress.result_states) {
+            let (metric_index, target) = match result.requirements[0] {
+                Requirement::AtLeast {
+                    metric_index,
+                    minimum,
+                } => (metric_index, format!("≥{}", format_count(minimum, false))),
+                Requirement::Exactly {
+                    metric_index,
+                    count,
+                } => (metric_index, format!("={}", format_count(count, false))),
+            };
+            let progress = poll_progress
+                .metric_progresses
+                .get(metric_index as usize)
+                .cloned()
+                .flatten();
+            ui.horizontal_wrapped(|ui| {
+                Frame::none()
+                    .inner_margin(vec2(4.0, 1.0))
+                    .rounding(3.)
+                    .fill(palette.color(state.overall_met))
+                    .show(ui, |ui| {
+                        ui.colored_label(
+                            ui.style().visuals.strong_text_color(),
+                            format!("{} {}", met_icon(state.overall_met), result.desc),
+                        );
+                    });
+                if let Some(progress) = progress {
+                    ui.strong(format!("{} / {target}", progress.render()));
+                }
+            });
+        }
+    });
+}
+
+/// How everyone answered the questions with public results
+fn show_breakdowns(ui: &mut Ui, poll: &Poll, poll_progress: &PollProgress) {
+    for (question, counts) in poll
+        .questions
+        .iter()
+        .zip(poll_progress.question_breakdowns.iter())
+    {
+        let Some(counts) = counts else {
+            continue;
+        };
+        let total = counts.iter().sum::<u64>().max(1);
+        ui.label(RichText::new(&question.prompt).strong());
+        for ((label, _), count) in question.form.choices().iter().zip(counts) {
+            ui.add(
+                ProgressBar::new(*count as f32 / total as f32).text(format!("{label}: {count}")),
+            );
+        }
+        ui.separator();
+    }
+}
+
+/// Who gave each answer, e.g. "Yes: Alice, Bob", for polls that show it
+fn show_responder_names(ui: &mut Ui, poll: &Poll, poll_progress: &PollProgress) {
+    let (Some(question), Some(names)) = (
+        poll.show_responder_names
+            .and_then(|index| poll.questions.get(index)),
+        &poll_progress.responder_names,
+    ) else {
+        return;
+    };
+    ui.label(RichText::new(&question.prompt).strong());
+    for ((label, _), names) in question.form.choices().iter().zip(names) {
+        if !names.is_empty() {
+            ui.label(format!("{label}: {}", names.join(", ")));
+        }
+    }
+    ui.separator();
+}
+
+/// "🥇 Alice  🥈 Bob  🥉 Carol", for polls that show who responded first
+fn show_first_responders(ui: &mut Ui, poll_progress: &PollProgress) {
+    let Some(first_responders) = &poll_progress.first_responders else {
+        return;
+    };
+    if first_responders.is_empty() {
+        return;
+    }
+    let podium = ["🥇", "🥈", "🥉"]
+        .iter()
+        .zip(first_responders)
+        .map(|(medal, name)| format!("{medal} {name}"))
+        .collect::<Vec<_>>();
+    ui.label(podium.join("  "))
+        .on_hover_text("First to respond");
+}
+
+fn show_subscriptions(ui: &mut Ui, poll: &Poll, subscribed_results: &mut Vec<usize>) {
+    let denied = notification_permission() == NotificationPermission::Denied;
+    if denied {
+        ui.label("Notifications are blocked for this site in your browser settings.");
     } else {
-        Color32::DARK_RED
+        ui.label("Notify me when...");
+    }
+    for (i, result) in poll.results.iter().enumerate() {
+        let mut subscribed = subscribed_results.contains(&i);
+        let response = ui.add_enabled(!denied, Checkbox::new(&mut subscribed, &result.desc));
+        if response.changed() {
+            if subscribed {
+                request_notification_permission();
+                subscribed_results.push(i);
+            } else {
+                subscribed_results.retain(|&subscribed| subscribed != i);
+            }
+        }
     }
 }
 
 impl ResultsUi {
-    pub fn process(&mut self, ui: &mut Ui, poll: &mut Poll, key: u64) {
+    pub fn process(
+        &mut self,
+        ui: &mut Ui,
+        poll: &mut Poll,
+        key: u64,
+        password: Option<&str>,
+        editor_secret: Option<&str>,
+        palette: Palette,
+    ) {
+        self.show(ui, poll, Some(key), palette);
+        self.show_reactions(ui, key, password);
+        self.fetch(ui, poll, key, password, editor_secret);
+    }
+
+    /// Draws the last progress. Only live results, for the poll at `key`, get refresh,
+    /// notification and calendar controls.
+    pub fn show(&mut self, ui: &mut Ui, poll: &Poll, key: Option<u64>, palette: Palette) {
+        let live = key.is_some();
+        if let (Some(max_responses), Some(poll_progress)) =
+            (poll.max_responses, &self.poll_progress)
+        {
+            show_capacity_gauge(ui, poll_progress.response_count, max_responses);
+        }
+        if matches!(&self.poll_progress, Some(progress) if progress.hidden) {
+            match poll.min_results_threshold {
+                Some(threshold) if !poll.reveal_when_closed || poll.is_closed(Utc::now()) => {
+                    ui.label(format!("Results available after {threshold} responses."));
+                }
+                _ => {
+                    ui.label("Results hidden until the poll closes.");
+                }
+            }
+            return;
+        }
+        if let (Some(quorum), Some(poll_progress)) = (poll.quorum, &self.poll_progress) {
+            let text = if poll_progress.quorum_met {
+                format!("Quorum of {quorum} reached ✅")
+            } else {
+                format!("Quorum of {quorum} not reached ❌")
+            };
+            results_banner(ui, text, poll_progress.quorum_met, palette);
+        }
+        if let Some(announcement) = poll.announcement.as_ref().filter(|a| !a.trim().is_empty()) {
+            Frame::group(ui.style()).show(ui, |ui| show_markup(ui, announcement));
+        }
         let ui_width = ui.available_width();
         let column_widths = match (poll.metric_trackers.is_empty(), poll.results.is_empty()) {
             (false, false) => {
@@ -85,68 +576,186 @@ impl ResultsUi {
                 .rounding(3.)
                 .fill(ui.style().visuals.widgets.active.bg_fill);
 
-            ui.unequal_columns(&column_widths, |columns| {
-                const UNDERHEADING_SPACE: f32 = 2.0;
-                let mut size = columns[0].available_size();
-                size.y = 0.;
-
-                if !poll.metric_trackers.is_empty() {
-                    let ui = &mut columns[0];
-                    let heading_rect = match (
-                        self.ui_state.metric_rects.first(),
-                        self.ui_state.metrics_heading_rect,
-                    ) {
-                        (Some(top_metric_rect), Some(previous_heading_rect)) => Rect {
-                            min: pos2(
-                                top_metric_rect.center().x - previous_heading_rect.width() / 2.0,
-                                ui.cursor().top(),
-                            ),
-                            max: pos2(
-                                top_metric_rect.center().x
-                                    + previous_heading_rect.width() / 2.0
-                                    + 1.0,
-                                f32::INFINITY,
-                            ),
-                        },
-                        _ => ui.available_rect_before_wrap(),
-                    };
-                    ui.allocate_ui_at_rect(heading_rect, |ui| {
-                        ui.with_layout(Layout::top_down(Align::Center), |ui| {
-                            let response = ui.label(RichText::new("Metrics").underline().strong());
-                            self.ui_state.metrics_heading_rect = Some(response.rect);
+            // Phones get the bottom line, with the full layout a tap away
+            let collapse_details = !poll.results.is_empty() && ui_width < COMPACT_WIDTH;
+            if !poll.results.is_empty() {
+                show_summary_card(ui, poll, poll_progress, palette);
+            }
+            let mut show_details = |ui: &mut Ui| {
+                ui.unequal_columns(&column_widths, |columns| {
+                    const UNDERHEADING_SPACE: f32 = 2.0;
+                    let mut size = columns[0].available_size();
+                    size.y = 0.;
+
+                    if !poll.metric_trackers.is_empty() {
+                        let ui = &mut columns[0];
+                        let heading_rect = match (
+                            self.ui_state.metric_rects.first(),
+                            self.ui_state.metrics_heading_rect,
+                        ) {
+                            (Some(top_metric_rect), Some(previous_heading_rect)) => Rect {
+                                min: pos2(
+                                    top_metric_rect.center().x
+                                        - previous_heading_rect.width() / 2.0,
+                                    ui.cursor().top(),
+                                ),
+                                max: pos2(
+                                    top_metric_rect.center().x
+                                        + previous_heading_rect.width() / 2.0
+                                        + 1.0,
+                                    f32::INFINITY,
+                                ),
+                            },
+                            _ => ui.available_rect_before_wrap(),
+                        };
+                        ui.allocate_ui_at_rect(heading_rect, |ui| {
+                            ui.with_layout(Layout::top_down(Align::Center), |ui| {
+                                let response =
+                                    ui.label(RichText::new("Metrics").underline().strong());
+                                self.ui_state.metrics_heading_rect = Some(response.rect);
+                            });
                         });
-                    });
 
-                    let scroll_max_height = ui.available_height() / 3.0;
-                    ui.add_space(UNDERHEADING_SPACE);
+                        let scroll_max_height = ui.available_height() / 3.0;
+                        ui.add_space(UNDERHEADING_SPACE);
 
-                    self.ui_state.progress_rects.clear();
-                    ScrollArea::vertical()
-                        .id_source("metrics_scroll")
-                        .max_height(scroll_max_height)
-                        .show(ui, |ui| {
-                            for (i, (metric_tracker, progress)) in poll
-                                .metric_trackers
-                                .iter()
-                                .zip(poll_progress.metric_progresses.iter())
-                                .enumerate()
-                            {
-                                ui.allocate_ui_with_layout(
-                                    size,
-                                    Layout::right_to_left(Align::Center),
-                                    |ui| {
-                                        let style = ui.style_mut();
-                                        style.spacing.item_spacing.x = 2.0;
-                                        let mut progress_rect = None;
-                                        if let Some(progress) = progress {
-                                            if let Some(metric_rect) =
-                                                self.ui_state.metric_rects.get(i)
+                        self.ui_state.progress_rects.clear();
+                        ScrollArea::vertical()
+                            .id_source("metrics_scroll")
+                            .max_height(scroll_max_height)
+                            .show(ui, |ui| {
+                                for (i, (metric_tracker, progress)) in poll
+                                    .metric_trackers
+                                    .iter()
+                                    .zip(poll_progress.metric_progresses.iter())
+                                    .enumerate()
+                                {
+                                    ui.allocate_ui_with_layout(
+                                        size,
+                                        Layout::right_to_left(Align::Center),
+                                        |ui| {
+                                            let style = ui.style_mut();
+                                            style.spacing.item_spacing.x = 2.0;
+                                            let mut progress_rect = None;
+                                            if let Some(progress) = progress {
+                                                if let Some(metric_rect) =
+                                                    self.ui_state.metric_rects.get(i)
+                                                {
+                                                    let rect = ui
+                                                        .available_rect_before_wrap()
+                                                        .translate(vec2(
+                                                            0.,
+                                                            metric_rect.height() / 2.
+                                                                - ui.text_style_height(
+                                                                    &TextStyle::Body,
+                                                                ) / 2.,
+                                                        ))
+                                                        .expand2(vec2(
+                                                            0.0,
+                                                            results_frame.stroke.width,
+                                                        ));
+                                                    ui.allocate_ui_at_rect(rect, |ui| {
+                                                        let response =
+                                                            results_frame.show(ui, |ui| {
+                                                                show_progress(ui, i, progress);
+                                                            });
+                                                        progress_rect =
+                                                            Some(response.response.rect);
+                                                    });
+                                                }
+                                            }
+
+                                            let metric_rect = results_frame
+                                                .show(ui, |ui| {
+                                                    clipped_label(
+                                                        ui,
+                                                        &metric_tracker
+                                                            .metric
+                                                            .render(&poll.questions),
+                                                        false,
+                                                    )
+                                                })
+                                                .response
+                                                .rect;
+                                            self.ui_state.progress_rects.push(
+                                                if let Some(progress_rect) = progress_rect {
+                                                    progress_rect
+                                                } else {
+                                                    metric_rect
+                                                },
+                                            );
+                                            if let Some(old_rect) =
+                                                self.ui_state.metric_rects.get_mut(i)
+                                            {
+                                                *old_rect = metric_rect
+                                            } else {
+                                                self.ui_state.metric_rects.push(metric_rect);
+                                            }
+                                        },
+                                    );
+                                }
+                            });
+                    }
+
+                    if !poll.results.is_empty() {
+                        let ui = &mut columns[2];
+                        let heading_rect = match (
+                            self.ui_state.result_rects.first(),
+                            self.ui_state.results_heading_rect,
+                        ) {
+                            (Some(top_metric_rect), Some(previous_heading_rect)) => Rect {
+                                min: pos2(
+                                    top_metric_rect.center().x
+                                        - previous_heading_rect.width() / 2.0,
+                                    ui.cursor().top(),
+                                ),
+                                max: pos2(
+                                    top_metric_rect.center().x
+                                        + previous_heading_rect.width() / 2.0
+                                        + 1.0,
+                                    f32::INFINITY,
+                                ),
+                            },
+                            _ => ui.available_rect_before_wrap(),
+                        };
+                        ui.allocate_ui_at_rect(heading_rect, |ui| {
+                            ui.with_layout(Layout::top_down(Align::Center), |ui| {
+                                let response =
+                                    ui.label(RichText::new("Results").underline().strong());
+                                self.ui_state.results_heading_rect = Some(response.rect);
+                            });
+                        });
+                        let scroll_max_height = ui.available_height() / 3.0;
+
+                        ui.add_space(UNDERHEADING_SPACE);
+                        self.ui_state.condition_rects.clear();
+                        ScrollArea::vertical()
+                            .id_source("results_scroll")
+                            .max_height(scroll_max_height)
+                            .show(ui, |ui| {
+                                for (i, (poll_result, result_state)) in poll
+                                    .results
+                                    .iter()
+                                    .zip(poll_progress.result_states.iter())
+                                    .enumerate()
+                                {
+                                    let results_frame =
+                                        results_frame.fill(palette.color(result_state.overall_met));
+                                    let mut size = ui.available_size();
+                                    size.y = 0.;
+                                    ui.allocate_ui_with_layout(
+                                        size,
+                                        Layout::left_to_right(Align::Center),
+                                        |ui| {
+                                            let style = ui.style_mut();
+                                            style.spacing.item_spacing.x = 2.0;
+                                            if let Some(result) = self.ui_state.result_rects.get(i)
                                             {
                                                 let rect = ui
                                                     .available_rect_before_wrap()
                                                     .translate(vec2(
                                                         0.,
-                                                        metric_rect.height() / 2.
+                                                        result.height() / 2.
                                                             - ui.text_style_height(
                                                                 &TextStyle::Body,
                                                             ) / 2.,
@@ -154,215 +763,315 @@ impl ResultsUi {
                                                     .expand2(vec2(0.0, results_frame.stroke.width));
                                                 ui.allocate_ui_at_rect(rect, |ui| {
                                                     let response = results_frame.show(ui, |ui| {
-                                                        ui.label(RichText::new(match progress {
-                                                            Progress::Count(count) => {
-                                                                count.to_string()
-                                                            }
-                                                        }));
+                                                        ui.colored_label(
+                                                            ui.style().visuals.strong_text_color(),
+                                                            RichText::new(format!(
+                                                                "{} {}",
+                                                                met_icon(result_state.overall_met),
+                                                                match poll_result.requirements[0] {
+                                                                    Requirement::AtLeast {
+                                                                        minimum,
+                                                                        ..
+                                                                    } => {
+                                                                        format!(
+                                                                            "≥{}",
+                                                                            format_count(
+                                                                                minimum, true
+                                                                            )
+                                                                        )
+                                                                    }
+                                                                    Requirement::Exactly {
+                                                                        count,
+                                                                        ..
+                                                                    } => {
+                                                                        format!(
+                                                                            "={}",
+                                                                            format_count(
+                                                                                count, true
+                                                                            )
+                                                                        )
+                                                                    }
+                                                                }
+                                                            )),
+                                                        );
                                                     });
-                                                    progress_rect = Some(response.response.rect);
+                                                    self.ui_state
+                                                        .condition_rects
+                                                        .push(response.response.rect);
                                                 });
                                             }
-                                        }
 
-                                        let metric_rect = results_frame
-                                            .show(ui, |ui| {
-                                                ui.add(
-                                                    Label::new(RichText::new(
-                                                        metric_tracker
-                                                            .metric
-                                                            .render(&poll.questions),
-                                                    ))
-                                                    .wrap(true),
-                                                )
-                                            })
-                                            .response
-                                            .rect;
-                                        self.ui_state.progress_rects.push(
-                                            if let Some(progress_rect) = progress_rect {
-                                                progress_rect
+                                            let rect = results_frame
+                                                .show(ui, |ui| {
+                                                    clipped_label(ui, &poll_result.desc, true)
+                                                })
+                                                .response
+                                                .rect;
+                                            let requirement = &poll_result.requirements[0];
+                                            let needed = requirement
+                                                .still_needed(&poll_progress.metric_progresses);
+                                            if let (
+                                                Some(needed),
+                                                Requirement::AtLeast { metric_index, .. },
+                                            ) = (needed, requirement)
+                                            {
+                                                // A majority is reached all at once, not one
+                                                // response at a time
+                                                let metric = &poll.metric_trackers
+                                                    [*metric_index as usize]
+                                                    .metric;
+                                                if !matches!(metric, Metric::MajorityReached { .. })
+                                                {
+                                                    ui.weak(format!(
+                                                        "needs {} more",
+                                                        format_count(needed, true)
+                                                    ));
+                                                }
+                                            }
+                                            if let Some(old_rect) =
+                                                self.ui_state.result_rects.get_mut(i)
+                                            {
+                                                *old_rect = rect
                                             } else {
-                                                metric_rect
-                                            },
-                                        );
-                                        if let Some(old_rect) =
-                                            self.ui_state.metric_rects.get_mut(i)
-                                        {
-                                            *old_rect = metric_rect
-                                        } else {
-                                            self.ui_state.metric_rects.push(metric_rect);
-                                        }
-                                    },
-                                );
-                            }
-                        });
-                }
+                                                self.ui_state.result_rects.push(rect);
+                                            }
+                                            self.ui_state.show_celebration(ui, i, rect);
+                                        },
+                                    );
+                                }
+                            });
+                    }
 
-                if !poll.results.is_empty() {
-                    let ui = &mut columns[2];
-                    let heading_rect = match (
-                        self.ui_state.result_rects.first(),
-                        self.ui_state.results_heading_rect,
-                    ) {
-                        (Some(top_metric_rect), Some(previous_heading_rect)) => Rect {
-                            min: pos2(
-                                top_metric_rect.center().x - previous_heading_rect.width() / 2.0,
-                                ui.cursor().top(),
-                            ),
-                            max: pos2(
-                                top_metric_rect.center().x
-                                    + previous_heading_rect.width() / 2.0
-                                    + 1.0,
-                                f32::INFINITY,
-                            ),
-                        },
-                        _ => ui.available_rect_before_wrap(),
-                    };
-                    ui.allocate_ui_at_rect(heading_rect, |ui| {
-                        ui.with_layout(Layout::top_down(Align::Center), |ui| {
-                            let response = ui.label(RichText::new("Results").underline().strong());
-                            self.ui_state.results_heading_rect = Some(response.rect);
-                        });
-                    });
-                    let scroll_max_height = ui.available_height() / 3.0;
-
-                    ui.add_space(UNDERHEADING_SPACE);
-                    self.ui_state.condition_rects.clear();
-                    ScrollArea::vertical()
-                        .id_source("results_scroll")
-                        .max_height(scroll_max_height)
-                        .show(ui, |ui| {
-                            for (i, (poll_result, result_state)) in poll
-                                .results
+                    if !(poll.metric_trackers.is_empty() || poll.results.is_empty()) {
+                        if let Some(results_heading_rect) = self.ui_state.results_heading_rect {
+                            let ui = &mut columns[1];
+                            let mut arrows_rect = ui
+                                .available_rect_before_wrap()
+                                .expand2(ui.spacing().item_spacing);
+                            arrows_rect.set_top(results_heading_rect.bottom());
+                            if let Some(bottom) = self.ui_state.bottom {
+                                arrows_rect.set_bottom(bottom);
+                            }
+                            ui.set_clip_rect(arrows_rect);
+                            for (i, (left_rect, (right_rect, result_state))) in self
+                                .ui_state
+                                .progress_rects
                                 .iter()
-                                .zip(poll_progress.result_states.iter())
+                                .zip(
+                                    self.ui_state
+                                        .condition_rects
+                                        .iter()
+                                        .zip(poll_progress.result_states.iter()),
+                                )
                                 .enumerate()
                             {
-                                let results_frame =
-                                    results_frame.fill(choose_color(result_state.overall_met));
-                                let mut size = ui.available_size();
-                                size.y = 0.;
-                                ui.allocate_ui_with_layout(
-                                    size,
-                                    Layout::left_to_right(Align::Center),
-                                    |ui| {
-                                        let style = ui.style_mut();
-                                        style.spacing.item_spacing.x = 2.0;
-                                        if let Some(result) = self.ui_state.result_rects.get(i) {
-                                            let rect = ui
-                                                .available_rect_before_wrap()
-                                                .translate(vec2(
-                                                    0.,
-                                                    result.height() / 2.
-                                                        - ui.text_style_height(&TextStyle::Body)
-                                                            / 2.,
-                                                ))
-                                                .expand2(vec2(0.0, results_frame.stroke.width));
-                                            ui.allocate_ui_at_rect(rect, |ui| {
-                                                let response = results_frame.show(ui, |ui| {
-                                                    ui.colored_label(
-                                                        ui.style().visuals.strong_text_color(),
-                                                        RichText::new(
-                                                            match poll_result.requirements[0] {
-                                                                Requirement::AtLeast {
-                                                                    minimum,
-                                                                    ..
-                                                                } => {
-                                                                    format!("≥{minimum}")
-                                                                }
-                                                            },
-                                                        ),
-                                                    );
-                                                });
-                                                self.ui_state
-                                                    .condition_rects
-                                                    .push(response.response.rect);
-                                            });
-                                        }
-
-                                        let rect = results_frame
-                                            .show(ui, |ui| {
-                                                ui.add(
-                                                    Label::new(
-                                                        RichText::new(&poll_result.desc).strong(),
-                                                    )
-                                                    .wrap(true),
-                                                )
-                                            })
-                                            .response
-                                            .rect;
-                                        if let Some(old_rect) =
-                                            self.ui_state.result_rects.get_mut(i)
-                                        {
-                                            *old_rect = rect
-                                        } else {
-                                            self.ui_state.result_rects.push(rect);
-                                        }
-                                    },
+                                const MARGIN: f32 = 3.0;
+                                let mut left = left_rect.right_center();
+                                let mut right = right_rect.left_center();
+                                left.x += MARGIN;
+                                right.x -= MARGIN;
+                                let vector = right - left;
+                                // Briefly thicken the arrow when its result flips
+                                let flip = ui.ctx().animate_bool_with_time(
+                                    Id::new(("result_met", i)),
+                                    result_state.overall_met,
+                                    ANIMATION_TIME * 2.0,
+                                );
+                                let pulse = (flip * std::f32::consts::PI).sin() * 2.0;
+                                ui.painter().line_segment(
+                                    [left, left + vector],
+                                    Stroke::new(
+                                        3.0 + pulse,
+                                        palette.color(result_state.overall_met),
+                                    ),
                                 );
                             }
-                        });
-                }
-
-                if !(poll.metric_trackers.is_empty() || poll.results.is_empty()) {
-                    if let Some(results_heading_rect) = self.ui_state.results_heading_rect {
-                        let ui = &mut columns[1];
-                        let mut arrows_rect = ui
-                            .available_rect_before_wrap()
-                            .expand2(ui.spacing().item_spacing);
-                        arrows_rect.set_top(results_heading_rect.bottom());
-                        if let Some(bottom) = self.ui_state.bottom {
-                            arrows_rect.set_bottom(bottom);
-                        }
-                        ui.set_clip_rect(arrows_rect);
-                        for (left_rect, (right_rect, result_state)) in
-                            self.ui_state.progress_rects.iter().zip(
-                                self.ui_state
-                                    .condition_rects
-                                    .iter()
-                                    .zip(poll_progress.result_states.iter()),
-                            )
-                        {
-                            const MARGIN: f32 = 3.0;
-                            let mut left = left_rect.right_center();
-                            let mut right = right_rect.left_center();
-                            left.x += MARGIN;
-                            right.x -= MARGIN;
-                            let vector = right - left;
-                            ui.painter().line_segment(
-                                [left, left + vector],
-                                Stroke::new(3.0, choose_color(result_state.overall_met)),
-                            );
                         }
                     }
+                });
+            };
+            if collapse_details {
+                CollapsingHeader::new("Details")
+                    .id_source("results_details")
+                    .show(ui, show_details);
+            } else {
+                show_details(ui);
+            }
+            self.ui_state.bottom = Some(ui.separator().rect.top());
+            show_breakdowns(ui, poll, poll_progress);
+            show_responder_names(ui, poll, poll_progress);
+            show_first_responders(ui, poll_progress);
+            ui.horizontal(|ui| {
+                if ui
+                    .small_button("📋")
+                    .on_hover_text("Copy Results Summary")
+                    .clicked()
+                {
+                    ui.output().copied_text = summary_text(poll, poll_progress);
+                }
+                let any_met = poll_progress
+                    .result_states
+                    .iter()
+                    .any(|state| state.overall_met);
+                if let (Some(key), true) = (key, any_met) {
+                    let event = poll.calendar_event(&calendar_uid(key), Utc::now());
+                    let response = ui
+                        .add_enabled(event.is_some(), Button::new("📅").small())
+                        .on_hover_text("Add to Calendar")
+                        .on_disabled_hover_text("This poll has no date to add");
+                    if let (true, Some(event)) = (response.clicked(), event) {
+                        download_file(&format!("{}.ics", poll.title), "text/calendar", &event);
+                    }
+                }
+                if live && ui.small_button("🔄").on_hover_text("Refresh now").clicked() {
+                    self.stale = true;
+                }
+                if live && !poll.results.is_empty() {
+                    ui.menu_button("🔔", |ui| {
+                        show_subscriptions(ui, poll, &mut self.subscribed_results);
+                    })
+                    .response
+                    .on_hover_text("Notify Me");
                 }
             });
-            self.ui_state.bottom = Some(ui.separator().rect.top());
         } else {
-            ui.spinner();
+            show_skeleton(ui, poll, &column_widths);
+        }
+    }
+
+    /// Queues a celebration for each result that went from unmet to met
+    fn celebrate_newly_met(&mut self, progress: &PollProgress, now: f64) {
+        let Some(old_progress) = &self.poll_progress else {
+            return;
+        };
+        for (i, state) in progress.result_states.iter().enumerate() {
+            let was_met =
+                matches!(old_progress.result_states.get(i), Some(state) if state.overall_met);
+            if !was_met && state.overall_met {
+                self.ui_state
+                    .celebrations
+                    .retain(|&(result, _)| result != i);
+                self.ui_state.celebrations.push((i, now));
+            }
         }
+    }
 
-        self.fetch(ui, key);
+    fn notify_newly_met(&self, poll: &Poll, progress: &PollProgress) {
+        let Some(old_progress) = &self.poll_progress else {
+            return;
+        };
+        for &i in &self.subscribed_results {
+            let was_met =
+                matches!(old_progress.result_states.get(i), Some(state) if state.overall_met);
+            let is_met = matches!(progress.result_states.get(i), Some(state) if state.overall_met);
+            if !was_met && is_met {
+                if let Some(result) = poll.results.get(i) {
+                    show_notification(&poll.title, &result.desc);
+                }
+            }
+        }
     }
 
-    fn fetch(&mut self, ui: &mut Ui, key: u64) {
+    /// A button per emoji in `REACTIONS` with its count. Only one reaction is sent at a
+    /// time, and the server turns away ones that come too quickly.
+    fn show_reactions(&mut self, ui: &mut Ui, key: u64, password: Option<&str>) {
+        let Some(poll_progress) = &mut self.poll_progress else {
+            return;
+        };
+        if let Some(reaction) = &mut self.reaction {
+            if let Some(result) = reaction.poll() {
+                match result {
+                    Ok(ReactResult::TooSoon) => {
+                        console_log!("Reacted to poll {key} too soon after the last reaction");
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        console_log!("Reacting to poll {key} failed: {e}");
+                    }
+                }
+                self.reaction = None;
+                self.stale = true;
+            }
+        }
+        ui.horizontal(|ui| {
+            for emoji in REACTIONS {
+                let count = poll_progress.reactions.get(emoji).copied().unwrap_or(0);
+                let text = if count == 0 {
+                    emoji.to_string()
+                } else {
+                    format!("{emoji} {count}")
+                };
+                let button = ui.add_enabled(self.reaction.is_none(), Button::new(text).small());
+                if button.clicked() {
+                    // Shown right away, and corrected by the next fetch
+                    *poll_progress
+                        .reactions
+                        .entry(emoji.to_string())
+                        .or_default() += 1;
+                    self.reaction = Some(ApiCall::new(api::react(
+                        ReactRequest {
+                            poll_key: key,
+                            emoji: emoji.to_string(),
+                        },
+                        password.map(str::to_string),
+                    )));
+                }
+            }
+        });
+    }
+
+    /// Progress is fetched with the editor secret, if this browser made the poll, so results
+    /// hidden until it closes still show to its editor
+    fn fetch(
+        &mut self,
+        ui: &mut Ui,
+        poll: &Poll,
+        key: u64,
+        password: Option<&str>,
+        editor_secret: Option<&str>,
+    ) {
         let mut fetch_complete = false;
         if let Some(ref mut fetch) = self.poll_progress_fetch {
             if let Some(progress) = fetch.poll() {
                 match progress {
-                    ProgressReportResult::Success { progress } => {
+                    Ok(ProgressReportResult::Success {
+                        progress,
+                        poll_fingerprint,
+                    }) => {
+                        if poll_fingerprint.is_some_and(|f| f != poll.fingerprint()) {
+                            console_log!("Poll {key} changed on the server");
+                            self.poll_changed = true;
+                        }
+                        let hash = progress_hash(&progress);
+                        if self.last_progress_hash != Some(hash) {
+                            self.last_progress_hash = Some(hash);
+                            self.last_change = Some(Instant::now());
+                        }
+                        self.notify_newly_met(poll, &progress);
+                        self.celebrate_newly_met(&progress, ui.input().time);
                         self.poll_progress = Some(progress);
                         self.stale = false;
                     }
-                    ProgressReportResult::Error => {}
+                    Ok(ProgressReportResult::Error) => {}
+                    Err(e) => {
+                        console_log!("Fetching progress of poll {key} failed: {e}");
+                    }
                 }
                 fetch_complete = true;
             }
         } else if self.stale
             || self.last_fetch.is_none()
-            || self.last_fetch.unwrap().elapsed() > Duration::from_secs_f32(1.5)
+            || self.last_fetch.unwrap().elapsed() > refresh_interval(&self.last_change)
         {
-            self.poll_progress_fetch = Some(Submitter::new("progress", key));
+            if self.stale {
+                // Likely our own submission, so keep up with whatever follows it
+                self.last_change = Some(Instant::now());
+            }
+            self.poll_progress_fetch = Some(ApiCall::new(api::get_progress(
+                key,
+                password.map(str::to_string),
+                editor_secret.map(str::to_string),
+            )));
             self.last_fetch = Some(Instant::now());
         }
         if fetch_complete {
@@ -1,16 +1,15 @@
-#[allow(unused)]
-use crate::misc::{console_log, log};
-use crate::{misc::Pollable, participation::ParticipationState, poll::PollState, SERVER_URL};
+use crate::api::{self, ApiCall};
+use crate::misc::console_log;
+use crate::{
+    participation::{ParticipationState, Resume},
+    poll::PollState,
+};
 use areyougoing_shared::PollQueryResult;
-use wasm_bindgen::JsCast;
-use wasm_bindgen_futures::JsFuture;
-use web_sys::{Request, RequestInit, RequestMode, Response};
 
 #[derive(Debug)]
 pub enum RetrievingState {
     None,
-    Fetching(JsFuture),
-    Converting(JsFuture),
+    Fetching(ApiCall<PollQueryResult>),
 }
 
 impl Default for RetrievingState {
@@ -20,56 +19,59 @@ impl Default for RetrievingState {
 }
 
 impl RetrievingState {
-    pub fn process(&mut self, next_poll_state: &mut Option<PollState>, poll_key: u64) {
+    pub fn process(
+        &mut self,
+        next_poll_state: &mut Option<PollState>,
+        poll_key: u64,
+        password: &Option<String>,
+        resume: &Option<Resume>,
+    ) {
         let mut next_retreiving_state = None;
         match self {
             RetrievingState::None => {
-                let mut opts = RequestInit::new();
-                opts.method("GET");
-                opts.mode(RequestMode::Cors);
-                let url = format!("{SERVER_URL}?poll_key={poll_key}");
-                let request = Request::new_with_str_and_init(&url, &opts).unwrap();
-                let window = web_sys::window().unwrap();
-                next_retreiving_state = Some(RetrievingState::Fetching(JsFuture::from(
-                    window.fetch_with_request(&request),
+                next_retreiving_state = Some(RetrievingState::Fetching(ApiCall::new(
+                    api::get_poll(poll_key, password.clone()),
                 )));
             }
-            RetrievingState::Fetching(js_future) => {
-                if let Some(result) = js_future.poll() {
-                    next_retreiving_state = Some(RetrievingState::None);
-                    if let Ok(resp_value) = result {
-                        assert!(resp_value.is_instance_of::<Response>());
-                        let resp: Response = resp_value.dyn_into().unwrap();
-
-                        // Convert this other `Promise` into a rust `Future`.
-                        if let Ok(json) = resp.json() {
-                            next_retreiving_state =
-                                Some(RetrievingState::Converting(JsFuture::from(json)));
-                        }
-                    }
-                }
-            }
-            RetrievingState::Converting(js_future) => {
-                if let Some(Ok(json)) = js_future.poll() {
-                    if let Ok(poll_query_result) = serde_wasm_bindgen::from_value(json) {
-                        match poll_query_result {
-                            PollQueryResult::Found(poll) => {
-                                *next_poll_state = Some(PollState::Found {
-                                    poll,
-                                    key: poll_key,
-                                    results_ui: Default::default(),
-                                    participation_state: ParticipationState::SignIn,
-                                });
+            RetrievingState::Fetching(call) => match call.poll() {
+                Some(Ok(poll_query_result)) => match poll_query_result {
+                    PollQueryResult::Found(poll) => {
+                        // Answers to an older version of the poll are dropped
+                        let participation_state = match resume {
+                            Some(resume) if poll.validate_responses(&resume.question_responses) => {
+                                ParticipationState::SignedIn {
+                                    user: resume.user.clone(),
+                                    question_responses: resume.question_responses.clone(),
+                                    submitted: resume.submitted.clone(),
+                                }
                             }
-                            PollQueryResult::NotFound => {
-                                *next_poll_state = Some(PollState::NotFound { key: poll_key });
-                            }
-                        }
-                    } else {
-                        next_retreiving_state = Some(RetrievingState::None);
+                            _ => ParticipationState::SignIn,
+                        };
+                        *next_poll_state = Some(PollState::Found {
+                            poll: *poll,
+                            key: poll_key,
+                            password: password.clone(),
+                            results_ui: Default::default(),
+                            participation_state,
+                        });
+                    }
+                    PollQueryResult::NotFound => {
+                        *next_poll_state = Some(PollState::NotFound { key: poll_key });
+                    }
+                    PollQueryResult::PasswordRequired => {
+                        *next_poll_state = Some(PollState::PasswordRequired {
+                            key: poll_key,
+                            password_entry: String::new(),
+                            wrong: password.is_some(),
+                        });
                     }
+                },
+                Some(Err(e)) => {
+                    console_log!("Fetching poll {poll_key} failed: {e}");
+                    next_retreiving_state = Some(RetrievingState::None);
                 }
-            }
+                None => {}
+            },
         }
         if let Some(next_state) = next_retreiving_state {
             *self = next_state;
@@ -0,0 +1,89 @@
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap},
+    hash::{Hash, Hasher},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, PoisonError,
+    },
+    time::{Duration, Instant, SystemTime},
+};
+
+use headers::ETag;
+
+/// How long a computed body is served as is. Results can change with the clock too, e.g.
+/// when a poll that hides them closes, so they can't only be cleared on submit.
+pub const RESULTS_TTL: Duration = Duration::from_secs(2);
+
+/// Serialized `/results.json` bodies by poll key, so integrations polling it don't make
+/// the server retally on every request
+#[derive(Default)]
+pub struct ResultsCache {
+    entries: Mutex<HashMap<u64, CachedResults>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+#[derive(Clone)]
+pub struct CachedResults {
+    pub body: String,
+    pub etag: ETag,
+    /// When the body last changed, which survives recomputing the same body
+    pub last_modified: SystemTime,
+    computed_at: Instant,
+}
+
+impl ResultsCache {
+    /// The body for `key`, recomputed with `compute` if it's missing or older than
+    /// `RESULTS_TTL`. `None` if `compute` finds no poll.
+    ///
+    /// Take the db lock before calling this, as `submit` does before `invalidate`, so the
+    /// two locks are always taken in the same order.
+    pub fn get_or_compute(
+        &self,
+        key: u64,
+        compute: impl FnOnce() -> Option<String>,
+    ) -> Option<CachedResults> {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(cached) = entries.get(&key) {
+            if cached.computed_at.elapsed() < RESULTS_TTL {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return Some(cached.clone());
+            }
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let body = compute()?;
+        let mut hasher = DefaultHasher::new();
+        body.hash(&mut hasher);
+        let etag = format!("\"{:x}\"", hasher.finish())
+            .parse::<ETag>()
+            .unwrap();
+        let last_modified = match entries.get(&key) {
+            Some(old) if old.etag == etag => old.last_modified,
+            _ => SystemTime::now(),
+        };
+        let cached = CachedResults {
+            body,
+            etag,
+            last_modified,
+            computed_at: Instant::now(),
+        };
+        entries.insert(key, cached.clone());
+        Some(cached)
+    }
+
+    /// Drops the body for `key`, so the next request sees new responses right away
+    pub fn invalidate(&self, key: u64) {
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .remove(&key);
+    }
+
+    /// How many requests were served from the cache, and how many had to recompute
+    pub fn stats(&self) -> (u64, u64) {
+        (
+            self.hits.load(Ordering::Relaxed),
+            self.misses.load(Ordering::Relaxed),
+        )
+    }
+}
@@ -0,0 +1,83 @@
+use std::marker::PhantomData;
+
+use areyougoing_shared::{
+    AddEditorResult, ClearResponsesResult, CreatePollResult, PollSubmissionResult,
+    ProgressReportResult, ReactResult,
+};
+use axum::{
+    async_trait,
+    body::HttpBody,
+    extract::{FromRequest, RequestParts},
+    response::{IntoResponse, Response},
+    BoxError, Json,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// What an endpoint answers when its request body can't be read
+pub trait MalformedRequest: Serialize {
+    fn malformed() -> Self;
+}
+
+impl MalformedRequest for CreatePollResult {
+    fn malformed() -> Self {
+        CreatePollResult::Error
+    }
+}
+
+impl MalformedRequest for PollSubmissionResult {
+    fn malformed() -> Self {
+        PollSubmissionResult::Error
+    }
+}
+
+impl MalformedRequest for AddEditorResult {
+    fn malformed() -> Self {
+        AddEditorResult::Error
+    }
+}
+
+impl MalformedRequest for ClearResponsesResult {
+    fn malformed() -> Self {
+        ClearResponsesResult::Error
+    }
+}
+
+impl MalformedRequest for ReactResult {
+    fn malformed() -> Self {
+        ReactResult::Error
+    }
+}
+
+impl MalformedRequest for ProgressReportResult {
+    fn malformed() -> Self {
+        ProgressReportResult::Error
+    }
+}
+
+/// Like `Json`, but a body that can't be read is answered with `R::malformed()` as JSON,
+/// which clients read like any other result, rather than axum's plain text rejection
+pub struct JsonBody<T, R>(pub T, pub PhantomData<R>);
+
+#[async_trait]
+impl<T, R, B> FromRequest<B> for JsonBody<T, R>
+where
+    T: DeserializeOwned,
+    R: MalformedRequest,
+    B: HttpBody + Send,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        match Json::<T>::from_request(req).await {
+            Ok(Json(value)) => Ok(Self(value, PhantomData)),
+            Err(rejection) => {
+                println!("Rejected malformed request to {}: {rejection}", req.uri());
+                // Keep the status, so it's still clear what went wrong
+                let status = rejection.into_response().status();
+                Err((status, Json(R::malformed())).into_response())
+            }
+        }
+    }
+}
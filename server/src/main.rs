@@ -1,72 +1,98 @@
+mod cache;
+mod extract;
+mod notify;
+mod password;
+mod reactions;
+mod schema;
+mod tally;
+
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fs,
     net::SocketAddr,
-    path::PathBuf,
-    sync::{Arc, Mutex, MutexGuard},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard, PoisonError,
+    },
+    time::Duration,
 };
 
+use anyhow::{bail, Context};
 use areyougoing_shared::{
-    CreatePollResult, Form, FormResponse, Metric, MetricTracker, Poll, PollProgress,
-    PollQueryResult, PollResponse, PollResult, PollStatus, PollSubmissionResult, Progress,
-    ProgressReportResult, Question, Requirement, ResultState,
+    option_counts, responder_names, AddEditorRequest, AddEditorResult, ClearResponsesRequest,
+    ClearResponsesResult, CreatePollResult, DedupStrategy, Form, FormResponse, HasRespondedResult,
+    MetricTracker, Poll, PollProgress, PollQueryResult, PollResponse, PollResult, PollStats,
+    PollStatus, PollSubmissionResult, Progress, ProgressReportResult, Question, ReactRequest,
+    ReactResult, Requirement, ResponderField, ResponderHistory, ResultState, ResultsReport,
+    ResultsReportResult, Submission, EDITOR_SECRET_HEADER, FIRST_RESPONDERS_SHOWN, PASSWORD_HEADER,
+    REACTIONS, RESULTS_REPORT_SCHEMA_VERSION,
 };
 use axum::{
-    extract::Query,
-    http::Method,
-    response::IntoResponse,
+    body::Body,
+    extract::{ConnectInfo, Query, TypedHeader},
+    handler::Handler,
+    http::{header, HeaderMap, Method, Request, StatusCode},
+    response::{Html, IntoResponse, Response},
     routing::{get, post},
     Extension, Json, Router,
 };
-use axum_server::tls_rustls::RustlsConfig;
+use axum_server::{tls_rustls::RustlsConfig, Handle};
+use cache::ResultsCache;
+use chrono::{DateTime, Utc};
+use extract::JsonBody;
+use headers::{IfModifiedSince, IfNoneMatch, LastModified};
 use local_ip_address::local_ip;
+use notify::{LoggingNotifier, SubmitNotifier};
+use password::{hash_password, verify_password};
+use rand::{distributions::Alphanumeric, Rng};
+use reactions::ReactionLimiter;
 use ron::{extensions::Extensions, ser::PrettyConfig};
 use serde::{Deserialize, Serialize};
+use tower::ServiceExt;
 use tower_http::{
+    compression::CompressionLayer,
     cors::{Any, CorsLayer},
-    trace::{DefaultMakeSpan, TraceLayer},
+    request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer},
+    services::ServeDir,
+    trace::TraceLayer,
 };
+use tracing::Span;
 use tracing_subscriber::{prelude::__tracing_subscriber_SubscriberExt, util::SubscriberInitExt};
 
 #[tokio::main]
 async fn main() {
+    // `areyougoing_server tally <archive>` tallies offline instead of serving
+    let mut args = std::env::args().skip(1);
+    if args.next().as_deref() == Some("tally") {
+        let Some(path) = args.next() else {
+            eprintln!("Usage: areyougoing_server tally <archive.ron | archive.json>");
+            std::process::exit(2);
+        };
+        if let Err(e) = tally::run(Path::new(&path)) {
+            eprintln!("Couldn't tally {path}: {e:#}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
     tracing_subscriber::registry()
         .with(tracing_subscriber::EnvFilter::new(
-            std::env::var("RUST_LOG").unwrap_or_else(|_| "tower_http=warn".into()),
+            std::env::var("RUST_LOG")
+                .unwrap_or_else(|_| "tower_http=warn,areyougoing_server=info".into()),
         ))
         .with(tracing_subscriber::fmt::layer())
         .init();
 
     let config = Config::new();
-    let db = Db::new();
+    let db = Arc::new(Mutex::new(Db::new()));
+    let addr = config.bind_addr;
+    if let Some(retention) = config.poll_retention {
+        tokio::spawn(prune_closed_polls(db.clone(), retention));
+    }
+    tokio::spawn(save_stats(db.clone()));
 
-    let app = Router::new()
-        // .route("/", get(get_page))
-        .route("/", get(get_poll))
-        .route("/submit", post(submit))
-        .route("/new_poll", post(new_poll))
-        .route("/progress", post(get_progress))
-        .layer(
-            // see https://docs.rs/tower-http/latest/tower_http/cors/index.html
-            // for more details
-            //
-            // pay attention that for some request types like posting content-type: application/json
-            // it is required to add ".allow_headers([http::header::CONTENT_TYPE])"
-            // or see this issue https://github.com/tokio-rs/axum/issues/849
-            CorsLayer::new()
-                .allow_origin(Any)
-                // .allow_origin("http://127.0.0.1:5000".parse::<HeaderValue>().unwrap())
-                .allow_methods([Method::GET])
-                // .allow_credentials(true)
-                .allow_headers([http::header::CONTENT_TYPE]),
-        )
-        .layer(
-            // logging
-            TraceLayer::new_for_http()
-                .make_span_with(DefaultMakeSpan::default().include_headers(true)),
-        )
-        .layer(Extension(config))
-        .layer(Extension(Arc::new(Mutex::new(db))));
+    let app = app(config, db.clone());
 
     // configure certificate and private key used by https
     let tls_config = RustlsConfig::from_pem_file(
@@ -76,33 +102,266 @@ async fn main() {
     .await
     .unwrap();
 
-    // let addr = SocketAddr::from(([127, 0, 0, 1], 3000)); // for offline use
-    // let addr = SocketAddr::from((local_ip().expect("Failed to get local ip address"), 3000));
-    let addr = SocketAddr::from((local_ip().expect("Failed to get local ip address"), 443));
-    // println!("Listening on http://{addr}");
+    let handle = Handle::new();
+    tokio::spawn(shutdown_on_signal(handle.clone()));
+
     println!("Listening on https://{addr}");
     axum_server::bind_rustls(addr, tls_config)
-        .serve(app.into_make_service())
+        .handle(handle)
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
         .await
         .unwrap();
+    // Responses are written as they come in, but view, progress and reaction counts wait
+    // for `save_stats`
+    lock_db(&db).write();
+    tracing::info!("flushed DB on shutdown");
     // axum::Server::bind(&addr)
     //     .serve(app.into_make_service_with_connect_info::<SocketAddr>())
     //     .await
     //     .unwrap();
 }
 
+/// Stops taking new connections on ctrl-c or SIGTERM, and gives open requests a few
+/// seconds to finish
+async fn shutdown_on_signal(handle: Handle) {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to listen for ctrl-c");
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+    println!("Shutting down");
+    handle.graceful_shutdown(Some(Duration::from_secs(10)));
+}
+
+/// How often closed polls are checked for being past `Config::poll_retention`
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Deletes polls closed for longer than `retention` every `PRUNE_INTERVAL`
+async fn prune_closed_polls(db: Arc<Mutex<Db>>, retention: chrono::Duration) {
+    let mut interval = tokio::time::interval(PRUNE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let mut db = lock_db(&db);
+        let (pruned, closings_changed) = db.prune_closed(retention, Utc::now());
+        if pruned > 0 || closings_changed {
+            db.write();
+        }
+        tracing::info!(pruned, remaining = db.0.len(), "pruned closed polls");
+    }
+}
+
+/// Set when a poll's view, progress or reaction counts change without the db being written,
+/// so these frequent requests don't each write the whole db
+static STATS_UNSAVED: AtomicBool = AtomicBool::new(false);
+
+/// How often counts changed by reads are written
+const STATS_SAVE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Writes the db every `STATS_SAVE_INTERVAL` if counts changed since it was last written.
+/// Shutdown writes whatever's left.
+async fn save_stats(db: Arc<Mutex<Db>>) {
+    let mut interval = tokio::time::interval(STATS_SAVE_INTERVAL);
+    loop {
+        interval.tick().await;
+        if STATS_UNSAVED.swap(false, Ordering::Relaxed) {
+            lock_db(&db).write();
+        }
+    }
+}
+
+const REQUEST_ID_HEADER: &str = "x-request-id";
+
+fn app(config: Config, db: Arc<Mutex<Db>>) -> Router {
+    let mut app = Router::new()
+        .route("/", get(get_root))
+        .route("/polls/batch", post(get_polls))
+        .route("/submit", post(submit))
+        .route("/new_poll", post(new_poll))
+        .route("/progress", post(get_progress))
+        .route("/results.json", get(get_results))
+        .route("/add_editor", post(add_editor))
+        .route("/clear_responses", post(clear_responses))
+        .route("/react", post(react))
+        .route("/has_responded", get(has_responded))
+        .route("/archive", get(get_archive))
+        .route("/responses", get(get_responses))
+        .route("/restore", post(restore))
+        .route("/schema", get(get_schema))
+        .route("/stats", get(get_stats));
+    if let Some(static_dir) = &config.static_dir {
+        println!("Serving static files from {}", static_dir.display());
+        app = app.fallback(serve_static.into_service());
+    }
+    app.layer(
+        // see https://docs.rs/tower-http/latest/tower_http/cors/index.html
+        // for more details
+        //
+        // pay attention that for some request types like posting content-type: application/json
+        // it is required to add ".allow_headers([http::header::CONTENT_TYPE])"
+        // or see this issue https://github.com/tokio-rs/axum/issues/849
+        CorsLayer::new()
+            .allow_origin(Any)
+            // .allow_origin("http://127.0.0.1:5000".parse::<HeaderValue>().unwrap())
+            .allow_methods([Method::GET, Method::POST, Method::OPTIONS])
+            // .allow_credentials(true)
+            .allow_headers([
+                http::header::CONTENT_TYPE,
+                header::HeaderName::from_static(PASSWORD_HEADER),
+                header::HeaderName::from_static(EDITOR_SECRET_HEADER),
+            ])
+            // so the client can report which request failed
+            .expose_headers([header::HeaderName::from_static(REQUEST_ID_HEADER)]),
+    )
+    .layer(
+        // logging, with a line per request tagged with its id
+        TraceLayer::new_for_http()
+            .make_span_with(|request: &Request<Body>| {
+                let request_id = request
+                    .headers()
+                    .get(REQUEST_ID_HEADER)
+                    .and_then(|id| id.to_str().ok())
+                    .unwrap_or_default();
+                tracing::info_span!(
+                    "request",
+                    request_id,
+                    method = %request.method(),
+                    uri = %request.uri(),
+                )
+            })
+            .on_response(|response: &Response, latency: Duration, _span: &Span| {
+                tracing::info!(
+                    status = response.status().as_u16(),
+                    latency_ms = latency.as_millis() as u64,
+                    "finished"
+                );
+            }),
+    )
+    // Reuses a caller's x-request-id, or makes one up, and echoes it in the response
+    .layer(PropagateRequestIdLayer::x_request_id())
+    .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+    .layer(Extension(config))
+    .layer(Extension(
+        Arc::new(LoggingNotifier) as Arc<dyn SubmitNotifier>
+    ))
+    .layer(Extension(db))
+    .layer(Extension(Arc::new(ResultsCache::default())))
+    .layer(Extension(Arc::new(ReactionLimiter::default())))
+    // gzip or brotli, whichever the client says it accepts
+    .layer(CompressionLayer::new())
+}
+
 async fn submit(
     Extension(db): Extension<Arc<Mutex<Db>>>,
-    Json(poll_response): Json<PollResponse>,
-) -> impl IntoResponse {
-    println!("{poll_response:?}");
+    Extension(notifier): Extension<Arc<dyn SubmitNotifier>>,
+    Extension(results_cache): Extension<Arc<ResultsCache>>,
+    headers: HeaderMap,
+    JsonBody(mut poll_response, _): JsonBody<PollResponse, PollSubmissionResult>,
+) -> Json<PollSubmissionResult> {
+    // Not the whole response, which can carry the responder's email
+    println!(
+        "Response to poll {} from {}",
+        poll_response.poll_id, poll_response.user
+    );
+    if can_view(&db, poll_response.poll_id, &headers).await != Some(true) {
+        return Json(PollSubmissionResult::Error);
+    }
     Json(if let Ok(mut db) = db.lock() {
         if let Some(poll_data) = db.0.get_mut(&poll_response.poll_id) {
+            if !poll_data.poll.validate_responses(&poll_response.responses) {
+                println!("Rejected invalid response from {}", poll_response.user);
+                return Json(PollSubmissionResult::Error);
+            }
             poll_data
-                .responses
-                .insert(poll_response.user.clone(), poll_response.responses);
-            poll_data.update_results();
+                .poll
+                .reset_hidden_answers(&mut poll_response.responses);
+            let response_key = match poll_data.poll.dedup {
+                DedupStrategy::ByName => poll_response.user.clone(),
+                // Otherwise everyone who leaves it out would share one response
+                DedupStrategy::ByDevice if poll_response.device_key.is_empty() => {
+                    println!(
+                        "Rejected response without a device key from {}",
+                        poll_response.user
+                    );
+                    return Json(PollSubmissionResult::Error);
+                }
+                DedupStrategy::ByDevice => poll_response.device_key.clone(),
+                DedupStrategy::None => new_secret(),
+            };
+            if poll_data.responses.contains_key(&response_key)
+                && poll_data.poll.edits_locked(Utc::now())
+            {
+                println!("Rejected late edit from {}", poll_response.user);
+                return Json(PollSubmissionResult::EditWindowClosed);
+            }
+            if poll_data.is_full_for(&response_key) {
+                println!("Turned away {}, the poll is full", poll_response.user);
+                return Json(PollSubmissionResult::NoSpotsLeft);
+            }
+            let taken = poll_data
+                .responder_values
+                .iter()
+                .filter(|(key, _)| **key != response_key)
+                .flat_map(|(_, values)| values.iter().cloned())
+                .collect::<Vec<_>>();
+            if let Some(reason) = poll_data.poll.constraint_violation(&poll_response, &taken) {
+                println!("Rejected response from {}: {reason}", poll_response.user);
+                return Json(PollSubmissionResult::ConstraintViolated { reason });
+            }
+            // Checked under the db lock, so when two people race for the last seat the
+            // first submission to get here takes it and the second is turned away
+            let full = poll_data.poll.full_choices(
+                &poll_data.responses,
+                &response_key,
+                &poll_response.responses,
+            );
+            if !full.is_empty() {
+                println!("Rejected full options from {}", poll_response.user);
+                let options = full
+                    .iter()
+                    .map(|(i, choice)| {
+                        let choices = poll_data.poll.questions[*i].form.choices();
+                        let (label, _) = choices.iter().find(|(_, c)| c == choice).unwrap();
+                        label.clone()
+                    })
+                    .collect();
+                return Json(PollSubmissionResult::Full { options });
+            }
+            let unique_values = poll_data.poll.unique_values(&poll_response);
+            if !unique_values.is_empty() {
+                poll_data
+                    .responder_values
+                    .insert(response_key.clone(), unique_values);
+            }
+            poll_data.stats.submissions += 1;
+            // Only the answers are kept, so emails never reach metrics or results
+            poll_data.record_submission(response_key, poll_response.responses.clone(), Utc::now());
+            for i in poll_data.update_results() {
+                tracing::info!(
+                    poll_key = poll_response.poll_id,
+                    result = %poll_data.poll.results[i].desc,
+                    responders = poll_data.responses.len(),
+                    "result met"
+                );
+            }
+            let poll = poll_data.poll.clone();
+            results_cache.invalidate(poll_response.poll_id);
             db.write();
+            // Whatever the notifier does, e.g. sending mail, shouldn't hold up other requests
+            drop(db);
+            notifier.notify(poll_response.poll_id, &poll, &poll_response);
             PollSubmissionResult::Success
         } else {
             PollSubmissionResult::Error
@@ -124,120 +383,766 @@ fn get_unused_key(db: &MutexGuard<Db>) -> u64 {
 
 async fn new_poll(
     Extension(db): Extension<Arc<Mutex<Db>>>,
-    Json(poll): Json<Poll>,
+    JsonBody(mut poll, _): JsonBody<Poll, CreatePollResult>,
 ) -> impl IntoResponse {
+    if let Err(e) = poll.validate() {
+        println!("Rejected invalid poll: {e}");
+        return Json(CreatePollResult::Error);
+    }
+    poll.view_password = poll
+        .view_password
+        .filter(|password| !password.is_empty())
+        .map(|password| hash_password(&password));
+    for warning in poll.warnings() {
+        println!("New poll warning: {warning}");
+    }
     Json(if let Ok(mut db) = db.lock() {
         let key = get_unused_key(&db);
-        println!("New Poll at {key}: {poll:?}");
+        println!(
+            "New Poll at {key}: {:?}",
+            Poll {
+                view_password: None,
+                ..poll.clone()
+            }
+        );
+        let editor_secret = new_secret();
         db.0.insert(
             key,
             PollData {
-                result_states: poll.results.iter().map(ResultState::from_result).collect(),
-                progresses: poll
-                    .metric_trackers
-                    .iter()
-                    .map(|t| match t.metric {
-                        Metric::SpecificResponses { .. } => Progress::Count(0),
-                    })
-                    .collect(),
-                poll,
-                responses: Default::default(),
+                editor_secrets: vec![editor_secret.clone()],
+                ..PollData::new(poll)
             },
         );
-        CreatePollResult::Success { key }
+        CreatePollResult::Success { key, editor_secret }
     } else {
         CreatePollResult::Error
     })
 }
 
+fn new_secret() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect()
+}
+
+async fn add_editor(
+    Extension(db): Extension<Arc<Mutex<Db>>>,
+    JsonBody(request, _): JsonBody<AddEditorRequest, AddEditorResult>,
+) -> impl IntoResponse {
+    Json(if let Ok(mut db) = db.lock() {
+        if let Some(poll_data) = db.0.get_mut(&request.poll_key) {
+            if poll_data.is_editor(&request.editor_secret) {
+                let editor_secret = new_secret();
+                poll_data.editor_secrets.push(editor_secret.clone());
+                db.write();
+                AddEditorResult::Success { editor_secret }
+            } else {
+                AddEditorResult::Unauthorized
+            }
+        } else {
+            AddEditorResult::Error
+        }
+    } else {
+        AddEditorResult::Error
+    })
+}
+
+/// Empties a poll's responses, keeping the poll and its link, e.g. after a test run
+async fn clear_responses(
+    Extension(db): Extension<Arc<Mutex<Db>>>,
+    Extension(results_cache): Extension<Arc<ResultsCache>>,
+    JsonBody(request, _): JsonBody<ClearResponsesRequest, ClearResponsesResult>,
+) -> impl IntoResponse {
+    Json(if let Ok(mut db) = db.lock() {
+        if let Some(poll_data) = db.0.get_mut(&request.poll_key) {
+            if poll_data.is_editor(&request.editor_secret) {
+                poll_data.clear_responses();
+                println!("Cleared responses to poll {}", request.poll_key);
+                results_cache.invalidate(request.poll_key);
+                db.write();
+                ClearResponsesResult::Success
+            } else {
+                ClearResponsesResult::Unauthorized
+            }
+        } else {
+            ClearResponsesResult::Error
+        }
+    } else {
+        ClearResponsesResult::Error
+    })
+}
+
+/// Adds one to an emoji's count, at most once per `REACTION_COOLDOWN` for each address
+async fn react(
+    Extension(db): Extension<Arc<Mutex<Db>>>,
+    Extension(limiter): Extension<Arc<ReactionLimiter>>,
+    Extension(results_cache): Extension<Arc<ResultsCache>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    JsonBody(request, _): JsonBody<ReactRequest, ReactResult>,
+) -> Json<ReactResult> {
+    if !REACTIONS.contains(&request.emoji.as_str()) {
+        println!("Rejected unknown reaction {:?}", request.emoji);
+        return Json(ReactResult::Error);
+    }
+    if can_view(&db, request.poll_key, &headers).await != Some(true) {
+        return Json(ReactResult::Error);
+    }
+    Json(if let Ok(mut db) = db.lock() {
+        if let Some(poll_data) = db.0.get_mut(&request.poll_key) {
+            if limiter.try_react(request.poll_key, peer.ip()) {
+                *poll_data.reactions.entry(request.emoji).or_default() += 1;
+                results_cache.invalidate(request.poll_key);
+                STATS_UNSAVED.store(true, Ordering::Relaxed);
+                ReactResult::Success
+            } else {
+                ReactResult::TooSoon
+            }
+        } else {
+            ReactResult::Error
+        }
+    } else {
+        ReactResult::Error
+    })
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 struct GetPollQuery {
     poll_key: u64,
 }
 
+/// Whether a request may see poll `key`: it has no view password, or the request sent it
+/// in `PASSWORD_HEADER`. `None` if there's no such poll.
+///
+/// The password is hashed after the db lock is released, and off the async threads, so
+/// guessing can't hold up other requests.
+async fn can_view(db: &Mutex<Db>, key: u64, headers: &HeaderMap) -> Option<bool> {
+    let hash = lock_db(db).0.get(&key)?.poll.view_password.clone();
+    let Some(hash) = hash else {
+        return Some(true);
+    };
+    let Some(password) = headers
+        .get(PASSWORD_HEADER)
+        .and_then(|password| password.to_str().ok())
+        .map(str::to_string)
+    else {
+        return Some(false);
+    };
+    let verified = tokio::task::spawn_blocking(move || verify_password(&password, &hash)).await;
+    Some(verified.unwrap_or(false))
+}
+
+/// Locks the db for reading. If a handler panicked while holding the lock, the mutex is
+/// poisoned, but the data is still readable, so recover it rather than failing forever.
+fn lock_db(db: &Mutex<Db>) -> MutexGuard<'_, Db> {
+    db.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// The client fetches polls from `/?poll_key=`, which is also the link people open in a
+/// browser. When serving the client, browsers asking for html get `index.html` instead.
+async fn get_root(
+    Extension(config): Extension<Config>,
+    Extension(db): Extension<Arc<Mutex<Db>>>,
+    headers: HeaderMap,
+    query: Option<Query<GetPollQuery>>,
+) -> Response {
+    let wants_html = headers
+        .get(header::ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("text/html"));
+    if let Some(static_dir) = &config.static_dir {
+        if wants_html || query.is_none() {
+            return serve_index(static_dir).await;
+        }
+    }
+    match query {
+        Some(query) => get_poll(Extension(db), headers, query)
+            .await
+            .into_response(),
+        None => StatusCode::BAD_REQUEST.into_response(),
+    }
+}
+
+async fn serve_index(static_dir: &Path) -> Response {
+    match tokio::fs::read_to_string(static_dir.join("index.html")).await {
+        Ok(index) => Html(index).into_response(),
+        Err(_) => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// The client's files, for anything no route above matches. Older links carried the poll
+/// key in the path, like `/42`, so those get `index.html` and the client reads the key.
+async fn serve_static(Extension(config): Extension<Config>, request: Request<Body>) -> Response {
+    let Some(static_dir) = &config.static_dir else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let segment = request.uri().path().trim_matches('/');
+    if !segment.is_empty() && segment.bytes().all(|b| b.is_ascii_digit()) {
+        return serve_index(static_dir).await;
+    }
+    match ServeDir::new(static_dir).oneshot(request).await {
+        Ok(response) => response.map(axum::body::boxed),
+        Err(e) => (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!("Failed to serve static file: {e}"),
+        )
+            .into_response(),
+    }
+}
+
 async fn get_poll(
     Extension(db): Extension<Arc<Mutex<Db>>>,
+    headers: HeaderMap,
     Query(get_poll_query): Query<GetPollQuery>,
-) -> impl IntoResponse {
+) -> Json<PollQueryResult> {
+    let Some(can_view) = can_view(&db, get_poll_query.poll_key, &headers).await else {
+        return Json(PollQueryResult::NotFound);
+    };
     Json(
-        if let Some(poll_data) = db.lock().unwrap().0.get(&get_poll_query.poll_key) {
-            PollQueryResult::Found(poll_data.poll.clone())
+        if let Some(poll_data) = lock_db(&db).0.get_mut(&get_poll_query.poll_key) {
+            poll_data.stats.views += 1;
+            STATS_UNSAVED.store(true, Ordering::Relaxed);
+            poll_data.poll_for_viewer(can_view)
         } else {
             PollQueryResult::NotFound
         },
     )
 }
 
-async fn get_progress(
+/// Most polls that can be asked for in one batch
+const MAX_BATCH: usize = 50;
+
+/// Several polls in the order asked for, read under a single lock. Polls with a view
+/// password come back as `PasswordRequired`.
+async fn get_polls(
+    Extension(db): Extension<Arc<Mutex<Db>>>,
+    Json(poll_keys): Json<Vec<u64>>,
+) -> Result<Json<Vec<PollQueryResult>>, StatusCode> {
+    if poll_keys.len() > MAX_BATCH {
+        return Err(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+    let db = lock_db(&db);
+    Ok(Json(
+        poll_keys
+            .iter()
+            .map(|key| match db.0.get(key) {
+                Some(poll_data) => poll_data.poll_for_viewer(false),
+                None => PollQueryResult::NotFound,
+            })
+            .collect(),
+    ))
+}
+
+/// Identifies a poll for an editor. The editor secret comes in `EDITOR_SECRET_HEADER`
+/// rather than the query, keeping it out of request logs and browser history.
+#[derive(Debug, Deserialize, Serialize)]
+struct EditorQuery {
+    poll_key: u64,
+}
+
+/// Whether the request sent one of the poll's editor secrets
+fn sent_editor_secret(poll_data: &PollData, headers: &HeaderMap) -> bool {
+    headers
+        .get(EDITOR_SECRET_HEADER)
+        .and_then(|secret| secret.to_str().ok())
+        .is_some_and(|secret| poll_data.is_editor(secret))
+}
+
+/// The full poll and its responses, in the same RON format as the data file
+async fn get_archive(
     Extension(db): Extension<Arc<Mutex<Db>>>,
-    Json(key): Json<u64>,
+    headers: HeaderMap,
+    Query(query): Query<EditorQuery>,
+) -> Result<impl IntoResponse, StatusCode> {
+    let db = lock_db(&db);
+    let poll_data = db.0.get(&query.poll_key).ok_or(StatusCode::NOT_FOUND)?;
+    if !sent_editor_secret(poll_data, &headers) {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    let archive = ron::ser::to_string_pretty(poll_data, ron_config()).map_err(|e| {
+        println!("Failed to archive poll {}: {e}", query.poll_key);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    Ok((
+        [(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"poll_{}.ron\"", query.poll_key),
+        )],
+        archive,
+    ))
+}
+
+/// Every responder's submissions, oldest first, in the order they first responded
+async fn get_responses(
+    Extension(db): Extension<Arc<Mutex<Db>>>,
+    headers: HeaderMap,
+    Query(query): Query<EditorQuery>,
+) -> Result<Json<Vec<ResponderHistory>>, StatusCode> {
+    let db = lock_db(&db);
+    let poll_data = db.0.get(&query.poll_key).ok_or(StatusCode::NOT_FOUND)?;
+    if !sent_editor_secret(poll_data, &headers) {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    Ok(Json(
+        poll_data
+            .response_order
+            .iter()
+            .map(|key| ResponderHistory {
+                responder: key.clone(),
+                submissions: poll_data.history.get(key).cloned().unwrap_or_default(),
+            })
+            .collect(),
+    ))
+}
+
+/// How many have viewed, responded to and followed the poll
+async fn get_stats(
+    Extension(db): Extension<Arc<Mutex<Db>>>,
+    headers: HeaderMap,
+    Query(query): Query<EditorQuery>,
+) -> Result<Json<PollStats>, StatusCode> {
+    let db = lock_db(&db);
+    let poll_data = db.0.get(&query.poll_key).ok_or(StatusCode::NOT_FOUND)?;
+    if !sent_editor_secret(poll_data, &headers) {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+    Ok(Json(poll_data.stats.clone()))
+}
+
+/// Recreates an archived poll under a new key, editable only with a fresh secret for
+/// whoever restored it. The archive's own secrets don't carry over, as anyone can restore.
+async fn restore(Extension(db): Extension<Arc<Mutex<Db>>>, archive: String) -> impl IntoResponse {
+    let mut poll_data = match ron::de::from_str::<PollData>(&archive) {
+        Ok(poll_data) => poll_data,
+        Err(e) => {
+            println!("Rejected malformed archive: {e}");
+            return Json(CreatePollResult::Error);
+        }
+    };
+    if let Err(e) = poll_data.validate() {
+        println!("Rejected archive: {e:#}");
+        return Json(CreatePollResult::Error);
+    }
+    // Don't trust the archived tallies
+    poll_data.update_results();
+    Json(if let Ok(mut db) = db.lock() {
+        let key = get_unused_key(&db);
+        println!("Restored Poll at {key}: {:?}", poll_data.poll);
+        let editor_secret = new_secret();
+        poll_data.editor_secrets = vec![editor_secret.clone()];
+        db.0.insert(key, poll_data);
+        db.write();
+        CreatePollResult::Success { key, editor_secret }
+    } else {
+        CreatePollResult::Error
+    })
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+struct HasRespondedQuery {
+    poll_key: u64,
+    user: String,
+    device_key: String,
+}
+
+async fn has_responded(
+    Extension(db): Extension<Arc<Mutex<Db>>>,
+    headers: HeaderMap,
+    Query(query): Query<HasRespondedQuery>,
 ) -> impl IntoResponse {
-    Json(if let Ok(db) = db.lock() {
-        let poll_data = db.0.get(&key).unwrap();
+    if can_view(&db, query.poll_key, &headers).await != Some(true) {
+        return Json(HasRespondedResult::Error);
+    }
+    Json(
+        if let Some(poll_data) = lock_db(&db).0.get(&query.poll_key) {
+            // Responses are kept under the same key `submit` picks for the poll
+            let response_key = match poll_data.poll.dedup {
+                DedupStrategy::ByName => Some(&query.user),
+                DedupStrategy::ByDevice if query.device_key.is_empty() => None,
+                DedupStrategy::ByDevice => Some(&query.device_key),
+                DedupStrategy::None => None,
+            };
+            HasRespondedResult::Success {
+                responded: response_key.is_some_and(|k| poll_data.responses.contains_key(k)),
+            }
+        } else {
+            HasRespondedResult::Error
+        },
+    )
+}
 
+/// Editors who send their secret see progress that's hidden until the poll closes
+async fn get_progress(
+    Extension(db): Extension<Arc<Mutex<Db>>>,
+    headers: HeaderMap,
+    JsonBody(key, _): JsonBody<u64, ProgressReportResult>,
+) -> impl IntoResponse {
+    let is_editor = lock_db(&db)
+        .0
+        .get(&key)
+        .is_some_and(|poll_data| sent_editor_secret(poll_data, &headers));
+    if !is_editor && can_view(&db, key, &headers).await != Some(true) {
+        return Json(ProgressReportResult::Error);
+    }
+    Json(if let Some(poll_data) = lock_db(&db).0.get_mut(&key) {
+        poll_data.stats.progress_fetches += 1;
+        STATS_UNSAVED.store(true, Ordering::Relaxed);
         ProgressReportResult::Success {
-            progress: PollProgress {
-                result_states: poll_data.result_states.clone(),
-                metric_progresses: poll_data
-                    .poll
-                    .metric_trackers
-                    .iter()
-                    .zip(poll_data.progresses.iter())
-                    .map(|(t, p)| {
-                        if t.publicly_visible {
-                            Some(p.clone())
-                        } else {
-                            None
-                        }
-                    })
-                    .collect(),
-            },
+            progress: poll_data.progress_for(is_editor, Utc::now()),
+            poll_fingerprint: Some(poll_data.poll.fingerprint()),
         }
     } else {
         ProgressReportResult::Error
     })
 }
 
+async fn get_schema() -> Json<serde_json::Value> {
+    Json(schema::api_schema())
+}
+
+/// Served from `ResultsCache`, and with an `ETag` and `Last-Modified` so unchanged
+/// results can be answered with a 304
+async fn get_results(
+    Extension(db): Extension<Arc<Mutex<Db>>>,
+    Extension(results_cache): Extension<Arc<ResultsCache>>,
+    Query(get_poll_query): Query<GetPollQuery>,
+    headers: HeaderMap,
+    if_none_match: Option<TypedHeader<IfNoneMatch>>,
+    if_modified_since: Option<TypedHeader<IfModifiedSince>>,
+) -> Response {
+    if can_view(&db, get_poll_query.poll_key, &headers).await == Some(false) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+    let db = lock_db(&db);
+    let cached = results_cache.get_or_compute(get_poll_query.poll_key, || {
+        let poll_data = db.0.get(&get_poll_query.poll_key)?;
+        let (hits, misses) = results_cache.stats();
+        tracing::debug!(hits, misses, "recomputing results");
+        let poll = &poll_data.poll;
+        let report = ResultsReportResult::Found(Box::new(ResultsReport {
+            schema_version: RESULTS_REPORT_SCHEMA_VERSION,
+            title: poll.title.clone(),
+            metric_labels: poll
+                .metric_trackers
+                .iter()
+                .map(|t| {
+                    if t.publicly_visible {
+                        Some(t.metric.render(&poll.questions))
+                    } else {
+                        None
+                    }
+                })
+                .collect(),
+            result_descs: poll.results.iter().map(|r| r.desc.clone()).collect(),
+            progress: poll_data.progress_for(false, Utc::now()),
+        }));
+        serde_json::to_string(&report).ok()
+    });
+    drop(db);
+    let Some(cached) = cached else {
+        return Json(ResultsReportResult::NotFound).into_response();
+    };
+    // If-None-Match wins when both are sent
+    let unchanged = match (if_none_match, if_modified_since) {
+        (Some(TypedHeader(if_none_match)), _) => !if_none_match.precondition_passes(&cached.etag),
+        (None, Some(TypedHeader(since))) => !since.is_modified(cached.last_modified),
+        (None, None) => false,
+    };
+    let headers = (
+        TypedHeader(cached.etag),
+        TypedHeader(LastModified::from(cached.last_modified)),
+    );
+    if unchanged {
+        (StatusCode::NOT_MODIFIED, headers).into_response()
+    } else {
+        (
+            headers,
+            [(header::CONTENT_TYPE, "application/json")],
+            cached.body,
+        )
+            .into_response()
+    }
+}
+
 #[derive(Clone)]
-struct Config {}
+struct Config {
+    bind_addr: SocketAddr,
+    /// Where the built client lives, if this server should host it too
+    static_dir: Option<PathBuf>,
+    /// How long closed polls are kept before being deleted. `None` keeps them forever.
+    poll_retention: Option<chrono::Duration>,
+}
 
 impl Config {
     pub fn new() -> Self {
-        Self {}
+        // e.g. `BIND_ADDR=0.0.0.0:8080`, for containers without a routable local ip
+        let bind_addr = match std::env::var("BIND_ADDR") {
+            Ok(addr) => addr
+                .parse()
+                .unwrap_or_else(|_| panic!("Failed to parse BIND_ADDR: {addr}")),
+            Err(_) => SocketAddr::from((local_ip().expect("Failed to get local ip address"), 443)),
+        };
+        let static_dir = std::env::var("STATIC_DIR").ok().map(PathBuf::from);
+        // e.g. `POLL_RETENTION_DAYS=90`
+        let poll_retention = std::env::var("POLL_RETENTION_DAYS").ok().map(|days| {
+            chrono::Duration::days(
+                days.parse()
+                    .unwrap_or_else(|_| panic!("Failed to parse POLL_RETENTION_DAYS: {days}")),
+            )
+        });
+        Self {
+            bind_addr,
+            static_dir,
+            poll_retention,
+        }
     }
 }
 
 #[derive(Deserialize, Serialize)]
 struct PollData {
     poll: Poll,
+    /// Secrets that each grant edit rights to the poll
+    #[serde(default)]
+    editor_secrets: Vec<String>,
     responses: HashMap<String, Vec<FormResponse>>,
+    /// Keys of `responses` in the order they were first submitted
+    #[serde(default)]
+    response_order: Vec<String>,
+    /// Each responder's last `MAX_HISTORY` submissions, oldest first. Only the latest,
+    /// kept in `responses`, is tallied.
+    #[serde(default)]
+    history: HashMap<String, Vec<Submission>>,
     progresses: Vec<Progress>,
     result_states: Vec<ResultState>,
+    /// How many times each of `REACTIONS` was sent. Separate from the responses, so they
+    /// never count toward a metric.
+    #[serde(default)]
+    reactions: BTreeMap<String, u64>,
+    /// Each response's `Poll::unique_values`, to check later responses against
+    #[serde(default)]
+    responder_values: HashMap<String, Vec<(ResponderField, String)>>,
+    /// When the poll was first seen closed without an expiration to say when it closed
+    #[serde(default)]
+    closed_at: Option<DateTime<Utc>>,
+    /// Views, submissions and progress fetches, flushed in batches
+    #[serde(default)]
+    stats: PollStats,
 }
 
 impl PollData {
-    pub fn update_results(&mut self) {
-        self.progresses = self
-            .poll
-            .metric_trackers
+    /// A poll before anyone has responded to it
+    fn new(poll: Poll) -> Self {
+        let (progresses, result_states) = poll.tally(&HashMap::new(), &[]);
+        Self {
+            poll,
+            editor_secrets: Vec::new(),
+            responses: HashMap::new(),
+            response_order: Vec::new(),
+            history: HashMap::new(),
+            progresses,
+            result_states,
+            reactions: BTreeMap::new(),
+            responder_values: HashMap::new(),
+            closed_at: None,
+            stats: PollStats::default(),
+        }
+    }
+
+    /// Whether the poll is valid and every response fits it, as for an uploaded archive
+    pub fn validate(&self) -> anyhow::Result<()> {
+        self.poll.validate().context("invalid poll")?;
+        if let Some(responder) = self
+            .responses
             .iter()
-            .map(|t| t.metric.calculate_progress(&self.responses))
-            .collect();
-        self.result_states = self
+            .find(|(_, responses)| !self.poll.validate_responses(responses))
+            .map(|(responder, _)| responder)
+        {
+            bail!("the response from {responder} doesn't match the questions");
+        }
+        Ok(())
+    }
+
+    pub fn is_editor(&self, editor_secret: &str) -> bool {
+        self.editor_secrets.iter().any(|s| s == editor_secret)
+    }
+
+    /// The poll without its password hash, if it needs none or `can_view` says the right
+    /// one was sent
+    pub fn poll_for_viewer(&self, can_view: bool) -> PollQueryResult {
+        if self.poll.view_password.is_some() && !can_view {
+            return PollQueryResult::PasswordRequired;
+        }
+        PollQueryResult::Found(Box::new(Poll {
+            view_password: None,
+            ..self.poll.clone()
+        }))
+    }
+
+    /// Forgets every response, its history and the reactions, and tallies the empty poll
+    pub fn clear_responses(&mut self) {
+        self.reactions.clear();
+        self.responses.clear();
+        self.response_order.clear();
+        self.history.clear();
+        self.responder_values.clear();
+        self.update_results();
+    }
+
+    /// When the poll closed, if it has. Polls closed by status rather than expiration
+    /// count from when this first notices.
+    pub fn closed_since(&mut self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self.poll.expiration {
+            Some(expiration) if expiration <= now => Some(expiration),
+            _ if self.poll.status == PollStatus::Closed => Some(*self.closed_at.get_or_insert(now)),
+            _ => {
+                self.closed_at = None;
+                None
+            }
+        }
+    }
+
+    /// Makes `responses` the latest for `key` and adds them to its history
+    pub fn record_submission(
+        &mut self,
+        key: String,
+        responses: Vec<FormResponse>,
+        time: DateTime<Utc>,
+    ) {
+        if !self.responses.contains_key(&key) {
+            self.response_order.push(key.clone());
+        }
+        let history = self.history.entry(key.clone()).or_default();
+        history.push((time, responses.clone()));
+        if history.len() > MAX_HISTORY {
+            history.drain(..history.len() - MAX_HISTORY);
+        }
+        self.responses.insert(key, responses);
+    }
+
+    /// Whether a response under `key` would go over `Poll::max_responses`. Edits never do.
+    pub fn is_full_for(&self, key: &str) -> bool {
+        self.poll.max_responses.is_some_and(|max| {
+            !self.responses.contains_key(key) && self.responses.len() as u64 >= max
+        })
+    }
+
+    /// `public_progress`, with the counts held back from non-editors if the poll only
+    /// reveals them once it's closed
+    pub fn progress_for(&self, is_editor: bool, now: DateTime<Utc>) -> PollProgress {
+        let mut progress = self.public_progress();
+        let too_few_responses = self
             .poll
-            .results
-            .iter()
-            .map(|r| {
-                r.requirements
+            .min_results_threshold
+            .is_some_and(|threshold| (self.responses.len() as u64) < threshold);
+        let hidden =
+            too_few_responses || (self.poll.reveal_when_closed && !self.poll.is_closed(now));
+        if hidden && !is_editor {
+            progress
+                .metric_progresses
+                .iter_mut()
+                .for_each(|p| *p = None);
+            progress
+                .question_breakdowns
+                .iter_mut()
+                .for_each(|b| *b = None);
+            progress.result_states = self
+                .poll
+                .results
+                .iter()
+                .map(ResultState::from_result)
+                .collect();
+            // With few responders, even these give away what each one picked
+            progress.seats_taken.iter_mut().for_each(|s| *s = None);
+            progress.quorum_met = false;
+            progress.responder_names = None;
+            progress.first_responders = None;
+            progress.hidden = true;
+        }
+        progress
+    }
+
+    /// Progress with the metrics that aren't publicly visible left out
+    pub fn public_progress(&self) -> PollProgress {
+        PollProgress {
+            result_states: self.result_states.clone(),
+            quorum_met: match self.poll.quorum {
+                Some(quorum) => self.responses.len() as u64 >= quorum,
+                None => true,
+            },
+            response_count: self.responses.len() as u64,
+            metric_progresses: self
+                .poll
+                .metric_trackers
+                .iter()
+                .zip(self.progresses.iter())
+                .map(|(t, p)| {
+                    if t.publicly_visible {
+                        Some(p.clone())
+                    } else {
+                        None
+                    }
+                })
+                .collect(),
+            question_breakdowns: self
+                .poll
+                .questions
+                .iter()
+                .enumerate()
+                .map(|(i, question)| {
+                    question
+                        .results_public
+                        .then(|| option_counts(&self.poll.questions, i, &self.responses).counts)
+                })
+                .collect(),
+            seats_taken: self
+                .poll
+                .questions
+                .iter()
+                .enumerate()
+                .map(|(i, question)| {
+                    question
+                        .form
+                        .capacities()
+                        .iter()
+                        .any(Option::is_some)
+                        .then(|| option_counts(&self.poll.questions, i, &self.responses).counts)
+                })
+                .collect(),
+            reactions: self.reactions.clone(),
+            hidden: false,
+            responder_names: self
+                .poll
+                .show_responder_names
+                .map(|i| responder_names(&self.poll.questions, i, &self.responses)),
+            // Edits don't move anyone, since only first submissions add to the order
+            first_responders: self.poll.show_first_responders.then(|| {
+                self.response_order
                     .iter()
-                    .map(|r| r.evaluate(&self.progresses))
-                    .collect::<Vec<_>>()
-            })
-            .map(|requirements_met| ResultState {
-                overall_met: requirements_met.iter().all(|x| *x),
-                requirements_met,
+                    .take(FIRST_RESPONDERS_SHOWN)
+                    .cloned()
+                    .collect()
+            }),
+        }
+    }
+
+    /// Recalculates progress and result states. Returns the indices of results that
+    /// weren't met before but are now.
+    pub fn update_results(&mut self) -> Vec<usize> {
+        let (progresses, result_states) = self.poll.tally(&self.responses, &self.response_order);
+        self.progresses = progresses;
+        let newly_met = result_states
+            .iter()
+            .enumerate()
+            .filter(|(i, state)| {
+                state.overall_met
+                    && !matches!(self.result_states.get(*i), Some(old) if old.overall_met)
             })
+            .map(|(i, _)| i)
             .collect();
+        self.result_states = result_states;
+        newly_met
     }
 }
 
@@ -245,23 +1150,46 @@ impl PollData {
 struct Db(HashMap<u64, PollData>);
 
 const DB_PATH: &str = "data.ron";
+/// How many submissions are remembered per responder
+const MAX_HISTORY: usize = 20;
+
+fn ron_config() -> PrettyConfig {
+    PrettyConfig::new()
+        .enumerate_arrays(true)
+        .extensions(Extensions::all())
+        .compact_arrays(true)
+}
 
 impl Db {
     pub fn write(&self) {
         fs::write(
             DB_PATH,
-            ron::ser::to_string_pretty(
-                self,
-                PrettyConfig::new()
-                    .enumerate_arrays(true)
-                    .extensions(Extensions::all())
-                    .compact_arrays(true),
-            )
-            .unwrap(),
+            ron::ser::to_string_pretty(self, ron_config()).unwrap(),
         )
         .unwrap();
     }
 
+    /// Deletes the polls closed for longer than `retention`. Returns how many went, and
+    /// whether any kept poll's `closed_at` changed, which needs writing too so that a
+    /// restart doesn't restart the retention period.
+    pub fn prune_closed(
+        &mut self,
+        retention: chrono::Duration,
+        now: DateTime<Utc>,
+    ) -> (usize, bool) {
+        let before = self.0.len();
+        let mut closings_changed = false;
+        self.0.retain(|_, poll_data| {
+            let closed_at = poll_data.closed_at;
+            let keep = poll_data
+                .closed_since(now)
+                .is_none_or(|closed| now - closed <= retention);
+            closings_changed |= keep && poll_data.closed_at != closed_at;
+            keep
+        });
+        (before - self.0.len(), closings_changed)
+    }
+
     fn get_from_file() -> Option<Self> {
         if let Ok(string) = fs::read_to_string(DB_PATH) {
             if let Ok(db) = ron::de::from_str(&string) {
@@ -281,7 +1209,12 @@ impl Db {
                     prompt: "Are you going?".to_string(),
                     form: Form::OneOrNone {
                         options: vec!["Yes".to_string(), "No".to_string()],
+                        capacities: Vec::new(),
                     },
+                    show_if: None,
+                    results_public: false,
+                    participant_visible: true,
+                    pinned_options: Vec::new(),
                 },
                 Question {
                     prompt: "How are you arriving?".to_string(),
@@ -291,7 +1224,12 @@ impl Db {
                             "Walking".to_string(),
                             "Uber".to_string(),
                         ],
+                        capacities: Vec::new(),
                     },
+                    show_if: None,
+                    results_public: false,
+                    participant_visible: true,
+                    pinned_options: Vec::new(),
                 },
                 Question {
                     prompt: "Which restaurant would you prefer?".to_string(),
@@ -303,35 +1241,47 @@ impl Db {
                             "Papasitos".to_string(),
                             "Taco Bell".to_string(),
                         ],
+                        capacities: Vec::new(),
                     },
+                    show_if: None,
+                    results_public: false,
+                    participant_visible: true,
+                    pinned_options: Vec::new(),
                 },
             ];
             db.0.insert(
                 0,
-                PollData {
-                    poll: Poll {
-                        title: "Test Poll".to_string(),
-                        announcement: None,
-                        description: "Today, 3pm, you know where".to_string(),
-                        expiration: None,
-                        results: vec![PollResult {
-                            requirements: vec![Requirement::AtLeast {
-                                metric_index: 0,
-                                minimum: 2,
-                            }],
-                            desc: "The party happens".to_string(),
+                PollData::new(Poll {
+                    title: "Test Poll".to_string(),
+                    announcement: None,
+                    quorum: None,
+                    max_responses: None,
+                    dedup: DedupStrategy::ByName,
+                    constraints: Vec::new(),
+                    reveal_when_closed: false,
+                    min_results_threshold: None,
+                    show_responder_names: None,
+                    show_first_responders: false,
+                    randomize_option_order: false,
+                    view_password: None,
+                    description: "Today, 3pm, you know where".to_string(),
+                    theme: None,
+                    expiration: None,
+                    edit_deadline: None,
+                    results: vec![PollResult {
+                        requirements: vec![Requirement::AtLeast {
+                            metric_index: 0,
+                            minimum: 2,
                         }],
-                        metric_trackers: vec![MetricTracker::init_from_questions(
-                            &default_questions,
-                        )
-                        .unwrap()],
-                        status: PollStatus::SeekingResponses,
-                        questions: default_questions,
-                    },
-                    responses: Default::default(),
-                    progresses: Vec::new(),
-                    result_states: Vec::new(),
-                },
+                        desc: "The party happens".to_string(),
+                        disabled: false,
+                    }],
+                    metric_trackers: vec![
+                        MetricTracker::init_from_questions(&default_questions).unwrap()
+                    ],
+                    status: PollStatus::SeekingResponses,
+                    questions: default_questions,
+                }),
             );
             db
         });
@@ -347,3 +1297,1027 @@ impl Db {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use areyougoing_shared::Constraint;
+    use axum::body::HttpBody;
+    use std::{marker::PhantomData, net::IpAddr, thread};
+
+    fn test_config() -> Config {
+        Config {
+            bind_addr: SocketAddr::from(([127, 0, 0, 1], 0)),
+            static_dir: None,
+            poll_retention: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn serves_polls_after_db_mutex_is_poisoned() {
+        let db = Arc::new(Mutex::new(Db::default()));
+        lock_db(&db).0.insert(1, PollData::new(Poll::default()));
+        {
+            let db = db.clone();
+            thread::spawn(move || {
+                let _guard = db.lock().unwrap();
+                panic!("Poisoning the db mutex");
+            })
+            .join()
+            .unwrap_err();
+        }
+        assert!(db.is_poisoned());
+
+        let Json(result) = get_poll(
+            Extension(db),
+            HeaderMap::new(),
+            Query(GetPollQuery { poll_key: 1 }),
+        )
+        .await;
+        assert!(matches!(result, PollQueryResult::Found(_)));
+    }
+
+    #[tokio::test]
+    async fn editors_see_how_far_their_poll_reached() {
+        let db = Arc::new(Mutex::new(Db::default()));
+        lock_db(&db).0.insert(
+            1,
+            PollData {
+                editor_secrets: vec!["secret".to_string()],
+                ..PollData::new(Poll::default())
+            },
+        );
+        for _ in 0..2 {
+            get_poll(
+                Extension(db.clone()),
+                HeaderMap::new(),
+                Query(GetPollQuery { poll_key: 1 }),
+            )
+            .await;
+        }
+        get_progress(
+            Extension(db.clone()),
+            HeaderMap::new(),
+            JsonBody(1, PhantomData),
+        )
+        .await;
+        assert!(STATS_UNSAVED.load(Ordering::Relaxed));
+
+        let stats = |editor_secret: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(EDITOR_SECRET_HEADER, editor_secret.parse().unwrap());
+            get_stats(
+                Extension(db.clone()),
+                headers,
+                Query(EditorQuery { poll_key: 1 }),
+            )
+        };
+        let Json(stats_seen) = stats("secret").await.unwrap();
+        assert_eq!(
+            stats_seen,
+            PollStats {
+                views: 2,
+                submissions: 0,
+                progress_fetches: 1,
+            }
+        );
+        assert_eq!(stats("guess").await.unwrap_err(), StatusCode::UNAUTHORIZED);
+    }
+
+    #[tokio::test]
+    async fn protected_polls_need_their_password() {
+        let poll_data = PollData::new(Poll {
+            view_password: Some(hash_password("hunter2")),
+            ..Default::default()
+        });
+        assert!(!poll_data
+            .poll
+            .view_password
+            .as_ref()
+            .unwrap()
+            .contains("hunter2"));
+        let db = Arc::new(Mutex::new(Db::default()));
+        lock_db(&db).0.insert(1, poll_data);
+        let app = app(test_config(), db);
+        let request = |method: Method, uri: &str, body: &str, password: Option<&str>| {
+            let mut request = http::Request::builder().method(method).uri(uri);
+            if let Some(password) = password {
+                request = request.header(PASSWORD_HEADER, password);
+            }
+            request
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(axum::body::Body::from(body.to_string()))
+                .unwrap()
+        };
+        let body = |response: Response| async move {
+            let body = response.into_body().data().await.unwrap().unwrap();
+            String::from_utf8(body.to_vec()).unwrap()
+        };
+
+        for password in [None, Some(""), Some("hunter3")] {
+            let response = app
+                .clone()
+                .oneshot(request(Method::GET, "/?poll_key=1", "", password))
+                .await
+                .unwrap();
+            assert_eq!(body(response).await, "\"PasswordRequired\"");
+            let response = app
+                .clone()
+                .oneshot(request(Method::POST, "/progress", "1", password))
+                .await
+                .unwrap();
+            assert_eq!(body(response).await, "\"Error\"");
+            let response = app
+                .clone()
+                .oneshot(request(
+                    Method::GET,
+                    "/has_responded?poll_key=1&user=Sandra&device_key=",
+                    "",
+                    password,
+                ))
+                .await
+                .unwrap();
+            assert_eq!(body(response).await, "\"Error\"");
+            let response = app
+                .clone()
+                .oneshot(request(
+                    Method::GET,
+                    "/results.json?poll_key=1",
+                    "",
+                    password,
+                ))
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        }
+
+        let response = app
+            .clone()
+            .oneshot(request(Method::GET, "/?poll_key=1", "", Some("hunter2")))
+            .await
+            .unwrap();
+        let PollQueryResult::Found(poll) = serde_json::from_str(&body(response).await).unwrap()
+        else {
+            panic!("The right password should show the poll");
+        };
+        assert_eq!(poll.view_password, None);
+        let response = app
+            .clone()
+            .oneshot(request(Method::POST, "/progress", "1", Some("hunter2")))
+            .await
+            .unwrap();
+        assert!(matches!(
+            serde_json::from_str(&body(response).await).unwrap(),
+            ProgressReportResult::Success { .. }
+        ));
+        let response = app
+            .oneshot(request(
+                Method::GET,
+                "/results.json?poll_key=1",
+                "",
+                Some("hunter2"),
+            ))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn editor_secrets_only_work_in_their_header() {
+        let poll_data = PollData {
+            editor_secrets: vec!["secret".to_string()],
+            ..PollData::new(Poll {
+                title: "Picnic".to_string(),
+                questions: vec![Question {
+                    prompt: "Are you going?".to_string(),
+                    form: Form::YesNo,
+                    show_if: None,
+                    results_public: true,
+                    participant_visible: true,
+                    pinned_options: Vec::new(),
+                }],
+                ..Default::default()
+            })
+        };
+        let db = Arc::new(Mutex::new(Db::default()));
+        lock_db(&db).0.insert(1, poll_data);
+        let app = app(test_config(), db);
+        let archive = |uri: String, editor_secret: Option<&str>| {
+            let mut request = http::Request::builder().uri(uri);
+            if let Some(editor_secret) = editor_secret {
+                request = request.header(EDITOR_SECRET_HEADER, editor_secret);
+            }
+            app.clone()
+                .oneshot(request.body(axum::body::Body::empty()).unwrap())
+        };
+
+        let response = archive("/archive?poll_key=1&editor_secret=secret".to_string(), None)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = archive("/archive?poll_key=1".to_string(), Some("secret"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let backup = response.into_body().data().await.unwrap().unwrap();
+
+        // Only the restorer's fresh secret edits the copy
+        let response = app
+            .clone()
+            .oneshot(
+                http::Request::builder()
+                    .method(Method::POST)
+                    .uri("/restore")
+                    .body(axum::body::Body::from(backup))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let body = response.into_body().data().await.unwrap().unwrap();
+        let CreatePollResult::Success { key, editor_secret } =
+            serde_json::from_slice(&body).unwrap()
+        else {
+            panic!("The backup should restore");
+        };
+        let response = archive(format!("/archive?poll_key={key}"), Some("secret"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = archive(format!("/archive?poll_key={key}"), Some(&editor_secret))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn older_links_with_the_key_in_the_path_open_the_client() {
+        let static_dir = std::env::temp_dir().join(format!("ayg_static_{}", std::process::id()));
+        fs::create_dir_all(&static_dir).unwrap();
+        fs::write(static_dir.join("index.html"), "<html>client</html>").unwrap();
+        fs::write(static_dir.join("client.js"), "run()").unwrap();
+        let config = Config {
+            static_dir: Some(static_dir.clone()),
+            ..test_config()
+        };
+        let app = app(config, Arc::new(Mutex::new(Db::default())));
+        let get = |uri: &str| {
+            app.clone().oneshot(
+                http::Request::builder()
+                    .uri(uri)
+                    .body(axum::body::Body::empty())
+                    .unwrap(),
+            )
+        };
+        let body = |response: Response| async move {
+            let body = response.into_body().data().await.unwrap().unwrap();
+            String::from_utf8(body.to_vec()).unwrap()
+        };
+
+        for uri in ["/42", "/42/"] {
+            let response = get(uri).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK);
+            assert_eq!(body(response).await, "<html>client</html>");
+        }
+        let response = get("/client.js").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body(response).await, "run()");
+        for uri in ["/42x", "/42/client.js"] {
+            assert_eq!(get(uri).await.unwrap().status(), StatusCode::NOT_FOUND);
+        }
+        fs::remove_dir_all(static_dir).unwrap();
+    }
+
+    #[test]
+    fn cleared_polls_take_fresh_responses() {
+        let mut poll_data = PollData::new(Poll {
+            questions: vec![Question {
+                prompt: "Are you going?".to_string(),
+                form: Form::YesNo,
+                show_if: None,
+                results_public: true,
+                participant_visible: true,
+                pinned_options: Vec::new(),
+            }],
+            metric_trackers: vec![MetricTracker {
+                metric: areyougoing_shared::Metric::SpecificResponses {
+                    question_index: 0,
+                    choice: areyougoing_shared::Choice::YesOrNo(true),
+                },
+                publicly_visible: true,
+            }],
+            ..Default::default()
+        });
+        let yes = || {
+            vec![FormResponse::ChooseOne(
+                areyougoing_shared::Choice::YesOrNo(true),
+            )]
+        };
+        poll_data.record_submission("Sandra".to_string(), yes(), Utc::now());
+        poll_data.record_submission("Peter".to_string(), yes(), Utc::now());
+        poll_data.update_results();
+
+        poll_data.clear_responses();
+        let progress = poll_data.public_progress();
+        assert_eq!(progress.metric_progresses, vec![Some(Progress::Count(0))]);
+        assert_eq!(progress.question_breakdowns, vec![Some(vec![0, 0])]);
+        assert!(poll_data.history.is_empty());
+
+        poll_data.record_submission("Bob".to_string(), yes(), Utc::now());
+        poll_data.update_results();
+        assert_eq!(
+            poll_data.public_progress().metric_progresses,
+            vec![Some(Progress::Count(1))]
+        );
+        assert_eq!(poll_data.response_order, vec!["Bob".to_string()]);
+    }
+
+    #[test]
+    fn reactions_have_a_cooldown_per_address() {
+        let limiter = ReactionLimiter::default();
+        let sandra = IpAddr::from([192, 168, 0, 2]);
+        let peter = IpAddr::from([192, 168, 0, 3]);
+        assert!(limiter.try_react(1, sandra));
+        assert!(!limiter.try_react(1, sandra));
+        assert!(limiter.try_react(2, sandra));
+        assert!(limiter.try_react(1, peter));
+    }
+
+    #[test]
+    fn db_loads_responses_it_does_not_recognize() {
+        let mut db = Db::default();
+        db.0.insert(
+            1,
+            PollData {
+                responses: [("Sandra".to_string(), vec![FormResponse::Number(3)])]
+                    .into_iter()
+                    .collect(),
+                response_order: vec!["Sandra".to_string()],
+                ..PollData::new(Poll::default())
+            },
+        );
+        let stored = ron::ser::to_string_pretty(&db, ron_config()).unwrap();
+        assert!(stored.contains("Number(3)"));
+        // As if a newer version had stored a kind of response this one doesn't know
+        let stored = stored.replace("Number(3)", "Signature(\"Sandra\")");
+        let db = ron::de::from_str::<Db>(&stored).unwrap();
+        assert_eq!(db.0[&1].responses["Sandra"], vec![FormResponse::Unknown]);
+    }
+
+    #[test]
+    fn history_keeps_only_recent_submissions() {
+        let mut poll_data = PollData::new(Poll::default());
+        for i in 0..MAX_HISTORY as i64 + 5 {
+            let responses = vec![FormResponse::Number(i)];
+            poll_data.record_submission("Sandra".to_string(), responses, Utc::now());
+        }
+        let history = &poll_data.history["Sandra"];
+        assert_eq!(history.len(), MAX_HISTORY);
+        assert_eq!(history[0].1, vec![FormResponse::Number(5)]);
+        assert_eq!(history.last().unwrap().1, poll_data.responses["Sandra"]);
+        assert_eq!(poll_data.response_order, vec!["Sandra".to_string()]);
+    }
+
+    #[test]
+    fn counts_stay_hidden_until_poll_closes() {
+        let mut poll_data = PollData {
+            progresses: vec![Progress::Count(3)],
+            ..PollData::new(Poll {
+                questions: vec![Question {
+                    prompt: "Are you going?".to_string(),
+                    form: Form::YesNo,
+                    show_if: None,
+                    results_public: false,
+                    participant_visible: true,
+                    pinned_options: Vec::new(),
+                }],
+                metric_trackers: vec![MetricTracker {
+                    metric: areyougoing_shared::Metric::Plurality { question_index: 0 },
+                    publicly_visible: true,
+                }],
+                reveal_when_closed: true,
+                ..Default::default()
+            })
+        };
+        let now = Utc::now();
+
+        let hidden = poll_data.progress_for(false, now);
+        assert!(hidden.hidden);
+        assert_eq!(hidden.metric_progresses, vec![None]);
+        let owner = poll_data.progress_for(true, now);
+        assert!(!owner.hidden);
+        assert_eq!(owner.metric_progresses, vec![Some(Progress::Count(3))]);
+
+        poll_data.poll.expiration = Some(now);
+        let expired = poll_data.progress_for(false, now);
+        assert!(!expired.hidden);
+        assert_eq!(expired.metric_progresses, vec![Some(Progress::Count(3))]);
+
+        poll_data.poll.expiration = None;
+        poll_data.poll.status = PollStatus::Closed;
+        let closed = poll_data.progress_for(false, now);
+        assert!(!closed.hidden);
+        assert_eq!(closed.metric_progresses, vec![Some(Progress::Count(3))]);
+    }
+
+    #[test]
+    fn results_wait_for_enough_responses() {
+        let mut poll_data = PollData::new(Poll {
+            questions: vec![
+                Question {
+                    prompt: "Are you going?".to_string(),
+                    form: Form::YesNo,
+                    show_if: None,
+                    results_public: true,
+                    participant_visible: true,
+                    pinned_options: Vec::new(),
+                },
+                Question {
+                    prompt: "Which shift?".to_string(),
+                    form: Form::One {
+                        options: vec!["Early".to_string(), "Late".to_string()],
+                        capacities: vec![Some(5), Some(5)],
+                        none_of_the_above: false,
+                    },
+                    show_if: None,
+                    results_public: false,
+                    participant_visible: true,
+                    pinned_options: Vec::new(),
+                },
+            ],
+            metric_trackers: vec![MetricTracker {
+                metric: areyougoing_shared::Metric::SpecificResponses {
+                    question_index: 0,
+                    choice: areyougoing_shared::Choice::YesOrNo(true),
+                },
+                publicly_visible: true,
+            }],
+            min_results_threshold: Some(2),
+            quorum: Some(1),
+            ..Default::default()
+        });
+        let yes = || {
+            vec![
+                FormResponse::ChooseOne(areyougoing_shared::Choice::YesOrNo(true)),
+                FormResponse::ChooseOne(areyougoing_shared::Choice::Index(0)),
+            ]
+        };
+        let now = Utc::now();
+
+        poll_data.record_submission("Sandra".to_string(), yes(), now);
+        poll_data.update_results();
+        let early = poll_data.progress_for(false, now);
+        assert!(early.hidden);
+        assert_eq!(early.metric_progresses, vec![None]);
+        assert_eq!(early.question_breakdowns, vec![None, None]);
+        assert_eq!(early.seats_taken, vec![None, None]);
+        assert!(!early.quorum_met);
+        let owner = poll_data.progress_for(true, now);
+        assert!(!owner.hidden);
+        assert_eq!(owner.metric_progresses, vec![Some(Progress::Count(1))]);
+        assert_eq!(owner.seats_taken, vec![None, Some(vec![1, 0])]);
+        assert!(owner.quorum_met);
+
+        poll_data.record_submission("Peter".to_string(), yes(), now);
+        poll_data.update_results();
+        let enough = poll_data.progress_for(false, now);
+        assert!(!enough.hidden);
+        assert_eq!(enough.metric_progresses, vec![Some(Progress::Count(2))]);
+        assert_eq!(enough.question_breakdowns, vec![Some(vec![0, 2]), None]);
+        assert_eq!(enough.seats_taken, vec![None, Some(vec![2, 0])]);
+    }
+
+    #[test]
+    fn responder_names_are_grouped_by_answer() {
+        let mut poll_data = PollData::new(Poll {
+            questions: vec![Question {
+                prompt: "Are you going?".to_string(),
+                form: Form::YesNo,
+                show_if: None,
+                results_public: false,
+                participant_visible: true,
+                pinned_options: Vec::new(),
+            }],
+            min_results_threshold: Some(3),
+            show_responder_names: Some(0),
+            ..Default::default()
+        });
+        let answer = |going| {
+            vec![FormResponse::ChooseOne(
+                areyougoing_shared::Choice::YesOrNo(going),
+            )]
+        };
+        let now = Utc::now();
+
+        poll_data.record_submission("Sandra".to_string(), answer(true), now);
+        poll_data.record_submission("Peter".to_string(), answer(false), now);
+        assert_eq!(poll_data.progress_for(false, now).responder_names, None);
+
+        poll_data.record_submission("Bob".to_string(), answer(true), now);
+        assert_eq!(
+            poll_data.progress_for(false, now).responder_names,
+            Some(vec![
+                vec!["Peter".to_string()],
+                vec!["Bob".to_string(), "Sandra".to_string()],
+            ])
+        );
+    }
+
+    #[test]
+    fn archives_tally_offline_from_ron_or_json() {
+        let mut poll_data = PollData::new(Poll {
+            title: "Party".to_string(),
+            questions: vec![Question {
+                prompt: "Are you going?".to_string(),
+                form: Form::YesNo,
+                show_if: None,
+                results_public: false,
+                participant_visible: true,
+                pinned_options: Vec::new(),
+            }],
+            metric_trackers: vec![MetricTracker {
+                metric: areyougoing_shared::Metric::SpecificResponses {
+                    question_index: 0,
+                    choice: areyougoing_shared::Choice::YesOrNo(true),
+                },
+                publicly_visible: false,
+            }],
+            ..Default::default()
+        });
+        let answer = |going| {
+            vec![FormResponse::ChooseOne(
+                areyougoing_shared::Choice::YesOrNo(going),
+            )]
+        };
+        let now = Utc::now();
+        poll_data.record_submission("Sandra".to_string(), answer(true), now);
+        poll_data.record_submission("Peter".to_string(), answer(true), now);
+        poll_data.record_submission("Bob".to_string(), answer(false), now);
+
+        let ron = ron::ser::to_string_pretty(&poll_data, ron_config()).unwrap();
+        let json = serde_json::to_string(&poll_data).unwrap();
+        for (path, contents) in [("poll.ron", ron), ("poll.json", json)] {
+            let archive = tally::read_archive(Path::new(path), &contents).unwrap();
+            // Private metrics are counted too, without the stored tallies
+            assert_eq!(
+                tally::tally(&archive).metric_progresses,
+                vec![Some(Progress::Count(2))],
+                "{path}"
+            );
+        }
+
+        assert!(tally::read_archive(Path::new("poll.json"), "{\"poll\": ").is_err());
+        poll_data
+            .responses
+            .insert("Alice".to_string(), vec![FormResponse::Number(1)]);
+        let ron = ron::ser::to_string_pretty(&poll_data, ron_config()).unwrap();
+        assert!(tally::read_archive(Path::new("poll.ron"), &ron).is_err());
+    }
+
+    #[test]
+    fn first_responders_keep_their_place_after_editing() {
+        let mut poll_data = PollData::new(Poll {
+            questions: vec![Question {
+                prompt: "Are you going?".to_string(),
+                form: Form::YesNo,
+                show_if: None,
+                results_public: false,
+                participant_visible: true,
+                pinned_options: Vec::new(),
+            }],
+            show_first_responders: true,
+            ..Default::default()
+        });
+        let answer = |going| {
+            vec![FormResponse::ChooseOne(
+                areyougoing_shared::Choice::YesOrNo(going),
+            )]
+        };
+        let now = Utc::now();
+
+        for (name, going) in [
+            ("Sandra", true),
+            ("Peter", false),
+            ("Sandra", false),
+            ("Bob", true),
+            ("Alice", true),
+        ] {
+            poll_data.record_submission(name.to_string(), answer(going), now);
+        }
+        assert_eq!(
+            poll_data.progress_for(false, now).first_responders,
+            Some(vec![
+                "Sandra".to_string(),
+                "Peter".to_string(),
+                "Bob".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn full_polls_only_take_edits() {
+        let mut poll_data = PollData::new(Poll {
+            questions: vec![Question {
+                prompt: "Are you going?".to_string(),
+                form: Form::YesNo,
+                show_if: None,
+                results_public: false,
+                participant_visible: true,
+                pinned_options: Vec::new(),
+            }],
+            max_responses: Some(2),
+            ..Default::default()
+        });
+        let yes = || {
+            vec![FormResponse::ChooseOne(
+                areyougoing_shared::Choice::YesOrNo(true),
+            )]
+        };
+        let now = Utc::now();
+
+        poll_data.record_submission("Sandra".to_string(), yes(), now);
+        assert!(!poll_data.is_full_for("Peter"));
+        poll_data.record_submission("Peter".to_string(), yes(), now);
+        assert!(poll_data.is_full_for("Bob"));
+        assert!(!poll_data.is_full_for("Sandra"));
+        assert_eq!(poll_data.progress_for(false, now).response_count, 2);
+    }
+
+    #[test]
+    fn long_closed_polls_are_pruned() {
+        let now = Utc::now();
+        let poll_data = |poll| PollData::new(poll);
+        let expired = |days| Poll {
+            expiration: Some(now - chrono::Duration::days(days)),
+            ..Default::default()
+        };
+        let mut db = Db(HashMap::new());
+        db.0.insert(1, poll_data(Poll::default()));
+        db.0.insert(2, poll_data(expired(100)));
+        db.0.insert(3, poll_data(expired(1)));
+        db.0.insert(
+            4,
+            poll_data(Poll {
+                status: PollStatus::Closed,
+                ..Default::default()
+            }),
+        );
+        // The closed poll's closing time is new, so it has to be saved
+        assert_eq!(db.prune_closed(chrono::Duration::days(30), now), (1, true));
+        let mut kept = db.0.keys().copied().collect::<Vec<_>>();
+        kept.sort_unstable();
+        assert_eq!(kept, vec![1, 3, 4]);
+        // Closed without an expiration, so it's counted from when it was first seen
+        let later = now + chrono::Duration::days(31);
+        assert_eq!(
+            db.prune_closed(chrono::Duration::days(30), later),
+            (2, false)
+        );
+        assert_eq!(db.0.keys().copied().collect::<Vec<_>>(), vec![1]);
+        assert_eq!(
+            db.prune_closed(chrono::Duration::days(30), later),
+            (0, false)
+        );
+    }
+
+    #[tokio::test]
+    async fn late_edits_are_rejected() {
+        let db = Arc::new(Mutex::new(Db::default()));
+        lock_db(&db).0.insert(
+            1,
+            PollData {
+                responses: [("Sandra".to_string(), Vec::new())].into_iter().collect(),
+                response_order: vec!["Sandra".to_string()],
+                ..PollData::new(Poll {
+                    edit_deadline: Some(Utc::now()),
+                    ..Default::default()
+                })
+            },
+        );
+        let Json(result) = submit(
+            Extension(db.clone()),
+            Extension(Arc::new(LoggingNotifier) as Arc<dyn SubmitNotifier>),
+            Extension(Arc::new(ResultsCache::default())),
+            HeaderMap::new(),
+            JsonBody(
+                PollResponse {
+                    poll_id: 1,
+                    user: "Sandra".to_string(),
+                    device_key: String::new(),
+                    email: None,
+                    responses: Vec::new(),
+                },
+                PhantomData,
+            ),
+        )
+        .await;
+        assert!(matches!(result, PollSubmissionResult::EditWindowClosed));
+        assert!(lock_db(&db).0[&1].history.is_empty());
+    }
+
+    #[tokio::test]
+    async fn responses_by_device_need_a_device_key() {
+        let db = Arc::new(Mutex::new(Db::default()));
+        lock_db(&db).0.insert(
+            1,
+            PollData {
+                responses: [(String::new(), Vec::new())].into_iter().collect(),
+                response_order: vec![String::new()],
+                ..PollData::new(Poll {
+                    dedup: DedupStrategy::ByDevice,
+                    ..Default::default()
+                })
+            },
+        );
+        let Json(result) = submit(
+            Extension(db.clone()),
+            Extension(Arc::new(LoggingNotifier) as Arc<dyn SubmitNotifier>),
+            Extension(Arc::new(ResultsCache::default())),
+            HeaderMap::new(),
+            JsonBody(
+                PollResponse {
+                    poll_id: 1,
+                    user: "Peter".to_string(),
+                    device_key: String::new(),
+                    email: None,
+                    responses: Vec::new(),
+                },
+                PhantomData,
+            ),
+        )
+        .await;
+        assert!(matches!(result, PollSubmissionResult::Error));
+        assert!(lock_db(&db).0[&1].history.is_empty());
+    }
+
+    #[tokio::test]
+    async fn responses_by_device_are_found_by_device_key() {
+        let db = Arc::new(Mutex::new(Db::default()));
+        lock_db(&db).0.insert(
+            1,
+            PollData {
+                responses: [("device-a".to_string(), Vec::new())].into_iter().collect(),
+                response_order: vec!["device-a".to_string()],
+                ..PollData::new(Poll {
+                    dedup: DedupStrategy::ByDevice,
+                    ..Default::default()
+                })
+            },
+        );
+        for (user, device_key, expected) in [
+            ("Sandra", "device-a", true),
+            ("Peter", "device-a", true),
+            ("device-a", "device-b", false),
+            ("device-a", "", false),
+        ] {
+            let result = has_responded(
+                Extension(db.clone()),
+                HeaderMap::new(),
+                Query(HasRespondedQuery {
+                    poll_key: 1,
+                    user: user.to_string(),
+                    device_key: device_key.to_string(),
+                }),
+            )
+            .await
+            .into_response();
+            let body = result.into_body().data().await.unwrap().unwrap();
+            let result: HasRespondedResult = serde_json::from_slice(&body).unwrap();
+            assert!(
+                matches!(result, HasRespondedResult::Success { responded } if responded == expected),
+                "{user} on {device_key:?}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn constrained_responses_are_turned_away() {
+        let db = Arc::new(Mutex::new(Db::default()));
+        lock_db(&db).0.insert(
+            1,
+            PollData {
+                responses: [("Sandra".to_string(), Vec::new())].into_iter().collect(),
+                response_order: vec!["Sandra".to_string()],
+                responder_values: [(
+                    "Sandra".to_string(),
+                    vec![(ResponderField::EmailDomain, "example.com".to_string())],
+                )]
+                .into_iter()
+                .collect(),
+                ..PollData::new(Poll {
+                    constraints: vec![Constraint::UniqueBy(ResponderField::EmailDomain)],
+                    ..Default::default()
+                })
+            },
+        );
+        for email in [Some("peter@Example.com"), None] {
+            let Json(result) = submit(
+                Extension(db.clone()),
+                Extension(Arc::new(LoggingNotifier) as Arc<dyn SubmitNotifier>),
+                Extension(Arc::new(ResultsCache::default())),
+                HeaderMap::new(),
+                JsonBody(
+                    PollResponse {
+                        poll_id: 1,
+                        user: "Peter".to_string(),
+                        device_key: String::new(),
+                        email: email.map(str::to_string),
+                        responses: Vec::new(),
+                    },
+                    PhantomData,
+                ),
+            )
+            .await;
+            assert!(matches!(
+                result,
+                PollSubmissionResult::ConstraintViolated { .. }
+            ));
+        }
+        assert_eq!(lock_db(&db).0[&1].responses.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn schema_describes_every_poll_field() {
+        let config = test_config();
+        let request = http::Request::builder()
+            .uri("/schema")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = app(config, Default::default())
+            .oneshot(request)
+            .await
+            .unwrap();
+        assert!(response.status().is_success());
+        let body = response.into_body().data().await.unwrap().unwrap();
+        let schema: serde_json::Value = serde_json::from_slice(&body).unwrap();
+
+        let serde_json::Value::Object(poll) = serde_json::to_value(Poll::default()).unwrap() else {
+            panic!("Polls serialize as objects");
+        };
+        let properties = schema["definitions"]["Poll"]["properties"]
+            .as_object()
+            .unwrap();
+        for field in poll.keys() {
+            assert!(
+                properties.contains_key(field),
+                "{field} missing from the schema"
+            );
+        }
+        assert_eq!(properties.len(), poll.len());
+        for name in ["PollResponse", "PollProgress", "PollSubmissionResult"] {
+            assert!(schema["definitions"][name].is_object(), "{name} missing");
+        }
+    }
+
+    #[tokio::test]
+    async fn cors_preflight_allows_post() {
+        let config = test_config();
+        let request = http::Request::builder()
+            .method(Method::OPTIONS)
+            .uri("/submit")
+            .header(header::ORIGIN, "https://client.example")
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "content-type")
+            .body(axum::body::Body::empty())
+            .unwrap();
+        let response = app(config, Default::default())
+            .oneshot(request)
+            .await
+            .unwrap();
+
+        assert!(response.status().is_success());
+        let allowed_methods = response
+            .headers()
+            .get(header::ACCESS_CONTROL_ALLOW_METHODS)
+            .unwrap()
+            .to_str()
+            .unwrap();
+        assert!(allowed_methods.contains("POST"));
+    }
+
+    #[tokio::test]
+    async fn malformed_bodies_get_the_endpoint_error() {
+        let config = test_config();
+        let request = http::Request::builder()
+            .method(Method::POST)
+            .uri("/new_poll")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from("{\"title\": "))
+            .unwrap();
+        let response = app(config, Default::default())
+            .oneshot(request)
+            .await
+            .unwrap();
+
+        assert!(response.status().is_client_error());
+        let body = response.into_body().data().await.unwrap().unwrap();
+        assert!(matches!(
+            serde_json::from_slice(&body).unwrap(),
+            CreatePollResult::Error
+        ));
+    }
+
+    #[tokio::test]
+    async fn responses_echo_request_id() {
+        let config = test_config();
+        let request = |id: Option<&str>| {
+            let mut request = http::Request::builder().uri("/?poll_key=1");
+            if let Some(id) = id {
+                request = request.header(REQUEST_ID_HEADER, id);
+            }
+            request.body(Body::empty()).unwrap()
+        };
+        let app = app(config, Default::default());
+
+        let response = app.clone().oneshot(request(Some("abc123"))).await.unwrap();
+        assert_eq!(response.headers()[REQUEST_ID_HEADER], "abc123");
+        let response = app.oneshot(request(None)).await.unwrap();
+        assert!(!response.headers()[REQUEST_ID_HEADER].is_empty());
+    }
+
+    #[tokio::test]
+    async fn batch_keeps_order_and_caps_size() {
+        let db = Arc::new(Mutex::new(Db::default()));
+        for key in [1, 3] {
+            lock_db(&db).0.insert(
+                key,
+                PollData::new(Poll {
+                    title: format!("Poll {key}"),
+                    ..Default::default()
+                }),
+            );
+        }
+        let Ok(Json(results)) = get_polls(Extension(db.clone()), Json(vec![3, 2, 1])).await else {
+            panic!("a small batch should succeed");
+        };
+        let titles = results
+            .into_iter()
+            .map(|result| match result {
+                PollQueryResult::Found(poll) => Some(poll.title),
+                PollQueryResult::NotFound | PollQueryResult::PasswordRequired => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(
+            titles,
+            vec![Some("Poll 3".to_string()), None, Some("Poll 1".to_string())]
+        );
+
+        let too_many = get_polls(Extension(db), Json(vec![1; MAX_BATCH + 1])).await;
+        assert!(matches!(too_many, Err(StatusCode::PAYLOAD_TOO_LARGE)));
+    }
+
+    #[tokio::test]
+    async fn unchanged_results_are_not_modified() {
+        let config = test_config();
+        let db = Arc::new(Mutex::new(Db::default()));
+        lock_db(&db).0.insert(1, PollData::new(Poll::default()));
+        let request = |etag: Option<&http::HeaderValue>| {
+            let mut request = http::Request::builder().uri("/results.json?poll_key=1");
+            if let Some(etag) = etag {
+                request = request.header(header::IF_NONE_MATCH, etag);
+            }
+            request.body(Body::empty()).unwrap()
+        };
+        let app = app(config, db);
+
+        let response = app.clone().oneshot(request(None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(response.headers().contains_key(header::LAST_MODIFIED));
+        let etag = response.headers()[header::ETAG].clone();
+        let response = app.oneshot(request(Some(&etag))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(response.headers()[header::ETAG], etag);
+    }
+
+    #[tokio::test]
+    async fn compresses_responses_when_accepted() {
+        let config = test_config();
+        let db = Arc::new(Mutex::new(Db::default()));
+        lock_db(&db).0.insert(
+            1,
+            PollData::new(Poll {
+                title: "A poll long enough to be worth compressing".to_string(),
+                ..Default::default()
+            }),
+        );
+        let request = |encoding: Option<&str>| {
+            let mut request = http::Request::builder()
+                .uri("/?poll_key=1")
+                .header(header::ORIGIN, "https://client.example");
+            if let Some(encoding) = encoding {
+                request = request.header(header::ACCEPT_ENCODING, encoding);
+            }
+            request.body(Body::empty()).unwrap()
+        };
+        let app = app(config, db);
+
+        for encoding in ["gzip", "br"] {
+            let response = app.clone().oneshot(request(Some(encoding))).await.unwrap();
+            let headers = response.headers();
+            assert_eq!(headers[header::CONTENT_ENCODING], encoding);
+            assert_eq!(headers[header::CONTENT_TYPE], "application/json");
+            assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], "*");
+        }
+        let response = app.oneshot(request(None)).await.unwrap();
+        assert!(!response.headers().contains_key(header::CONTENT_ENCODING));
+    }
+}
@@ -0,0 +1,22 @@
+use areyougoing_shared::{Poll, PollResponse};
+
+/// Called after a response is successfully submitted, e.g. to email the responder a
+/// confirmation. Swap in a real sender in `main`; the default only logs.
+pub trait SubmitNotifier: Send + Sync {
+    fn notify(&self, poll_key: u64, poll: &Poll, response: &PollResponse);
+}
+
+/// Logs what would have been sent, so nothing goes out during development or tests
+pub struct LoggingNotifier;
+
+impl SubmitNotifier for LoggingNotifier {
+    fn notify(&self, poll_key: u64, poll: &Poll, response: &PollResponse) {
+        // The address itself stays out of the logs
+        if response.email.is_some() {
+            println!(
+                "Would send a confirmation for poll {poll_key} ({}) to {}'s email",
+                poll.title, response.user
+            );
+        }
+    }
+}
@@ -0,0 +1,53 @@
+use std::num::NonZeroU32;
+
+use ring::{digest::SHA256_OUTPUT_LEN, pbkdf2};
+
+const ITERATIONS: u32 = 100_000;
+const SALT_LEN: usize = 16;
+
+/// A salted PBKDF2 hash of `password`, as `salt$hash` in hex, for storing in place of it
+pub fn hash_password(password: &str) -> String {
+    let salt = rand::random::<[u8; SALT_LEN]>();
+    let mut hash = [0; SHA256_OUTPUT_LEN];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        iterations(),
+        &salt,
+        password.as_bytes(),
+        &mut hash,
+    );
+    format!("{}${}", to_hex(&salt), to_hex(&hash))
+}
+
+/// Whether `password` is the one `stored` was made from by `hash_password`
+pub fn verify_password(password: &str, stored: &str) -> bool {
+    let Some((salt, hash)) = stored.split_once('$') else {
+        return false;
+    };
+    let (Some(salt), Some(hash)) = (from_hex(salt), from_hex(hash)) else {
+        return false;
+    };
+    pbkdf2::verify(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        iterations(),
+        &salt,
+        password.as_bytes(),
+        &hash,
+    )
+    .is_ok()
+}
+
+fn iterations() -> NonZeroU32 {
+    NonZeroU32::new(ITERATIONS).unwrap()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    hex.as_bytes()
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect()
+}
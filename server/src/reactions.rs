@@ -0,0 +1,36 @@
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+/// Least time between reactions from one address to one poll
+pub const REACTION_COOLDOWN: Duration = Duration::from_secs(2);
+
+/// When each address last reacted to each poll. Keyed on the peer's address rather than
+/// anything the client sends, which it could change with every request. Only kept in memory,
+/// since forgetting a cooldown this short on restart doesn't matter.
+#[derive(Default)]
+pub struct ReactionLimiter {
+    last_reactions: Mutex<HashMap<(u64, IpAddr), Instant>>,
+}
+
+impl ReactionLimiter {
+    /// Whether `ip` may react to `poll_key` now, and if so starts its cooldown
+    pub fn try_react(&self, poll_key: u64, ip: IpAddr) -> bool {
+        let mut last_reactions = self
+            .last_reactions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let now = Instant::now();
+        // Finished cooldowns are dropped here, so the map stays small
+        last_reactions.retain(|_, time| now.duration_since(*time) < REACTION_COOLDOWN);
+        let key = (poll_key, ip);
+        if last_reactions.contains_key(&key) {
+            return false;
+        }
+        last_reactions.insert(key, now);
+        true
+    }
+}
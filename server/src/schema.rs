@@ -0,0 +1,39 @@
+use areyougoing_shared::{
+    AddEditorRequest, AddEditorResult, ClearResponsesRequest, ClearResponsesResult,
+    CreatePollResult, HasRespondedResult, Poll, PollProgress, PollQueryResult, PollResponse,
+    PollStats, PollSubmissionResult, ProgressReportResult, ReactRequest, ReactResult,
+    ResponderHistory, ResultsReportResult,
+};
+use schemars::{gen::SchemaSettings, JsonSchema};
+use serde_json::{json, Value};
+
+/// JSON Schema for what the endpoints take and return, served at `/schema` so
+/// integrations can generate typed clients. Every type, and everything they use, is under
+/// `definitions` by name.
+pub fn api_schema() -> Value {
+    let mut generator = SchemaSettings::draft07().into_generator();
+    fn add<T: JsonSchema>(generator: &mut schemars::gen::SchemaGenerator) {
+        generator.subschema_for::<T>();
+    }
+    add::<Poll>(&mut generator);
+    add::<PollQueryResult>(&mut generator);
+    add::<PollResponse>(&mut generator);
+    add::<PollSubmissionResult>(&mut generator);
+    add::<HasRespondedResult>(&mut generator);
+    add::<CreatePollResult>(&mut generator);
+    add::<AddEditorRequest>(&mut generator);
+    add::<AddEditorResult>(&mut generator);
+    add::<ReactRequest>(&mut generator);
+    add::<ReactResult>(&mut generator);
+    add::<ClearResponsesRequest>(&mut generator);
+    add::<ClearResponsesResult>(&mut generator);
+    add::<PollProgress>(&mut generator);
+    add::<ProgressReportResult>(&mut generator);
+    add::<ResultsReportResult>(&mut generator);
+    add::<ResponderHistory>(&mut generator);
+    add::<PollStats>(&mut generator);
+    json!({
+        "$schema": generator.settings().meta_schema,
+        "definitions": generator.definitions(),
+    })
+}
@@ -0,0 +1,51 @@
+//! `areyougoing_server tally <archive>`: tallies a poll archive, like the ones `/archive`
+//! downloads, without running the server, e.g. to check what a result would be or to
+//! check tallies in CI
+
+use std::{fs, path::Path};
+
+use anyhow::Context;
+use areyougoing_shared::PollProgress;
+
+use crate::PollData;
+
+/// Reads an archive as JSON if `path` ends in `.json`, or as RON like `data.ron` otherwise
+pub fn read_archive(path: &Path, contents: &str) -> anyhow::Result<PollData> {
+    let poll_data: PollData = if path
+        .extension()
+        .is_some_and(|extension| extension == "json")
+    {
+        serde_json::from_str(contents).context("not a poll archive in JSON")?
+    } else {
+        ron::de::from_str(contents).context("not a poll archive in RON")?
+    };
+    poll_data.validate()?;
+    Ok(poll_data)
+}
+
+/// What an editor would see, including metrics that aren't publicly visible. Worked out
+/// from the responses, not the tallies stored with them.
+pub fn tally(poll_data: &PollData) -> PollProgress {
+    let mut unordered = poll_data
+        .responses
+        .keys()
+        .filter(|key| !poll_data.response_order.contains(key))
+        .collect::<Vec<_>>();
+    unordered.sort();
+    let responses = poll_data
+        .response_order
+        .iter()
+        .chain(unordered)
+        .filter_map(|key| Some((key.clone(), poll_data.responses.get(key)?.clone())))
+        .collect::<Vec<_>>();
+    poll_data.poll.snapshot(&responses)
+}
+
+/// Prints the tally of the archive at `path` as JSON
+pub fn run(path: &Path) -> anyhow::Result<()> {
+    let contents =
+        fs::read_to_string(path).with_context(|| format!("couldn't read {}", path.display()))?;
+    let poll_data = read_archive(path, &contents)?;
+    println!("{}", serde_json::to_string_pretty(&tally(&poll_data))?);
+    Ok(())
+}
@@ -1,29 +1,413 @@
+use anyhow::bail;
 use chrono::{DateTime, Utc};
 use enum_as_inner::EnumAsInner;
-use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fmt::Display};
+use serde::{
+    de::{EnumAccess, IgnoredAny, VariantAccess, Visitor},
+    Deserialize, Deserializer, Serialize,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    mem,
+};
 use strum::EnumIter;
 
-#[derive(Deserialize, Serialize, PartialEq, Clone, Debug, Default)]
+#[derive(Deserialize, Serialize, PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Question {
     pub prompt: String,
     pub form: Form,
+    /// Only ask this question if an earlier question was answered with this choice
+    pub show_if: Option<(usize, Choice)>,
+    /// Show how everyone answered this question alongside the results
+    #[serde(default)]
+    pub results_public: bool,
+    /// Whether responders are asked this question. Hidden questions still get a default
+    /// answer, so responses line up with the questions, but it never counts.
+    #[serde(default = "visible_by_default")]
+    pub participant_visible: bool,
+    /// Options that keep their place when the poll shuffles option order, e.g. "Other"
+    #[serde(default)]
+    pub pinned_options: Vec<usize>,
 }
 
-#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+fn visible_by_default() -> bool {
+    true
+}
+
+impl Default for Question {
+    fn default() -> Self {
+        Self {
+            prompt: Default::default(),
+            form: Default::default(),
+            show_if: None,
+            results_public: false,
+            participant_visible: true,
+            pinned_options: Vec::new(),
+        }
+    }
+}
+
+impl Question {
+    pub fn init_response(&self) -> FormResponse {
+        match self.form {
+            Form::OneOrNone { .. } | Form::YesNoNone => FormResponse::ChooseOneOrNone(None),
+            Form::One { .. } => FormResponse::ChooseOne(Choice::Index(0)),
+            Form::YesNo => FormResponse::ChooseOne(Choice::YesOrNo(false)),
+            Form::Multiple { .. } => FormResponse::ChooseMultiple(Vec::new()),
+            Form::RankTopN { .. } => FormResponse::RankedChoice(Vec::new()),
+            Form::Number => FormResponse::Number(0),
+        }
+    }
+
+    /// Whether `response` is the right kind for this question, with only valid choices, and
+    /// no choice ranked twice
+    pub fn accepts(&self, response: &FormResponse) -> bool {
+        let choices = self.form.choices();
+        let valid = |choice: &Choice| choices.iter().any(|(_, c)| c == choice);
+        match (&self.form, response) {
+            (Form::OneOrNone { .. } | Form::YesNoNone, FormResponse::ChooseOneOrNone(choice)) => {
+                choice.iter().all(valid)
+            }
+            (Form::One { .. } | Form::YesNo, FormResponse::ChooseOne(choice)) => valid(choice),
+            (Form::Multiple { .. }, FormResponse::ChooseMultiple(choices)) => {
+                choices.iter().all(valid)
+            }
+            (Form::RankTopN { n, .. }, FormResponse::RankedChoice(ranking)) => {
+                ranking.len() <= *n
+                    && ranking.iter().all(valid)
+                    && ranking
+                        .iter()
+                        .enumerate()
+                        .all(|(i, choice)| !ranking[..i].contains(choice))
+            }
+            (Form::Number, FormResponse::Number(_)) => true,
+            _ => false,
+        }
+    }
+}
+
+/// How the responders a question was shown to answered it
+#[derive(PartialEq, Debug, Clone, Default)]
+pub struct OptionCounts {
+    /// How many picked each of the question's `Form::choices`
+    pub counts: Vec<u64>,
+    /// How many left it unanswered, as in `FormResponse::is_answered`
+    pub abstained: u64,
+}
+
+/// Tallies one question's answers. Responders the question was hidden from don't count,
+/// and each responder counts at most once per choice, even if they picked it twice.
+pub fn option_counts(
+    questions: &[Question],
+    question_index: usize,
+    responses: &HashMap<String, Vec<FormResponse>>,
+) -> OptionCounts {
+    let choices = questions[question_index].form.choices();
+    let mut option_counts = OptionCounts {
+        counts: vec![0; choices.len()],
+        abstained: 0,
+    };
+    for poll_response in responses.values() {
+        if !shown_questions(questions, poll_response)[question_index] {
+            continue;
+        }
+        let Some(response) = poll_response.get(question_index) else {
+            continue;
+        };
+        if !response.is_answered() {
+            option_counts.abstained += 1;
+        }
+        for (count, (_, choice)) in option_counts.counts.iter_mut().zip(&choices) {
+            if response.includes(choice) {
+                *count += 1;
+            }
+        }
+    }
+    option_counts
+}
+
+/// Who picked each of one question's `Form::choices`, in alphabetical order. Responders
+/// are named by their keys in `responses`, so this only names them when responses are
+/// kept by name.
+pub fn responder_names(
+    questions: &[Question],
+    question_index: usize,
+    responses: &HashMap<String, Vec<FormResponse>>,
+) -> Vec<Vec<String>> {
+    let choices = questions[question_index].form.choices();
+    let mut names = vec![Vec::new(); choices.len()];
+    for (name, poll_response) in responses {
+        if !shown_questions(questions, poll_response)[question_index] {
+            continue;
+        }
+        let Some(response) = poll_response.get(question_index) else {
+            continue;
+        };
+        for (names, (_, choice)) in names.iter_mut().zip(&choices) {
+            if response.includes(choice) {
+                names.push(name.clone());
+            }
+        }
+    }
+    for names in &mut names {
+        names.sort();
+    }
+    names
+}
+
+/// Indices of options that match another option once case and surrounding whitespace
+/// are ignored, e.g. "Taco Bell" and "taco bell ".
+pub fn duplicate_options(options: &[String]) -> Vec<usize> {
+    let normalized = options
+        .iter()
+        .map(|option| option.trim().to_lowercase())
+        .collect::<Vec<_>>();
+    let mut counts = HashMap::<&str, usize>::new();
+    for option in &normalized {
+        *counts.entry(option).or_default() += 1;
+    }
+    normalized
+        .iter()
+        .enumerate()
+        .filter(|(_, option)| counts[option.as_str()] > 1)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// Options imported from elsewhere, e.g. a URL. Reads a CSV, with `csv` set, as a header
+/// row and then an option per row from the first column. Otherwise reads a JSON array, an
+/// option per line, or, if it's all on one line, options separated by commas. Fields in
+/// double quotes can hold commas, and `""` in them is a quote.
+pub fn parse_option_list(text: &str, csv: bool) -> Vec<String> {
+    let options: Vec<String> = if csv {
+        csv_records(text)
+            .into_iter()
+            .skip(1)
+            .filter_map(|record| record.into_iter().next())
+            .collect()
+    } else if let Ok(values) = serde_json::from_str::<Vec<serde_json::Value>>(text) {
+        values
+            .into_iter()
+            .map(|value| match value {
+                serde_json::Value::String(s) => s,
+                other => other.to_string(),
+            })
+            .collect()
+    } else {
+        let lines = text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .collect::<Vec<_>>();
+        match lines[..] {
+            [line] => csv_records(line).into_iter().flatten().collect(),
+            // Commas are part of the option here, e.g. "Smith, John", unless it's quoted
+            _ => lines
+                .into_iter()
+                .map(|line| match &csv_records(line)[..] {
+                    [record] if record.len() == 1 => record[0].clone(),
+                    _ => line.to_string(),
+                })
+                .collect(),
+        }
+    };
+    options
+        .into_iter()
+        .map(|option| option.trim().to_string())
+        .filter(|option| !option.is_empty())
+        .collect()
+}
+
+/// The rows of a CSV, split into fields. Quoted fields can hold commas and line breaks.
+fn csv_records(text: &str) -> Vec<Vec<String>> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if in_quotes => in_quotes = false,
+            '"' if field.trim().is_empty() => {
+                field.clear();
+                in_quotes = true;
+            }
+            ',' if !in_quotes => record.push(mem::take(&mut field)),
+            '\n' if !in_quotes => {
+                record.push(mem::take(&mut field));
+                records.push(mem::take(&mut record));
+            }
+            '\r' if !in_quotes => {}
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records.retain(|record| record.iter().any(|field| !field.trim().is_empty()));
+    records
+}
+
+/// Which questions are shown for the given responses. A question is hidden if it isn't
+/// `participant_visible`, if its `show_if` isn't satisfied, or if the question it depends
+/// on is itself hidden.
+pub fn shown_questions(questions: &[Question], responses: &[FormResponse]) -> Vec<bool> {
+    let mut shown = Vec::with_capacity(questions.len());
+    for question in questions {
+        shown.push(
+            question.participant_visible
+                && match &question.show_if {
+                    Some((index, choice)) => {
+                        shown.get(*index).copied().unwrap_or(false)
+                            && matches!(
+                                responses.get(*index),
+                                Some(response) if response.includes(choice)
+                            )
+                    }
+                    None => true,
+                },
+        );
+    }
+    shown
+}
+
+#[derive(Serialize, PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum FormResponse {
     ChooseOneOrNone(Option<Choice>),
     ChooseOne(Choice),
     ChooseMultiple(Vec<Choice>),
+    /// Picks in order of preference, best first. Unranked options come after all of these.
+    RankedChoice(Vec<Choice>),
+    Number(i64),
+    /// A kind of response this build doesn't know about, e.g. one written by a newer
+    /// version. It never counts toward anything and is never valid to submit.
+    Unknown,
+}
+
+// Hand written so an unrecognized variant becomes `Unknown` instead of failing the whole
+// db load. New variants must hold a single value (like the ones above) to be skippable.
+impl<'de> Deserialize<'de> for FormResponse {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(field_identifier)]
+        enum Tag {
+            ChooseOneOrNone,
+            ChooseOne,
+            ChooseMultiple,
+            RankedChoice,
+            Number,
+            Unknown,
+            #[serde(other)]
+            Unrecognized,
+        }
+
+        struct FormResponseVisitor;
+
+        impl<'de> Visitor<'de> for FormResponseVisitor {
+            type Value = FormResponse;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a form response")
+            }
+
+            fn visit_enum<A: EnumAccess<'de>>(self, data: A) -> Result<Self::Value, A::Error> {
+                let (tag, variant) = data.variant()?;
+                Ok(match tag {
+                    Tag::ChooseOneOrNone => {
+                        FormResponse::ChooseOneOrNone(variant.newtype_variant()?)
+                    }
+                    Tag::ChooseOne => FormResponse::ChooseOne(variant.newtype_variant()?),
+                    Tag::ChooseMultiple => FormResponse::ChooseMultiple(variant.newtype_variant()?),
+                    Tag::RankedChoice => FormResponse::RankedChoice(variant.newtype_variant()?),
+                    Tag::Number => FormResponse::Number(variant.newtype_variant()?),
+                    Tag::Unknown => {
+                        variant.unit_variant()?;
+                        FormResponse::Unknown
+                    }
+                    Tag::Unrecognized => {
+                        variant.newtype_variant::<IgnoredAny>()?;
+                        FormResponse::Unknown
+                    }
+                })
+            }
+        }
+
+        deserializer.deserialize_enum(
+            "FormResponse",
+            &[
+                "ChooseOneOrNone",
+                "ChooseOne",
+                "ChooseMultiple",
+                "RankedChoice",
+                "Number",
+                "Unknown",
+            ],
+            FormResponseVisitor,
+        )
+    }
+}
+
+impl FormResponse {
+    pub fn includes(&self, choice: &Choice) -> bool {
+        match self {
+            FormResponse::ChooseOneOrNone(response_choice) => {
+                response_choice.as_ref() == Some(choice)
+            }
+            FormResponse::ChooseOne(response_choice) => response_choice == choice,
+            FormResponse::ChooseMultiple(response_choices)
+            | FormResponse::RankedChoice(response_choices) => response_choices.contains(choice),
+            FormResponse::Number(_) | FormResponse::Unknown => false,
+        }
+    }
+
+    /// Whether the responder has picked something, as opposed to leaving the initial response
+    pub fn is_answered(&self) -> bool {
+        match self {
+            FormResponse::ChooseOneOrNone(choice) => choice.is_some(),
+            FormResponse::ChooseOne(_) | FormResponse::Number(_) => true,
+            FormResponse::ChooseMultiple(choices) | FormResponse::RankedChoice(choices) => {
+                !choices.is_empty()
+            }
+            FormResponse::Unknown => false,
+        }
+    }
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Clone, Debug, EnumIter)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum Form {
-    OneOrNone { options: Vec<String> },
-    One { options: Vec<String> },
-    Multiple { options: Vec<String> },
+    OneOrNone {
+        options: Vec<String>,
+        /// Most responders that can pick each option, or `None` for no limit
+        #[serde(default)]
+        capacities: Vec<Option<u64>>,
+    },
+    One {
+        options: Vec<String>,
+        #[serde(default)]
+        capacities: Vec<Option<u64>>,
+        /// Adds a "None of the above" choice, so rejecting every option is a response
+        #[serde(default)]
+        none_of_the_above: bool,
+    },
+    Multiple {
+        options: Vec<String>,
+        #[serde(default)]
+        capacities: Vec<Option<u64>>,
+    },
     YesNoNone,
     YesNo,
+    RankTopN {
+        options: Vec<String>,
+        n: usize,
+    },
+    Number,
 }
 
 impl Display for Form {
@@ -47,20 +431,122 @@ impl Display for Form {
                 Form::YesNo => {
                     "Yes/No"
                 }
+                Form::RankTopN { .. } => {
+                    "Rank Top N"
+                }
+                Form::Number => {
+                    "Number"
+                }
             }
         )
     }
 }
 
+impl Form {
+    /// Every choice that can be made for this form, with a label for each
+    pub fn choices(&self) -> Vec<(String, Choice)> {
+        match self {
+            Form::One {
+                options,
+                none_of_the_above: true,
+                ..
+            } => options
+                .iter()
+                .enumerate()
+                .map(|(i, option)| (option.clone(), Choice::Index(i as u8)))
+                .chain([(NONE_OF_THE_ABOVE.to_string(), Choice::NoneOfTheAbove)])
+                .collect(),
+            Form::OneOrNone { options, .. }
+            | Form::One { options, .. }
+            | Form::Multiple { options, .. }
+            | Form::RankTopN { options, .. } => options
+                .iter()
+                .enumerate()
+                .map(|(i, option)| (option.clone(), Choice::Index(i as u8)))
+                .collect(),
+            Form::YesNoNone => vec![
+                ("No".to_string(), Choice::YesOrNo(false)),
+                ("Yes".to_string(), Choice::YesOrNo(true)),
+                ("Maybe".to_string(), Choice::Maybe),
+            ],
+            Form::YesNo => vec![
+                ("No".to_string(), Choice::YesOrNo(false)),
+                ("Yes".to_string(), Choice::YesOrNo(true)),
+            ],
+            Form::Number => Vec::new(),
+        }
+    }
+
+    /// The label for `choice`, or a placeholder once the form no longer offers it, e.g. after
+    /// its option was deleted
+    pub fn choice_label(&self, choice: &Choice) -> String {
+        self.choices()
+            .into_iter()
+            .find(|(_, c)| c == choice)
+            .map(|(label, _)| label)
+            .unwrap_or_else(|| REMOVED_OPTION.to_string())
+    }
+
+    /// Whether responders can leave this unanswered, which `Choice::NoSelection` counts
+    pub fn can_go_unanswered(&self) -> bool {
+        matches!(
+            self,
+            Form::OneOrNone { .. }
+                | Form::YesNoNone
+                | Form::Multiple { .. }
+                | Form::RankTopN { .. }
+        )
+    }
+
+    /// `choices`, plus `Choice::NoSelection` if this can go unanswered. What
+    /// `Metric::SpecificResponses` can count.
+    pub fn countable_choices(&self) -> Vec<(String, Choice)> {
+        let mut choices = self.choices();
+        if self.can_go_unanswered() {
+            choices.push((NO_SELECTION.to_string(), Choice::NoSelection));
+        }
+        choices
+    }
+
+    /// How many responders can pick each of `choices`, or `None` for no limit
+    pub fn capacities(&self) -> Vec<Option<u64>> {
+        match self {
+            Form::OneOrNone {
+                options,
+                capacities,
+            }
+            | Form::One {
+                options,
+                capacities,
+                ..
+            }
+            | Form::Multiple {
+                options,
+                capacities,
+            } => {
+                let mut padded = (0..options.len())
+                    .map(|i| capacities.get(i).copied().flatten())
+                    .collect::<Vec<_>>();
+                // "None of the above" never runs out
+                padded.resize(self.choices().len(), None);
+                padded
+            }
+            _ => vec![None; self.choices().len()],
+        }
+    }
+}
+
 impl Default for Form {
     fn default() -> Self {
         Self::OneOrNone {
             options: Default::default(),
+            capacities: Default::default(),
         }
     }
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum PollStatus {
     SeekingResponses,
     Closed,
@@ -73,47 +559,212 @@ impl Default for PollStatus {
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Clone, Debug, EnumAsInner)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum Choice {
     Index(u8),
     YesOrNo(bool),
+    Maybe,
+    NoneOfTheAbove,
+    /// Leaving a question that can be skipped unanswered. Only counted by
+    /// `Metric::SpecificResponses`, never part of a response.
+    NoSelection,
 }
 
+pub const NONE_OF_THE_ABOVE: &str = "None of the above";
+/// Stands in for choices metrics still track after they were taken off their question
+pub const REMOVED_OPTION: &str = "(removed option)";
+pub const NO_SELECTION: &str = "No selection";
+/// Most combinations a `Metric::CompositeRanking` can score, since it scores every one
+pub const MAX_COMBINATIONS: usize = 1000;
+/// How many of a `Metric::CompositeRanking`'s best combinations its progress keeps
+pub const STANDINGS_SHOWN: usize = 10;
+
 #[derive(Deserialize, Serialize, PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum Metric {
     SpecificResponses {
         question_index: usize,
         choice: Choice,
     },
+    /// Counts the answers to a number question between each pair of ascending `buckets`
+    /// boundaries, plus below the first and from the last onwards. A value on a boundary
+    /// counts toward the bucket above it.
+    NumberHistogram {
+        question_index: usize,
+        buckets: Vec<i64>,
+    },
+    /// The option with the most points on a ranking question, where a pick ranked r-th
+    /// of the top n gets n - r + 1 points
+    RankedWinner {
+        question_index: usize,
+        tiebreak: TiebreakRule,
+    },
+    /// The most picked option on a pick one question. Ties go to the option whose text
+    /// sorts first.
+    Plurality { question_index: usize },
+    /// The best `STANDINGS_SHOWN` combinations of one option from each ranking question,
+    /// best first. Each option scores points as in `RankedWinner`, and a combination scores
+    /// the sum of its options' points. Equal scores are ordered by their text.
+    CompositeRanking { question_indices: Vec<usize> },
+    /// A count of 1 if more than half of the responders shown the question picked
+    /// `choice`, otherwise 0, so `AtLeast { minimum: 1 }` reads as "a majority"
+    MajorityReached {
+        question_index: usize,
+        choice: Choice,
+    },
+    /// Responders whose picks on a pick several question include both choices. The same
+    /// choice twice is just a count of it, like `SpecificResponses`.
+    CoOccurrence {
+        question_index: usize,
+        choice_a: Choice,
+        choice_b: Choice,
+    },
+    /// Responders who answered every question they were asked. Questions that can't be
+    /// left unanswered, like yes or no ones, always count as answered.
+    CompletionRate {},
+}
+
+/// How a tie for the top spot of a ranked tally gets settled
+#[derive(Deserialize, Serialize, PartialEq, Eq, Clone, Copy, Debug, Default, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum TiebreakRule {
+    /// The option whose text sorts first
+    #[default]
+    Lexicographic,
+    /// A pseudorandom pick from this seed, so anyone can reproduce it
+    Seeded(u64),
+    /// The option that was ranked first in the earliest submitted response
+    FirstSubmitted,
+}
+
+impl Display for TiebreakRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TiebreakRule::Lexicographic => write!(f, "Alphabetical"),
+            TiebreakRule::Seeded(seed) => write!(f, "Random (seed {seed})"),
+            TiebreakRule::FirstSubmitted => write!(f, "First Submitted"),
+        }
+    }
+}
+
+impl TiebreakRule {
+    /// Picks one of `tied` (option indices). `rankings` are in submission order.
+    fn pick(&self, tied: &[u8], options: &[String], rankings: &[&Vec<Choice>]) -> u8 {
+        let by_text = || *tied.iter().min_by_key(|&&i| &options[i as usize]).unwrap();
+        match self {
+            TiebreakRule::Lexicographic => by_text(),
+            TiebreakRule::Seeded(seed) => tied[(splitmix64(*seed) % tied.len() as u64) as usize],
+            TiebreakRule::FirstSubmitted => rankings
+                .iter()
+                .flat_map(|ranking| ranking.iter())
+                .filter_map(|choice| choice.as_index().copied())
+                .find(|i| tied.contains(i))
+                .unwrap_or_else(by_text),
+        }
+    }
+}
+
+/// FNV-1a of `bytes`, for hashes that have to match across platforms, builds and visits,
+/// which `DefaultHasher` doesn't promise
+pub fn stable_hash(bytes: &[u8]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    bytes.iter().fold(OFFSET_BASIS, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(PRIME)
+    })
+}
+
+/// The order to show `option_count` options in: shuffled by `seed`, except for `pinned`
+/// options, which stay where they are. Holds original indices, so choices are stored the
+/// same whatever order they were shown in.
+pub fn display_order(option_count: usize, pinned: &[usize], seed: u64) -> Vec<usize> {
+    let free_slots = (0..option_count)
+        .filter(|i| !pinned.contains(i))
+        .collect::<Vec<_>>();
+    let mut shuffled = free_slots.clone();
+    let mut state = seed;
+    for i in (1..shuffled.len()).rev() {
+        state = splitmix64(state);
+        shuffled.swap(i, (state % (i as u64 + 1)) as usize);
+    }
+    let mut order = (0..option_count).collect::<Vec<_>>();
+    for (slot, option) in free_slots.into_iter().zip(shuffled) {
+        order[slot] = option;
+    }
+    order
+}
+
+/// A small, stable PRNG step, so seeded tiebreaks don't depend on any crate's algorithm
+fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
 }
 
 impl Metric {
     pub fn render(&self, questions: &[Question]) -> String {
         match self {
+            Metric::SpecificResponses {
+                question_index,
+                choice: Choice::NoSelection,
+            } => format!("No selection for {}", questions[*question_index].prompt),
             Metric::SpecificResponses {
                 question_index,
                 choice,
             } => {
-                let Question { prompt, form } = &questions[*question_index];
-                use Form::*;
-                let choice = match form {
-                    OneOrNone { options } | One { options } | Multiple { options } => {
-                        &options[*choice.as_index().unwrap() as usize]
-                    }
-                    YesNoNone | YesNo => {
-                        if *choice.as_yes_or_no().unwrap() {
-                            "Yes"
-                        } else {
-                            "No"
-                        }
-                    }
-                };
-                format!("{choice} to {prompt}")
+                let Question { prompt, form, .. } = &questions[*question_index];
+                format!("{} to {prompt}", form.choice_label(choice))
             }
+            Metric::MajorityReached {
+                question_index,
+                choice,
+            } => {
+                format!(
+                    "Majority chose {}",
+                    questions[*question_index].form.choice_label(choice)
+                )
+            }
+            Metric::CoOccurrence {
+                question_index,
+                choice_a,
+                choice_b,
+            } => {
+                let Question { prompt, form, .. } = &questions[*question_index];
+                let text = |choice| form.choice_label(choice);
+                if choice_a == choice_b {
+                    format!("{} to {prompt}", text(choice_a))
+                } else {
+                    format!("{} and {} to {prompt}", text(choice_a), text(choice_b))
+                }
+            }
+            Metric::NumberHistogram { question_index, .. } => {
+                format!("Spread of {}", questions[*question_index].prompt)
+            }
+            Metric::RankedWinner { question_index, .. } => {
+                format!("Top pick for {}", questions[*question_index].prompt)
+            }
+            Metric::Plurality { question_index } => {
+                format!(
+                    "Most popular answer to {}",
+                    questions[*question_index].prompt
+                )
+            }
+            Metric::CompletionRate {} => "Fully completed responses".to_string(),
+            Metric::CompositeRanking { question_indices } => format!(
+                "Best combination of {}",
+                question_indices
+                    .iter()
+                    .map(|&i| questions[i].prompt.as_str())
+                    .collect::<Vec<_>>()
+                    .join(" & ")
+            ),
         }
     }
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct MetricTracker {
     pub metric: Metric,
     pub publicly_visible: bool,
@@ -124,62 +775,427 @@ impl MetricTracker {
         use Form::*;
         questions.get(0).map(|question| MetricTracker {
             publicly_visible: false,
-            metric: Metric::SpecificResponses {
-                question_index: 0,
-                choice: match question.form {
-                    OneOrNone { .. } | One { .. } | Multiple { .. } => Choice::Index(0),
-                    YesNoNone | YesNo => Choice::YesOrNo(true),
+            metric: match question.form {
+                OneOrNone { .. } | One { .. } | Multiple { .. } | RankTopN { .. } => {
+                    Metric::SpecificResponses {
+                        question_index: 0,
+                        choice: Choice::Index(0),
+                    }
+                }
+                YesNoNone | YesNo => Metric::SpecificResponses {
+                    question_index: 0,
+                    choice: Choice::YesOrNo(true),
+                },
+                Number => Metric::NumberHistogram {
+                    question_index: 0,
+                    buckets: vec![0],
                 },
             },
         })
     }
 }
 
-#[derive(Deserialize, Serialize, PartialEq, Clone, Debug)]
+#[derive(Deserialize, Serialize, PartialEq, Clone, Debug, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum Progress {
     Count(u64),
+    /// Count in each bucket, lowest first
+    Histogram(Vec<u64>),
+    /// The leading option, if anything has been picked, and the rule that settled a tie
+    Winner {
+        winner: Option<String>,
+        tiebreak: Option<TiebreakRule>,
+    },
+    /// Labels and their scores, best first
+    Standings(Vec<(String, u64)>),
+}
+
+impl Progress {
+    pub fn render(&self) -> String {
+        match self {
+            Progress::Count(count) => format_count(*count, false),
+            Progress::Histogram(counts) => counts
+                .iter()
+                .map(|&count| format_count(count, false))
+                .collect::<Vec<_>>()
+                .join(" / "),
+            Progress::Winner { winner, .. } => winner.clone().unwrap_or_else(|| "-".to_string()),
+            Progress::Standings(standings) => standings
+                .first()
+                .map(|(label, score)| format!("{label} ({})", format_count(*score, false)))
+                .unwrap_or_else(|| "-".to_string()),
+        }
+    }
+}
+
+/// Formats `count` with thousands separators, e.g. "12,345". `abbreviate` shortens counts
+/// of ten thousand or more for tight spaces, e.g. "12.3k", so leave it off for exports.
+pub fn format_count(count: u64, abbreviate: bool) -> String {
+    const UNITS: [(u64, &str); 3] = [(1_000, "k"), (1_000_000, "M"), (1_000_000_000, "B")];
+    if abbreviate && count >= 10_000 {
+        let tenths_of = |unit: u64| (count as u128 * 10 + unit as u128 / 2) / unit as u128;
+        let mut i = UNITS.iter().rposition(|&(unit, _)| count >= unit).unwrap();
+        // Rounding can carry over, e.g. 999,960 is "1M" rather than "1000k"
+        if tenths_of(UNITS[i].0) >= 10_000 && i + 1 < UNITS.len() {
+            i += 1;
+        }
+        let (unit, suffix) = UNITS[i];
+        let tenths = tenths_of(unit);
+        let whole = format_count((tenths / 10) as u64, false);
+        return match tenths % 10 {
+            0 => format!("{whole}{suffix}"),
+            decimal => format!("{whole}.{decimal}{suffix}"),
+        };
+    }
+    let digits = count.to_string();
+    let mut formatted = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+    formatted
+}
+
+/// A run of text with the same formatting, from `parse_markup`
+#[derive(PartialEq, Eq, Clone, Debug, Default)]
+pub struct MarkupSpan {
+    pub text: String,
+    pub bold: bool,
+    pub italic: bool,
+    pub link: Option<String>,
+}
+
+/// Lines of spans for the little markdown announcements take: `**bold**`, `*italics*` or
+/// `_italics_`, and `[text](https://...)` links. Anything else, HTML included, and links
+/// other than http(s) or mailto, is kept as written.
+pub fn parse_markup(text: &str) -> Vec<Vec<MarkupSpan>> {
+    text.lines().map(parse_markup_line).collect()
+}
+
+fn parse_markup_line(line: &str) -> Vec<MarkupSpan> {
+    let mut spans = Vec::new();
+    let mut current = MarkupSpan::default();
+    let mut italic_marker = None;
+    let mut i = 0;
+    while let Some(c) = line[i..].chars().next() {
+        let rest = &line[i..];
+        let toggle_at = if rest.starts_with("**") && (current.bold || rest[2..].contains("**")) {
+            Some(2)
+        } else if c == '*' || c == '_' {
+            // Single markers only count at the edges of words, leaving snake_case alone
+            let word_edge = |c: Option<char>| c.is_none_or(|c| !c.is_alphanumeric());
+            match italic_marker {
+                Some(marker) if marker == c && word_edge(rest[1..].chars().next()) => Some(1),
+                None if word_edge(line[..i].chars().next_back())
+                    && rest[1..].find(c).is_some_and(|end| end > 0) =>
+                {
+                    Some(1)
+                }
+                _ => None,
+            }
+        } else {
+            None
+        };
+        if let Some(len) = toggle_at {
+            if !current.text.is_empty() {
+                spans.push(current.clone());
+                current.text.clear();
+            }
+            if len == 2 {
+                current.bold = !current.bold;
+            } else {
+                current.italic = !current.italic;
+                italic_marker = current.italic.then_some(c);
+            }
+            i += len;
+        } else if let Some((label, link, len)) = markup_link(rest) {
+            if !current.text.is_empty() {
+                spans.push(current.clone());
+                current.text.clear();
+            }
+            spans.push(MarkupSpan {
+                text: label.to_string(),
+                link: Some(link.to_string()),
+                ..current.clone()
+            });
+            i += len;
+        } else {
+            current.text.push(c);
+            i += c.len_utf8();
+        }
+    }
+    if !current.text.is_empty() {
+        spans.push(current);
+    }
+    spans
+}
+
+/// The label and target of a `[label](target)` link at the start of `text`, and how long
+/// it is, if the target is safe to open
+fn markup_link(text: &str) -> Option<(&str, &str, usize)> {
+    const SCHEMES: [&str; 3] = ["https://", "http://", "mailto:"];
+    let label_end = text.strip_prefix('[')?.find("](")? + 1;
+    let link_start = label_end + 2;
+    let link_end = link_start + text[link_start..].find(')')?;
+    let (label, link) = (&text[1..label_end], &text[link_start..link_end]);
+    let safe = SCHEMES.iter().any(|scheme| link.starts_with(scheme))
+        && !link.contains(char::is_whitespace);
+    (safe && !label.is_empty()).then_some((label, link, link_end + 1))
+}
+
+/// How many combinations a `Metric::CompositeRanking` over `question_indices` scores
+fn composite_combinations(questions: &[Question], question_indices: &[usize]) -> usize {
+    question_indices
+        .iter()
+        .map(|&question_index| match &questions[question_index].form {
+            Form::RankTopN { options, .. } => options.len(),
+            _ => 0,
+        })
+        .fold(1, usize::saturating_mul)
+}
+
+/// Points for each option of a ranking question, where a pick ranked r-th of the top n
+/// gets n - r + 1 points. Responders the question was hidden from don't count.
+fn ranked_points(
+    questions: &[Question],
+    question_index: usize,
+    responses: &HashMap<String, Vec<FormResponse>>,
+) -> Vec<usize> {
+    let Form::RankTopN { options, n } = &questions[question_index].form else {
+        return Vec::new();
+    };
+    let mut points = vec![0; options.len()];
+    for poll_response in responses.values() {
+        if !shown_questions(questions, poll_response)[question_index] {
+            continue;
+        }
+        if let Some(FormResponse::RankedChoice(ranking)) = poll_response.get(question_index) {
+            for (rank, choice) in ranking.iter().take(*n).enumerate() {
+                if let Some(points) = choice.as_index().and_then(|&i| points.get_mut(i as usize)) {
+                    *points += n - rank;
+                }
+            }
+        }
+    }
+    points
 }
 
 impl Metric {
-    pub fn calculate_progress(&self, responses: &HashMap<String, Vec<FormResponse>>) -> Progress {
+    /// `submission_order` has the keys of `responses` in the order they were first
+    /// submitted, for `TiebreakRule::FirstSubmitted`
+    pub fn calculate_progress(
+        &self,
+        questions: &[Question],
+        responses: &HashMap<String, Vec<FormResponse>>,
+        submission_order: &[String],
+    ) -> Progress {
         match self {
             Metric::SpecificResponses {
+                question_index,
+                choice: Choice::NoSelection,
+            } => Progress::Count(option_counts(questions, *question_index, responses).abstained),
+            Metric::SpecificResponses {
+                question_index,
+                choice: metric_choice,
+            } => {
+                let choices = questions[*question_index].form.choices();
+                let counts = option_counts(questions, *question_index, responses).counts;
+                Progress::Count(
+                    choices
+                        .iter()
+                        .position(|(_, choice)| choice == metric_choice)
+                        .map_or(0, |i| counts[i]),
+                )
+            }
+            Metric::MajorityReached {
                 question_index,
                 choice: metric_choice,
             } => {
-                let mut count = 0;
+                let responders = responses
+                    .values()
+                    .filter(|poll_response| {
+                        shown_questions(questions, poll_response)[*question_index]
+                    })
+                    .count() as u64;
+                let matches = Metric::SpecificResponses {
+                    question_index: *question_index,
+                    choice: metric_choice.clone(),
+                }
+                .calculate_progress(questions, responses, submission_order);
+                // No responders is no majority
+                Progress::Count(match matches {
+                    Progress::Count(matches) => (matches * 2 > responders) as u64,
+                    _ => 0,
+                })
+            }
+            Metric::CoOccurrence {
+                question_index,
+                choice_a,
+                choice_b,
+            } => {
+                if choice_a == choice_b {
+                    return Metric::SpecificResponses {
+                        question_index: *question_index,
+                        choice: choice_a.clone(),
+                    }
+                    .calculate_progress(questions, responses, submission_order);
+                }
+                Progress::Count(
+                    responses
+                        .values()
+                        .filter(|poll_response| {
+                            shown_questions(questions, poll_response)[*question_index]
+                        })
+                        .filter(|poll_response| {
+                            matches!(
+                                poll_response.get(*question_index),
+                                Some(FormResponse::ChooseMultiple(choices))
+                                    if choices.contains(choice_a) && choices.contains(choice_b)
+                            )
+                        })
+                        .count() as u64,
+                )
+            }
+            Metric::CompletionRate {} => Progress::Count(
+                responses
+                    .values()
+                    .filter(|poll_response| {
+                        let shown = shown_questions(questions, poll_response);
+                        (0..questions.len()).all(|i| {
+                            !shown[i] || poll_response.get(i).is_some_and(FormResponse::is_answered)
+                        })
+                    })
+                    .count() as u64,
+            ),
+            Metric::NumberHistogram {
+                question_index,
+                buckets,
+            } => {
+                let mut counts = vec![0; buckets.len() + 1];
                 for poll_response in responses.values() {
-                    match poll_response.get(*question_index).unwrap() {
-                        FormResponse::ChooseOneOrNone(response_choice) => {
-                            if let Some(response_choice) = response_choice {
-                                if response_choice == metric_choice {
-                                    count += 1;
-                                }
-                            }
-                        }
-                        FormResponse::ChooseOne(response_choice) => {
-                            if response_choice == metric_choice {
-                                count += 1;
-                            }
-                        }
-                        FormResponse::ChooseMultiple(response_choices) => {
-                            for response_choice in response_choices {
-                                if response_choice == metric_choice {
-                                    count += 1;
-                                }
-                            }
-                        }
+                    if !shown_questions(questions, poll_response)[*question_index] {
+                        continue;
+                    }
+                    if let Some(FormResponse::Number(value)) = poll_response.get(*question_index) {
+                        counts[buckets.partition_point(|boundary| boundary <= value)] += 1;
                     }
                 }
-                Progress::Count(count)
+                Progress::Histogram(counts)
+            }
+            Metric::RankedWinner {
+                question_index,
+                tiebreak,
+            } => {
+                let Form::RankTopN { options, .. } = &questions[*question_index].form else {
+                    return Progress::Winner {
+                        winner: None,
+                        tiebreak: None,
+                    };
+                };
+                let rankings = submission_order
+                    .iter()
+                    .filter_map(|key| responses.get(key))
+                    .filter(|poll_response| {
+                        shown_questions(questions, poll_response)[*question_index]
+                    })
+                    .filter_map(|poll_response| match poll_response.get(*question_index) {
+                        Some(FormResponse::RankedChoice(ranking)) => Some(ranking),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                let points = ranked_points(questions, *question_index, responses);
+                let most = points.iter().copied().max().unwrap_or(0);
+                if most == 0 {
+                    return Progress::Winner {
+                        winner: None,
+                        tiebreak: None,
+                    };
+                }
+                let tied = (0..options.len() as u8)
+                    .filter(|&i| points[i as usize] == most)
+                    .collect::<Vec<_>>();
+                let (winner, tiebreak) = if tied.len() == 1 {
+                    (tied[0], None)
+                } else {
+                    (tiebreak.pick(&tied, options, &rankings), Some(*tiebreak))
+                };
+                Progress::Winner {
+                    winner: Some(options[winner as usize].clone()),
+                    tiebreak,
+                }
+            }
+            Metric::CompositeRanking { question_indices } => {
+                // Checked by `Poll::validate` too, but the editor tallies unsaved polls
+                if composite_combinations(questions, question_indices) > MAX_COMBINATIONS {
+                    return Progress::Standings(Vec::new());
+                }
+                let mut standings = vec![(Vec::new(), 0)];
+                for &question_index in question_indices {
+                    let Form::RankTopN { options, .. } = &questions[question_index].form else {
+                        return Progress::Standings(Vec::new());
+                    };
+                    let points = ranked_points(questions, question_index, responses);
+                    standings = standings
+                        .into_iter()
+                        .flat_map(|(labels, score)| {
+                            options.iter().zip(&points).map(move |(option, points)| {
+                                let mut labels = labels.clone();
+                                labels.push(option.as_str());
+                                (labels, score + *points as u64)
+                            })
+                        })
+                        .collect();
+                }
+                let mut standings = standings
+                    .into_iter()
+                    .map(|(labels, score)| (labels.join(" + "), score))
+                    .collect::<Vec<_>>();
+                standings.sort_by(|(a_label, a_score), (b_label, b_score)| {
+                    b_score.cmp(a_score).then_with(|| a_label.cmp(b_label))
+                });
+                standings.truncate(STANDINGS_SHOWN);
+                Progress::Standings(standings)
+            }
+            Metric::Plurality { question_index } => {
+                let options = questions[*question_index]
+                    .form
+                    .choices()
+                    .into_iter()
+                    .map(|(label, _)| label)
+                    .collect::<Vec<_>>();
+                let counts = option_counts(questions, *question_index, responses).counts;
+                let most = counts.iter().copied().max().unwrap_or(0);
+                if most == 0 {
+                    return Progress::Winner {
+                        winner: None,
+                        tiebreak: None,
+                    };
+                }
+                let tied = (0..options.len() as u8)
+                    .filter(|&i| counts[i as usize] == most)
+                    .collect::<Vec<_>>();
+                let (winner, tiebreak) = if tied.len() == 1 {
+                    (tied[0], None)
+                } else {
+                    let rule = TiebreakRule::Lexicographic;
+                    (rule.pick(&tied, &options, &[]), Some(rule))
+                };
+                Progress::Winner {
+                    winner: Some(options[winner as usize].clone()),
+                    tiebreak,
+                }
             }
         }
     }
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum Requirement {
     AtLeast { metric_index: u16, minimum: u64 },
+    Exactly { metric_index: u16, count: u64 },
 }
 
 impl Requirement {
@@ -188,18 +1204,46 @@ impl Requirement {
             Requirement::AtLeast {
                 minimum,
                 metric_index,
-            } => {
-                let Progress::Count(count) = progresses.get(*metric_index as usize).unwrap();
-                count >= minimum
-            }
+            } => match progresses.get(*metric_index as usize).unwrap() {
+                Progress::Count(count) => count >= minimum,
+                Progress::Histogram(_) | Progress::Winner { .. } | Progress::Standings(_) => false,
+            },
+            Requirement::Exactly {
+                count: required,
+                metric_index,
+            } => match progresses.get(*metric_index as usize).unwrap() {
+                Progress::Count(count) => count == required,
+                Progress::Histogram(_) | Progress::Winner { .. } | Progress::Standings(_) => false,
+            },
+        }
+    }
+
+    /// How far an `AtLeast`'s count is from its minimum. `None` once it's met, if its
+    /// metric's progress is hidden, or for other requirements.
+    pub fn still_needed(&self, progresses: &[Option<Progress>]) -> Option<u64> {
+        let Requirement::AtLeast {
+            metric_index,
+            minimum,
+        } = self
+        else {
+            return None;
+        };
+        match progresses.get(*metric_index as usize)? {
+            Some(Progress::Count(count)) if count < minimum => Some(minimum - count),
+            _ => None,
         }
     }
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct PollResult {
     pub desc: String,
     pub requirements: Vec<Requirement>,
+    /// Set when a metric the requirements need is deleted while editing. Kept so the result
+    /// isn't lost, but never met, and the poll can't be created until it's fixed or deleted.
+    #[serde(default)]
+    pub disabled: bool,
 }
 
 impl Default for PollResult {
@@ -210,11 +1254,66 @@ impl Default for PollResult {
                 metric_index: 0,
                 minimum: 1,
             }],
+            disabled: false,
         }
     }
 }
 
-#[derive(Deserialize, Serialize, PartialEq, Clone, Debug)]
+/// Points `results`' requirements at their metrics' new places after metrics are moved or
+/// deleted. `new_index` maps each old metric index to its new one, or to `None` if that
+/// metric was deleted, which disables the results that needed it rather than pointing them
+/// at another.
+pub fn remap_metric_indices(
+    results: &mut [PollResult],
+    new_index: impl Fn(usize) -> Option<usize>,
+) {
+    for result in results {
+        for requirement in &mut result.requirements {
+            let (Requirement::AtLeast { metric_index, .. }
+            | Requirement::Exactly { metric_index, .. }) = requirement;
+            match new_index(*metric_index as usize) {
+                Some(index) => *metric_index = index as u16,
+                None => result.disabled = true,
+            }
+        }
+    }
+}
+
+/// The changes one frame of editing a list makes, applied in this order: a deletion, an
+/// insertion, then a swap of two neighbours
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Default)]
+pub struct ListEdits {
+    pub deleted: Option<usize>,
+    pub inserted: Option<usize>,
+    pub swapped: Option<(usize, usize)>,
+}
+
+impl ListEdits {
+    /// Where the item at `old_index` is after these edits, or `None` if it was deleted.
+    /// For keeping indices into the list pointed at the same items.
+    pub fn new_position(&self, old_index: usize) -> Option<usize> {
+        let mut index = old_index;
+        if let Some(deleted) = self.deleted {
+            if index == deleted {
+                return None;
+            }
+            if index > deleted {
+                index -= 1;
+            }
+        }
+        if matches!(self.inserted, Some(inserted) if index >= inserted) {
+            index += 1;
+        }
+        match self.swapped {
+            Some((a, b)) if index == a => Some(b),
+            Some((a, b)) if index == b => Some(a),
+            _ => Some(index),
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, PartialEq, Clone, Debug, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct ResultState {
     pub requirements_met: Vec<bool>,
     pub overall_met: bool,
@@ -227,20 +1326,199 @@ impl ResultState {
             overall_met: false,
         }
     }
+
+    pub fn evaluate(result: &PollResult, progresses: &[Progress]) -> Self {
+        if result.disabled {
+            return Self::from_result(result);
+        }
+        let requirements_met = result
+            .requirements
+            .iter()
+            .map(|r| r.evaluate(progresses))
+            .collect::<Vec<_>>();
+        Self {
+            overall_met: requirements_met.iter().all(|x| *x),
+            requirements_met,
+        }
+    }
 }
 
-#[derive(Deserialize, Serialize, PartialEq, Clone, Debug)]
+#[derive(Deserialize, Serialize, PartialEq, Clone, Debug, Hash)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct PollProgress {
     pub metric_progresses: Vec<Option<Progress>>,
+    /// For each question with public results, how many responders picked each of
+    /// `Form::choices`
+    #[serde(default)]
+    pub question_breakdowns: Vec<Option<Vec<u64>>>,
+    /// For each question with limited options, how many responders picked each of
+    /// `Form::choices`
+    #[serde(default)]
+    pub seats_taken: Vec<Option<Vec<u64>>>,
     pub result_states: Vec<ResultState>,
+    pub quorum_met: bool,
+    /// How many have responded, e.g. to show against `Poll::max_responses`
+    #[serde(default)]
+    pub response_count: u64,
+    /// Counts are being held back, see `Poll::reveal_when_closed` and
+    /// `Poll::min_results_threshold`
+    #[serde(default)]
+    pub hidden: bool,
+    /// How many times each of `REACTIONS` was sent
+    #[serde(default)]
+    pub reactions: BTreeMap<String, u64>,
+    /// Who picked each of `Form::choices` on the question in `Poll::show_responder_names`
+    #[serde(default)]
+    pub responder_names: Option<Vec<Vec<String>>>,
+    /// The first `FIRST_RESPONDERS_SHOWN` to respond, earliest first, for polls with
+    /// `Poll::show_first_responders`
+    #[serde(default)]
+    pub first_responders: Option<Vec<String>>,
+}
+
+/// How many of the earliest responders `PollProgress::first_responders` names
+pub const FIRST_RESPONDERS_SHOWN: usize = 3;
+
+#[derive(Deserialize, Serialize, PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PollTheme {
+    pub accent_color: [u8; 3],
+    pub header_emoji: String,
+}
+
+impl Default for PollTheme {
+    fn default() -> Self {
+        Self {
+            accent_color: [0, 92, 128],
+            header_emoji: "🎉".to_string(),
+        }
+    }
+}
+
+/// How submissions are matched up, so a later one replaces an earlier one
+#[derive(Deserialize, Serialize, PartialEq, Eq, Clone, Copy, Debug, Default, EnumIter)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum DedupStrategy {
+    /// One response per typed name. Anyone can edit a response by signing in with its name.
+    #[default]
+    ByName,
+    /// One response per browser. Names can be reused, but a responder can't edit from
+    /// another device.
+    ByDevice,
+    /// Every submission is kept as a new response, so responses can't be edited.
+    None,
+}
+
+impl Display for DedupStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                DedupStrategy::ByName => "By Name",
+                DedupStrategy::ByDevice => "By Device",
+                DedupStrategy::None => "Anonymous",
+            }
+        )
+    }
+}
+
+/// A rule a response has to follow for the server to take it
+#[derive(Deserialize, Serialize, PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum Constraint {
+    /// No two responses can share this detail of who sent them
+    UniqueBy(ResponderField),
+    /// The answer to the question has to include the choice, if the question is shown
+    RequiresChoice(usize, Choice),
+}
+
+/// A detail of who sent a response, as opposed to their answers
+#[derive(Deserialize, Serialize, PartialEq, Eq, Clone, Copy, Debug, EnumIter)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum ResponderField {
+    Name,
+    Device,
+    EmailDomain,
+}
+
+impl Display for ResponderField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ResponderField::Name => "name",
+                ResponderField::Device => "device",
+                ResponderField::EmailDomain => "email domain",
+            }
+        )
+    }
+}
+
+impl ResponderField {
+    /// This detail of `response`, lowercased so it compares case insensitively. `None` if
+    /// the responder didn't give it.
+    pub fn value(&self, response: &PollResponse) -> Option<String> {
+        let value = match self {
+            ResponderField::Name => Some(response.user.as_str()),
+            ResponderField::Device => Some(response.device_key.as_str()),
+            ResponderField::EmailDomain => response
+                .email
+                .as_deref()
+                .and_then(|email| email.rsplit_once('@'))
+                .map(|(_, domain)| domain),
+        }?
+        .trim()
+        .to_lowercase();
+        (!value.is_empty()).then_some(value)
+    }
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Clone, Debug, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct Poll {
     pub title: String,
     pub description: String,
+    pub theme: Option<PollTheme>,
     pub expiration: Option<DateTime<Utc>>,
+    /// After this, new people can still respond but earlier responses are locked
+    #[serde(default)]
+    pub edit_deadline: Option<DateTime<Utc>>,
     pub announcement: Option<String>,
+    /// Minimum number of responses before the poll counts as "official"
+    pub quorum: Option<u64>,
+    /// Most responses the poll takes, e.g. the spots at an event. Anyone new is turned
+    /// away once it's reached, but earlier responders can still edit.
+    #[serde(default)]
+    pub max_responses: Option<u64>,
+    #[serde(default)]
+    pub dedup: DedupStrategy,
+    #[serde(default)]
+    pub constraints: Vec<Constraint>,
+    /// Only show progress to non-editors once the poll is closed, to avoid bandwagoning
+    #[serde(default)]
+    pub reveal_when_closed: bool,
+    /// Only show progress to non-editors once this many have responded, so results from
+    /// a handful of responses can't give away who said what
+    #[serde(default)]
+    pub min_results_threshold: Option<u64>,
+    /// Shows everyone who gave each answer to the question at this index, e.g. who's
+    /// going. Only allowed when responses are kept by name, which are what's shown.
+    #[serde(default)]
+    pub show_responder_names: Option<usize>,
+    /// Shows who responded first. Edits keep a responder's place. Only allowed when
+    /// responses are kept by name, like `show_responder_names`.
+    #[serde(default)]
+    pub show_first_responders: bool,
+    /// Shows each responder the options in their own order, so the first option doesn't
+    /// get picked just for being first. See `Question::pinned_options`.
+    #[serde(default)]
+    pub randomize_option_order: bool,
+    /// Needed to view the poll at all. Sent in plain text when creating the poll, but the
+    /// server only keeps a salted hash, and never sends it back.
+    #[serde(default)]
+    pub view_password: Option<String>,
     pub metric_trackers: Vec<MetricTracker>,
     pub results: Vec<PollResult>,
     pub status: PollStatus,
@@ -251,48 +1529,763 @@ impl Poll {
     pub fn init_responses(&self) -> Vec<FormResponse> {
         self.questions
             .iter()
-            .map(|q| match q.form {
-                Form::OneOrNone { .. } | Form::YesNoNone => FormResponse::ChooseOneOrNone(None),
-                Form::One { .. } => FormResponse::ChooseOne(Choice::Index(0)),
-                Form::YesNo => FormResponse::ChooseOne(Choice::YesOrNo(false)),
-                Form::Multiple { .. } => FormResponse::ChooseMultiple(Vec::new()),
+            .map(Question::init_response)
+            .collect::<Vec<_>>()
+    }
+
+    /// Puts back the default answers to questions responders aren't asked, whatever was
+    /// sent for them
+    pub fn reset_hidden_answers(&self, responses: &mut [FormResponse]) {
+        for (question, response) in self.questions.iter().zip(responses) {
+            if !question.participant_visible {
+                *response = question.init_response();
+            }
+        }
+    }
+
+    /// A hash of what the poll asks and tallies: its questions, metrics and results. It's
+    /// the same on every platform and build, so a client can compare it with the server's
+    /// to notice the poll was edited.
+    pub fn fingerprint(&self) -> u64 {
+        stable_hash(
+            &serde_json::to_vec(&(&self.questions, &self.metric_trackers, &self.results)).unwrap(),
+        )
+    }
+
+    /// The order to show question `question_index`'s options in to the responder `seed`
+    /// stands for, e.g. a hash of their device key
+    pub fn option_order(&self, question_index: usize, seed: u64) -> Vec<usize> {
+        let question = &self.questions[question_index];
+        let option_count = match &question.form {
+            Form::OneOrNone { options, .. }
+            | Form::One { options, .. }
+            | Form::Multiple { options, .. }
+            | Form::RankTopN { options, .. } => options.len(),
+            Form::YesNoNone | Form::YesNo | Form::Number => 0,
+        };
+        if !self.randomize_option_order {
+            return (0..option_count).collect();
+        }
+        let seed = seed ^ splitmix64(question_index as u64);
+        display_order(option_count, &question.pinned_options, seed)
+    }
+
+    /// Every metric's progress and every result's state for these responses.
+    /// `submission_order` is as in `Metric::calculate_progress`.
+    pub fn tally(
+        &self,
+        responses: &HashMap<String, Vec<FormResponse>>,
+        submission_order: &[String],
+    ) -> (Vec<Progress>, Vec<ResultState>) {
+        let progresses = self
+            .metric_trackers
+            .iter()
+            .map(|t| {
+                t.metric
+                    .calculate_progress(&self.questions, responses, submission_order)
             })
+            .collect::<Vec<_>>();
+        let result_states = self
+            .results
+            .iter()
+            .map(|r| ResultState::evaluate(r, &progresses))
+            .collect();
+        (progresses, result_states)
+    }
+
+    /// What an editor would see for `responses`, given in submission order. Worked out
+    /// from scratch, which makes it what the fixtures in `tests/fixtures` pin down.
+    pub fn snapshot(&self, responses: &[(String, Vec<FormResponse>)]) -> PollProgress {
+        let submission_order = responses
+            .iter()
+            .map(|(key, _)| key.clone())
+            .collect::<Vec<_>>();
+        let responses = responses.iter().cloned().collect::<HashMap<_, _>>();
+        let (progresses, result_states) = self.tally(&responses, &submission_order);
+        let counts = |i| option_counts(&self.questions, i, &responses).counts;
+        PollProgress {
+            metric_progresses: progresses.into_iter().map(Some).collect(),
+            question_breakdowns: self
+                .questions
+                .iter()
+                .enumerate()
+                .map(|(i, question)| question.results_public.then(|| counts(i)))
+                .collect(),
+            seats_taken: self
+                .questions
+                .iter()
+                .enumerate()
+                .map(|(i, question)| {
+                    question
+                        .form
+                        .capacities()
+                        .iter()
+                        .any(Option::is_some)
+                        .then(|| counts(i))
+                })
+                .collect(),
+            result_states,
+            quorum_met: match self.quorum {
+                Some(quorum) => responses.len() as u64 >= quorum,
+                None => true,
+            },
+            response_count: responses.len() as u64,
+            hidden: false,
+            reactions: Default::default(),
+            responder_names: self
+                .show_responder_names
+                .map(|i| responder_names(&self.questions, i, &responses)),
+            first_responders: self.show_first_responders.then(|| {
+                submission_order
+                    .iter()
+                    .take(FIRST_RESPONDERS_SHOWN)
+                    .cloned()
+                    .collect()
+            }),
+        }
+    }
+
+    /// Whether people who already responded can no longer change their answers
+    pub fn edits_locked(&self, now: DateTime<Utc>) -> bool {
+        self.edit_deadline.is_some_and(|d| d <= now)
+    }
+
+    /// Whether the poll has been closed, or has expired as of `now`
+    pub fn is_closed(&self, now: DateTime<Utc>) -> bool {
+        self.status == PollStatus::Closed || self.expiration.is_some_and(|e| e <= now)
+    }
+
+    /// An iCalendar (.ics) event for the poll, held at its expiration.
+    /// `None` if the poll has no expiration to use as a date.
+    pub fn calendar_event(&self, uid: &str, stamp: DateTime<Utc>) -> Option<String> {
+        const TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+        let start = self.expiration?;
+        let description = [Some(&self.description), self.announcement.as_ref()]
+            .into_iter()
+            .flatten()
+            .filter(|text| !text.is_empty())
+            .map(String::as_str)
             .collect::<Vec<_>>()
+            .join("\n\n");
+        let mut lines = vec![
+            "BEGIN:VCALENDAR".to_string(),
+            "VERSION:2.0".to_string(),
+            "PRODID:-//areyougoing//EN".to_string(),
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{}", ics_escape(uid)),
+            format!("DTSTAMP:{}", stamp.format(TIME_FORMAT)),
+            format!("DTSTART:{}", start.format(TIME_FORMAT)),
+            format!("SUMMARY:{}", ics_escape(&self.title)),
+        ];
+        if !description.is_empty() {
+            lines.push(format!("DESCRIPTION:{}", ics_escape(&description)));
+        }
+        lines.push("END:VEVENT".to_string());
+        lines.push("END:VCALENDAR".to_string());
+        Some(lines.iter().map(|line| ics_fold(line) + "\r\n").collect())
+    }
+
+    /// The values of `response` that the poll's `UniqueBy` constraints keep unique
+    pub fn unique_values(&self, response: &PollResponse) -> Vec<(ResponderField, String)> {
+        self.constraints
+            .iter()
+            .filter_map(|constraint| match constraint {
+                Constraint::UniqueBy(field) => Some((*field, field.value(response)?)),
+                Constraint::RequiresChoice(..) => None,
+            })
+            .collect()
+    }
+
+    /// Why `response` breaks one of the poll's constraints, if it does. `taken` are the
+    /// `unique_values` of the other responses.
+    pub fn constraint_violation(
+        &self,
+        response: &PollResponse,
+        taken: &[(ResponderField, String)],
+    ) -> Option<String> {
+        let shown = shown_questions(&self.questions, &response.responses);
+        self.constraints
+            .iter()
+            .find_map(|constraint| match constraint {
+                Constraint::UniqueBy(field) => match field.value(response) {
+                    None if *field == ResponderField::EmailDomain => {
+                        Some("Responding needs an email address".to_string())
+                    }
+                    None => Some(format!("Responding needs a {field}")),
+                    Some(value) if taken.contains(&(*field, value.clone())) => Some(format!(
+                        "Someone with the same {field} has already responded"
+                    )),
+                    Some(_) => None,
+                },
+                Constraint::RequiresChoice(question_index, choice) => {
+                    let question = self.questions.get(*question_index)?;
+                    let answered = response
+                        .responses
+                        .get(*question_index)
+                        .is_some_and(|answer| answer.includes(choice));
+                    if !shown[*question_index] || answered {
+                        return None;
+                    }
+                    let (label, _) = question
+                        .form
+                        .choices()
+                        .into_iter()
+                        .find(|(_, c)| c == choice)?;
+                    Some(format!("\"{}\" needs {label} picked", question.prompt))
+                }
+            })
+    }
+
+    pub fn validate_responses(&self, responses: &[FormResponse]) -> bool {
+        responses.len() == self.questions.len()
+            && self
+                .questions
+                .iter()
+                .zip(responses)
+                .all(|(question, response)| question.accepts(response))
+    }
+
+    /// Options picked in `new_responses` that are already at capacity. The responder's own
+    /// earlier response, stored under `responder_key`, doesn't count against them, so
+    /// editing a response keeps the seats it already holds.
+    pub fn full_choices(
+        &self,
+        responses: &HashMap<String, Vec<FormResponse>>,
+        responder_key: &str,
+        new_responses: &[FormResponse],
+    ) -> Vec<(usize, Choice)> {
+        let mut others = responses.clone();
+        others.remove(responder_key);
+        let shown = shown_questions(&self.questions, new_responses);
+        let mut full = Vec::new();
+        for (i, (question, response)) in self.questions.iter().zip(new_responses).enumerate() {
+            let capacities = question.form.capacities();
+            if !shown[i] || capacities.iter().all(Option::is_none) {
+                continue;
+            }
+            let taken = option_counts(&self.questions, i, &others).counts;
+            for (((_, choice), capacity), taken) in question
+                .form
+                .choices()
+                .into_iter()
+                .zip(capacities)
+                .zip(taken)
+            {
+                if response.includes(&choice) && capacity.is_some_and(|c| taken >= c) {
+                    full.push((i, choice));
+                }
+            }
+        }
+        full
+    }
+
+    /// Likely mistakes that don't make the poll invalid
+    pub fn warnings(&self) -> Vec<String> {
+        self.questions
+            .iter()
+            .enumerate()
+            .filter_map(|(i, question)| {
+                let labels = question
+                    .form
+                    .choices()
+                    .into_iter()
+                    .map(|(label, _)| label)
+                    .collect::<Vec<_>>();
+                // Name each repeated option once
+                let mut repeated = Vec::<&str>::new();
+                for d in duplicate_options(&labels) {
+                    let label = labels[d].trim();
+                    if !repeated
+                        .iter()
+                        .any(|r| r.to_lowercase() == label.to_lowercase())
+                    {
+                        repeated.push(label);
+                    }
+                }
+                (!repeated.is_empty()).then(|| {
+                    format!(
+                        "Question {} lists the same option more than once: {}",
+                        i + 1,
+                        repeated.join(", ")
+                    )
+                })
+            })
+            .collect()
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.title.trim().is_empty() {
+            bail!("The poll needs a title");
+        }
+        if !self.questions.is_empty()
+            && !self
+                .questions
+                .iter()
+                .any(|question| question.participant_visible)
+        {
+            bail!("At least one question must be shown to responders");
+        }
+        for (i, question) in self.questions.iter().enumerate() {
+            if let Form::OneOrNone { options, .. }
+            | Form::One { options, .. }
+            | Form::Multiple { options, .. } = &question.form
+            {
+                if options.is_empty() {
+                    bail!("Question {} needs at least one option", i + 1);
+                }
+            }
+            if let Form::RankTopN { options, n } = &question.form {
+                if *n == 0 || *n > options.len() {
+                    bail!(
+                        "Question {} must rank between 1 and {} options",
+                        i + 1,
+                        options.len()
+                    );
+                }
+            }
+            if let Some((index, choice)) = &question.show_if {
+                if *index >= i {
+                    bail!("Question {} can only depend on an earlier question", i + 1);
+                }
+                if !self.questions[*index]
+                    .form
+                    .choices()
+                    .iter()
+                    .any(|(_, c)| c == choice)
+                {
+                    bail!(
+                        "Question {} depends on an answer that question {} doesn't have",
+                        i + 1,
+                        index + 1
+                    );
+                }
+            }
+        }
+        if let Some(index) = self.show_responder_names {
+            if self.dedup != DedupStrategy::ByName {
+                bail!("Responder names can only be shown when there's one response per name");
+            }
+            if self
+                .questions
+                .get(index)
+                .is_none_or(|question| question.form.choices().is_empty())
+            {
+                bail!("Responder names need a question with answers to group them by");
+            }
+        }
+        if self.max_responses == Some(0) {
+            bail!("A poll that takes no responses can't be answered");
+        }
+        if self.show_first_responders && self.dedup != DedupStrategy::ByName {
+            bail!("First responders can only be shown when there's one response per name");
+        }
+        for (i, constraint) in self.constraints.iter().enumerate() {
+            if let Constraint::RequiresChoice(question_index, choice) = constraint {
+                if !self.questions.get(*question_index).is_some_and(|question| {
+                    question.form.choices().iter().any(|(_, c)| c == choice)
+                }) {
+                    bail!(
+                        "Constraint {} requires an answer its question doesn't have",
+                        i + 1
+                    );
+                }
+            }
+        }
+        for (i, tracker) in self.metric_trackers.iter().enumerate() {
+            let question_indices = match &tracker.metric {
+                Metric::SpecificResponses { question_index, .. }
+                | Metric::MajorityReached { question_index, .. }
+                | Metric::CoOccurrence { question_index, .. }
+                | Metric::NumberHistogram { question_index, .. }
+                | Metric::RankedWinner { question_index, .. }
+                | Metric::Plurality { question_index } => vec![*question_index],
+                Metric::CompositeRanking { question_indices } => question_indices.clone(),
+                Metric::CompletionRate {} => Vec::new(),
+            };
+            if question_indices
+                .iter()
+                .any(|&index| index >= self.questions.len())
+            {
+                bail!("Metric {} refers to a missing question", i + 1);
+            }
+            if let Metric::SpecificResponses {
+                question_index,
+                choice,
+            }
+            | Metric::MajorityReached {
+                question_index,
+                choice,
+            } = &tracker.metric
+            {
+                let form = &self.questions[*question_index].form;
+                let choices = match tracker.metric {
+                    Metric::SpecificResponses { .. } => form.countable_choices(),
+                    _ => form.choices(),
+                };
+                if !choices.iter().any(|(_, c)| c == choice) {
+                    bail!(
+                        "Metric {} counts an answer its question doesn't have",
+                        i + 1
+                    );
+                }
+            }
+            if let Metric::NumberHistogram {
+                question_index,
+                buckets,
+            } = &tracker.metric
+            {
+                if !matches!(
+                    self.questions.get(*question_index),
+                    Some(Question {
+                        form: Form::Number,
+                        ..
+                    })
+                ) {
+                    bail!("Metric {} needs a number question", i + 1);
+                }
+                if buckets.windows(2).any(|pair| pair[0] >= pair[1]) {
+                    bail!("Metric {} bucket boundaries must be ascending", i + 1);
+                }
+            }
+            if let Metric::CoOccurrence {
+                question_index,
+                choice_a,
+                choice_b,
+            } = &tracker.metric
+            {
+                let Question { form, .. } = &self.questions[*question_index];
+                if !matches!(form, Form::Multiple { .. }) {
+                    bail!("Metric {} needs a pick several question", i + 1);
+                }
+                let choices = form.choices();
+                if ![choice_a, choice_b]
+                    .iter()
+                    .all(|&choice| choices.iter().any(|(_, c)| c == choice))
+                {
+                    bail!(
+                        "Metric {} counts an answer its question doesn't have",
+                        i + 1
+                    );
+                }
+            }
+            if let Metric::RankedWinner { question_index, .. } = &tracker.metric {
+                if !matches!(
+                    self.questions.get(*question_index),
+                    Some(Question {
+                        form: Form::RankTopN { .. },
+                        ..
+                    })
+                ) {
+                    bail!("Metric {} needs a ranking question", i + 1);
+                }
+            }
+            if let Metric::CompositeRanking { question_indices } = &tracker.metric {
+                let mut distinct = question_indices.clone();
+                distinct.sort_unstable();
+                distinct.dedup();
+                if distinct.len() < 2 || distinct.len() != question_indices.len() {
+                    bail!("Metric {} needs at least two different questions", i + 1);
+                }
+                if !question_indices.iter().all(|&question_index| {
+                    matches!(
+                        self.questions.get(question_index),
+                        Some(Question {
+                            form: Form::RankTopN { .. },
+                            ..
+                        })
+                    )
+                }) {
+                    bail!("Metric {} can only combine ranking questions", i + 1);
+                }
+                if composite_combinations(&self.questions, question_indices) > MAX_COMBINATIONS {
+                    bail!(
+                        "Metric {} has more than {MAX_COMBINATIONS} combinations of options to score",
+                        i + 1
+                    );
+                }
+            }
+            if let Metric::Plurality { question_index } = &tracker.metric {
+                if !matches!(
+                    self.questions.get(*question_index),
+                    Some(Question {
+                        form: Form::One { .. } | Form::OneOrNone { .. },
+                        ..
+                    })
+                ) {
+                    bail!("Metric {} needs a pick one question", i + 1);
+                }
+            }
+        }
+        for (i, result) in self.results.iter().enumerate() {
+            if result.disabled {
+                bail!(
+                    "Result {} is disabled because its metric was deleted",
+                    i + 1
+                );
+            }
+            if result
+                .requirements
+                .iter()
+                .any(|requirement| match requirement {
+                    Requirement::AtLeast { metric_index, .. }
+                    | Requirement::Exactly { metric_index, .. } => {
+                        *metric_index as usize >= self.metric_trackers.len()
+                    }
+                })
+            {
+                bail!("Result {} depends on a missing metric", i + 1);
+            }
+        }
+        Ok(())
     }
 }
 
+/// The header a poll's view password is sent in, with every request about the poll
+pub const PASSWORD_HEADER: &str = "x-poll-password";
+
+/// The header editors send their secret in, for the endpoints only editors can use
+pub const EDITOR_SECRET_HEADER: &str = "x-editor-secret";
+
 #[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct PollQuery {
     pub id: u64,
 }
 
+/// The poll a link points at, from its `poll_key` query parameter or, for older links
+/// like `/42`, its first path segment. The query parameter wins if there are both.
+pub fn poll_key_from_link(path: &str, query: Option<&str>) -> Option<u64> {
+    query_value(query, "poll_key")
+        .and_then(|value| value.parse().ok())
+        .or_else(|| path.trim_start_matches('/').split('/').next()?.parse().ok())
+}
+
+/// The poll a whole link points at, however it was copied: with or without the scheme and
+/// host, with a trailing slash or fragment, or just the key itself
+pub fn parse_poll_link(link: &str) -> Option<u64> {
+    let link = link.trim();
+    let link = link.split_once('#').map_or(link, |(link, _fragment)| link);
+    let link = match link.split_once("://") {
+        Some((_scheme, rest)) => rest.find(['/', '?']).map_or("", |start| &rest[start..]),
+        None => {
+            // A host without a scheme, e.g. `example.com/42` or `localhost:8080/?poll_key=42`
+            let host_end = link.find(['/', '?']).unwrap_or(link.len());
+            if link[..host_end].contains(['.', ':']) {
+                &link[host_end..]
+            } else {
+                link
+            }
+        }
+    };
+    let (path, query) = match link.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (link, None),
+    };
+    poll_key_from_link(path, query)
+}
+
+/// Whether a link asks for the poll without the app's header, to sit inside another page
+pub fn link_is_embedded(query: Option<&str>) -> bool {
+    query_value(query, "embed") == Some("1")
+}
+
+fn query_value<'a>(query: Option<&'a str>, name: &str) -> Option<&'a str> {
+    query
+        .into_iter()
+        .flat_map(|query| query.split('&'))
+        .find_map(|pair| match pair.split_once('=') {
+            Some((key, value)) if key == name => Some(value),
+            _ => None,
+        })
+}
+
 #[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum PollQueryResult {
-    Found(Poll),
+    Found(Box<Poll>),
     NotFound,
+    /// The poll has a view password, and it was missing or wrong
+    PasswordRequired,
 }
 
 #[derive(Deserialize, Serialize, PartialEq, Debug, Default, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub struct PollResponse {
     pub poll_id: u64,
     pub user: String,
+    #[serde(default)]
+    pub device_key: String,
+    /// Where to send a confirmation, if the responder wants one
+    #[serde(default)]
+    pub email: Option<String>,
     pub responses: Vec<FormResponse>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum PollSubmissionResult {
     Success,
+    /// Some picked options had no seats left, so nothing was saved
+    Full {
+        options: Vec<String>,
+    },
+    /// This person already responded and the edit deadline has passed
+    EditWindowClosed,
+    /// The poll already has `Poll::max_responses` responses, so nothing was saved
+    NoSpotsLeft,
+    /// The response broke one of the poll's constraints, so nothing was saved
+    ConstraintViolated {
+        reason: String,
+    },
     Error,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum HasRespondedResult {
+    Success { responded: bool },
+    Error,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum CreatePollResult {
-    Success { key: u64 },
+    Success { key: u64, editor_secret: String },
+    Error,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct AddEditorRequest {
+    pub poll_key: u64,
+    /// Any existing editor secret for the poll
+    pub editor_secret: String,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum AddEditorResult {
+    Success { editor_secret: String },
+    Unauthorized,
+    Error,
+}
+
+/// Emoji that can be sent as reactions, which are just for fun and never tallied
+pub const REACTIONS: [&str; 5] = ["🔥", "🎉", "👍", "😂", "😴"];
+
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ReactRequest {
+    pub poll_key: u64,
+    /// One of `REACTIONS`
+    pub emoji: String,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum ReactResult {
+    Success,
+    /// This device reacted to the poll too recently
+    TooSoon,
+    Error,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ClearResponsesRequest {
+    pub poll_key: u64,
+    pub editor_secret: String,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum ClearResponsesResult {
+    Success,
+    Unauthorized,
     Error,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
 pub enum ProgressReportResult {
-    Success { progress: PollProgress },
+    Success {
+        progress: PollProgress,
+        /// `Poll::fingerprint` of the poll as the server has it
+        #[serde(default)]
+        poll_fingerprint: Option<u64>,
+    },
     Error,
 }
+
+/// Escapes text for an iCalendar property value
+fn ics_escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+/// Splits a content line so no part is longer than 75 bytes, as iCalendar requires
+fn ics_fold(line: &str) -> String {
+    const MAX_LINE_BYTES: usize = 75;
+    let mut folded = String::new();
+    let mut line_bytes = 0;
+    for c in line.chars() {
+        if line_bytes + c.len_utf8() > MAX_LINE_BYTES {
+            folded.push_str("\r\n ");
+            // The leading space counts toward the continuation line
+            line_bytes = 1;
+        }
+        folded.push(c);
+        line_bytes += c.len_utf8();
+    }
+    folded
+}
+
+pub const RESULTS_REPORT_SCHEMA_VERSION: u32 = 1;
+
+/// Stable, self-describing snapshot of a poll's tally for integrations.
+#[derive(Deserialize, Serialize, PartialEq, Clone, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ResultsReport {
+    pub schema_version: u32,
+    pub title: String,
+    /// Rendered metric labels, `None` where the metric isn't publicly visible
+    pub metric_labels: Vec<Option<String>>,
+    pub result_descs: Vec<String>,
+    pub progress: PollProgress,
+}
+
+/// When a set of responses was submitted, and the responses
+pub type Submission = (DateTime<Utc>, Vec<FormResponse>);
+
+/// What one responder has submitted to a poll, for its editors
+#[derive(Deserialize, Serialize, PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct ResponderHistory {
+    /// The name or device the responses are matched up by, depending on `DedupStrategy`
+    pub responder: String,
+    /// Recent submissions, oldest first. The last is the one that counts. Empty for
+    /// responses from before submissions were kept.
+    pub submissions: Vec<Submission>,
+}
+
+/// How far a poll has reached, for its editors. Only counts, never who or from where.
+#[derive(Deserialize, Serialize, PartialEq, Eq, Clone, Debug, Default)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub struct PollStats {
+    /// Times the poll was opened
+    pub views: u64,
+    /// Responses taken, counting each edit
+    pub submissions: u64,
+    /// Times its progress was fetched, which open results pages do regularly
+    pub progress_fetches: u64,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(feature = "schema", derive(schemars::JsonSchema))]
+pub enum ResultsReportResult {
+    Found(Box<ResultsReport>),
+    NotFound,
+}
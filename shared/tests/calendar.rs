@@ -0,0 +1,47 @@
+use areyougoing_shared::Poll;
+use chrono::{TimeZone, Utc};
+
+fn party() -> Poll {
+    Poll {
+        title: "Party; bring snacks, drinks".to_string(),
+        description: "At Sandra's".to_string(),
+        expiration: Some(Utc.with_ymd_and_hms(2023, 6, 3, 18, 30, 0).unwrap()),
+        announcement: Some("It's on!\nSee you there".to_string()),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn event_uses_poll_details() {
+    let stamp = Utc.with_ymd_and_hms(2023, 5, 1, 9, 0, 0).unwrap();
+    let ics = party().calendar_event("7@areyougoing", stamp).unwrap();
+    let lines = ics.split_terminator("\r\n").collect::<Vec<_>>();
+    assert_eq!(lines.first(), Some(&"BEGIN:VCALENDAR"));
+    assert_eq!(lines.last(), Some(&"END:VCALENDAR"));
+    assert!(lines.contains(&"UID:7@areyougoing"));
+    assert!(lines.contains(&"DTSTAMP:20230501T090000Z"));
+    assert!(lines.contains(&"DTSTART:20230603T183000Z"));
+    assert!(lines.contains(&"SUMMARY:Party\\; bring snacks\\, drinks"));
+    assert!(lines.contains(&"DESCRIPTION:At Sandra's\\n\\nIt's on!\\nSee you there"));
+}
+
+#[test]
+fn long_lines_are_folded() {
+    let poll = Poll {
+        description: "é".repeat(100),
+        ..party()
+    };
+    let ics = poll.calendar_event("7@areyougoing", Utc::now()).unwrap();
+    assert!(ics.split("\r\n").all(|line| line.len() <= 75));
+    let unfolded = ics.replace("\r\n ", "");
+    assert!(unfolded.contains(&format!("DESCRIPTION:{}\\n\\nIt's on!", "é".repeat(100))));
+}
+
+#[test]
+fn polls_without_a_date_have_no_event() {
+    let poll = Poll {
+        expiration: None,
+        ..party()
+    };
+    assert_eq!(poll.calendar_event("7@areyougoing", Utc::now()), None);
+}
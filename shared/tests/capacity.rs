@@ -0,0 +1,62 @@
+use areyougoing_shared::{Choice, Form, FormResponse, Poll, Question};
+use std::collections::HashMap;
+
+fn carpool_poll() -> Poll {
+    Poll {
+        title: "Carpool".to_string(),
+        questions: vec![Question {
+            prompt: "Who are you riding with?".to_string(),
+            form: Form::OneOrNone {
+                options: vec!["Sandra".to_string(), "Peter".to_string()],
+                capacities: vec![Some(2), None],
+            },
+            show_if: None,
+            results_public: false,
+            participant_visible: true,
+            pinned_options: Vec::new(),
+        }],
+        ..Default::default()
+    }
+}
+
+fn ride(driver: u8) -> Vec<FormResponse> {
+    vec![FormResponse::ChooseOneOrNone(Some(Choice::Index(driver)))]
+}
+
+fn responses(riders: &[(&str, u8)]) -> HashMap<String, Vec<FormResponse>> {
+    riders
+        .iter()
+        .map(|(user, driver)| (user.to_string(), ride(*driver)))
+        .collect()
+}
+
+#[test]
+fn last_seat_can_be_taken() {
+    let poll = carpool_poll();
+    let taken = responses(&[("Bob", 0)]);
+    assert!(poll.full_choices(&taken, "Ann", &ride(0)).is_empty());
+}
+
+#[test]
+fn full_option_is_rejected() {
+    let poll = carpool_poll();
+    let taken = responses(&[("Bob", 0), ("Ann", 0)]);
+    assert_eq!(
+        poll.full_choices(&taken, "Lee", &ride(0)),
+        vec![(0, Choice::Index(0))]
+    );
+}
+
+#[test]
+fn seat_holder_can_resubmit() {
+    let poll = carpool_poll();
+    let taken = responses(&[("Bob", 0), ("Ann", 0)]);
+    assert!(poll.full_choices(&taken, "Ann", &ride(0)).is_empty());
+}
+
+#[test]
+fn unlimited_options_never_fill() {
+    let poll = carpool_poll();
+    let taken = responses(&[("Bob", 1), ("Ann", 1), ("Lee", 1)]);
+    assert!(poll.full_choices(&taken, "Sandra", &ride(1)).is_empty());
+}
@@ -0,0 +1,43 @@
+use areyougoing_shared::{duplicate_options, Form, Poll, Question};
+
+fn options(options: &[&str]) -> Vec<String> {
+    options.iter().map(|o| o.to_string()).collect()
+}
+
+#[test]
+fn whitespace_and_case_differences_are_duplicates() {
+    assert_eq!(
+        duplicate_options(&options(&["Taco Bell", "Chilis", "taco bell "])),
+        vec![0, 2]
+    );
+    assert_eq!(
+        duplicate_options(&options(&["  Chilis", "CHILIS", "Chili's"])),
+        vec![0, 1]
+    );
+    assert!(duplicate_options(&options(&["Taco Bell", "Chilis"])).is_empty());
+}
+
+#[test]
+fn duplicates_warn_without_invalidating() {
+    let poll = Poll {
+        title: "Dinner".to_string(),
+        questions: vec![Question {
+            prompt: "Where should we eat?".to_string(),
+            form: Form::One {
+                options: options(&["Taco Bell", "Chilis", "None of the above "]),
+                capacities: Vec::new(),
+                none_of_the_above: true,
+            },
+            show_if: None,
+            results_public: false,
+            participant_visible: true,
+            pinned_options: Vec::new(),
+        }],
+        ..Default::default()
+    };
+    assert!(poll.validate().is_ok());
+    assert_eq!(
+        poll.warnings(),
+        vec!["Question 1 lists the same option more than once: None of the above".to_string()]
+    );
+}
@@ -0,0 +1,49 @@
+use areyougoing_shared::{stable_hash, Form, Poll, Question};
+
+fn question(prompt: &str) -> Question {
+    Question {
+        prompt: prompt.to_string(),
+        form: Form::YesNo,
+        show_if: None,
+        results_public: false,
+        participant_visible: true,
+        pinned_options: Vec::new(),
+    }
+}
+
+fn poll() -> Poll {
+    Poll {
+        title: "Game night".to_string(),
+        questions: vec![question("Are you going?"), question("Bringing snacks?")],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn fingerprints_follow_the_questions() {
+    assert_eq!(poll().fingerprint(), poll().fingerprint());
+
+    let mut reordered = poll();
+    reordered.questions.swap(0, 1);
+    assert_ne!(reordered.fingerprint(), poll().fingerprint());
+
+    let mut reworded = poll();
+    reworded.questions[1].prompt = "Bringing drinks?".to_string();
+    assert_ne!(reworded.fingerprint(), poll().fingerprint());
+}
+
+#[test]
+fn fingerprints_ignore_the_title_and_description() {
+    let mut retitled = poll();
+    retitled.title = "Board game night".to_string();
+    retitled.description = "At Sandra's".to_string();
+    assert_eq!(retitled.fingerprint(), poll().fingerprint());
+}
+
+#[test]
+fn stable_hashes_match_fnv_1a() {
+    // Published FNV-1a test vectors, so shuffles and fingerprints never shift under anyone
+    assert_eq!(stable_hash(b""), 0xcbf2_9ce4_8422_2325);
+    assert_eq!(stable_hash(b"a"), 0xaf63_dc4c_8601_ec8c);
+    assert_eq!(stable_hash(b"foobar"), 0x8594_4171_f739_67e8);
+}
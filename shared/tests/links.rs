@@ -0,0 +1,67 @@
+use areyougoing_shared::{link_is_embedded, parse_poll_link, poll_key_from_link};
+
+#[test]
+fn keys_come_from_the_query() {
+    assert_eq!(poll_key_from_link("/", Some("poll_key=42")), Some(42));
+    assert_eq!(
+        poll_key_from_link("/", Some("embed=1&poll_key=42")),
+        Some(42)
+    );
+    assert_eq!(poll_key_from_link("/", None), None);
+    assert_eq!(poll_key_from_link("/", Some("poll_key=soon")), None);
+}
+
+#[test]
+fn older_links_have_keys_in_the_path() {
+    assert_eq!(poll_key_from_link("/42", None), Some(42));
+    assert_eq!(poll_key_from_link("/42/", Some("embed=1")), Some(42));
+    assert_eq!(poll_key_from_link("/about", None), None);
+}
+
+#[test]
+fn the_query_wins_over_the_path() {
+    assert_eq!(poll_key_from_link("/42", Some("poll_key=99")), Some(99));
+    // Unless it isn't a key
+    assert_eq!(poll_key_from_link("/42", Some("poll_key=")), Some(42));
+}
+
+#[test]
+fn whole_links_parse_however_they_were_copied() {
+    for link in [
+        "https://areyougoing.example/?poll_key=42",
+        "HTTPS://AreYouGoing.Example/?poll_key=42",
+        "https://areyougoing.example/?poll_key=42#results",
+        "https://areyougoing.example/?embed=1&poll_key=42",
+        "https://areyougoing.example/42",
+        "https://areyougoing.example/42/",
+        "http://localhost:8080/42#top",
+        "areyougoing.example/?poll_key=42",
+        "localhost:8080/42/",
+        "/?poll_key=42",
+        "?poll_key=42",
+        "/42",
+        "42",
+        "  https://areyougoing.example/?poll_key=42\n",
+    ] {
+        assert_eq!(parse_poll_link(link), Some(42), "{link}");
+    }
+    for link in [
+        "",
+        "https://areyougoing.example",
+        "https://areyougoing.example/",
+        "https://areyougoing.example/about",
+        "https://areyougoing.example/?poll_key=soon",
+        "areyougoing.example",
+        "#42",
+    ] {
+        assert_eq!(parse_poll_link(link), None, "{link}");
+    }
+}
+
+#[test]
+fn embedding_is_asked_for_in_the_query() {
+    assert!(link_is_embedded(Some("poll_key=42&embed=1")));
+    assert!(!link_is_embedded(Some("poll_key=42&embed=0")));
+    assert!(!link_is_embedded(Some("poll_key=42")));
+    assert!(!link_is_embedded(None));
+}
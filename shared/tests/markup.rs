@@ -0,0 +1,79 @@
+use areyougoing_shared::{parse_markup, MarkupSpan};
+
+fn plain(text: &str) -> MarkupSpan {
+    MarkupSpan {
+        text: text.to_string(),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn announcements_take_bold_italics_and_links() {
+    assert_eq!(
+        parse_markup("It's **on**! See _you_ [there](https://example.com/map)"),
+        vec![vec![
+            plain("It's "),
+            MarkupSpan {
+                bold: true,
+                ..plain("on")
+            },
+            plain("! See "),
+            MarkupSpan {
+                italic: true,
+                ..plain("you")
+            },
+            plain(" "),
+            MarkupSpan {
+                link: Some("https://example.com/map".to_string()),
+                ..plain("there")
+            },
+        ]]
+    );
+    assert_eq!(
+        parse_markup("**Bring *snacks***"),
+        vec![vec![
+            MarkupSpan {
+                bold: true,
+                ..plain("Bring ")
+            },
+            MarkupSpan {
+                bold: true,
+                italic: true,
+                ..plain("snacks")
+            },
+        ]]
+    );
+}
+
+#[test]
+fn each_line_is_kept() {
+    assert_eq!(
+        parse_markup("See you there\n\nBring a *friend*"),
+        vec![
+            vec![plain("See you there")],
+            Vec::new(),
+            vec![
+                plain("Bring a "),
+                MarkupSpan {
+                    italic: true,
+                    ..plain("friend")
+                },
+            ],
+        ]
+    );
+}
+
+#[test]
+fn anything_else_is_kept_as_written() {
+    for text in [
+        "<b>Not bold</b>",
+        "2 * 3 = 6",
+        "some_snake_case",
+        "**not closed",
+        "[click](javascript:alert(1))",
+        "[spaced](https://example.com/a b)",
+        "[](https://example.com)",
+    ] {
+        assert_eq!(parse_markup(text), vec![vec![plain(text)]], "{text}");
+    }
+}
@@ -0,0 +1,182 @@
+use areyougoing_shared::{
+    display_order, option_counts, Choice, Form, FormResponse, OptionCounts, Question,
+};
+use std::collections::HashMap;
+
+fn question(form: Form) -> Question {
+    Question {
+        prompt: "What should we bring?".to_string(),
+        form,
+        show_if: None,
+        results_public: false,
+        participant_visible: true,
+        pinned_options: Vec::new(),
+    }
+}
+
+fn options() -> Vec<String> {
+    vec!["Chips".to_string(), "Salsa".to_string(), "Soda".to_string()]
+}
+
+fn counts(form: Form, responses: Vec<FormResponse>) -> OptionCounts {
+    let questions = vec![question(form)];
+    let responses = responses
+        .into_iter()
+        .enumerate()
+        .map(|(i, response)| (format!("Responder {i}"), vec![response]))
+        .collect::<HashMap<_, _>>();
+    option_counts(&questions, 0, &responses)
+}
+
+fn expected(counts: Vec<u64>, abstained: u64) -> OptionCounts {
+    OptionCounts { counts, abstained }
+}
+
+#[test]
+fn one_or_none_counts_skips_as_abstaining() {
+    let form = Form::OneOrNone {
+        options: options(),
+        capacities: Vec::new(),
+    };
+    let responses = vec![
+        FormResponse::ChooseOneOrNone(Some(Choice::Index(1))),
+        FormResponse::ChooseOneOrNone(Some(Choice::Index(1))),
+        FormResponse::ChooseOneOrNone(None),
+    ];
+    assert_eq!(counts(form, responses), expected(vec![0, 2, 0], 1));
+}
+
+#[test]
+fn one_counts_none_of_the_above_as_a_choice() {
+    let form = Form::One {
+        options: options(),
+        capacities: Vec::new(),
+        none_of_the_above: true,
+    };
+    let responses = vec![
+        FormResponse::ChooseOne(Choice::Index(0)),
+        FormResponse::ChooseOne(Choice::NoneOfTheAbove),
+    ];
+    assert_eq!(counts(form, responses), expected(vec![1, 0, 0, 1], 0));
+}
+
+#[test]
+fn multiple_counts_each_pick_once() {
+    let form = Form::Multiple {
+        options: options(),
+        capacities: Vec::new(),
+    };
+    let responses = vec![
+        FormResponse::ChooseMultiple(vec![Choice::Index(0), Choice::Index(2), Choice::Index(0)]),
+        FormResponse::ChooseMultiple(vec![Choice::Index(2)]),
+        FormResponse::ChooseMultiple(Vec::new()),
+    ];
+    assert_eq!(counts(form, responses), expected(vec![1, 0, 2], 1));
+}
+
+#[test]
+fn yes_no_none_counts_maybe() {
+    let responses = vec![
+        FormResponse::ChooseOneOrNone(Some(Choice::YesOrNo(true))),
+        FormResponse::ChooseOneOrNone(Some(Choice::Maybe)),
+        FormResponse::ChooseOneOrNone(None),
+    ];
+    // Choices are No, Yes, Maybe
+    assert_eq!(
+        counts(Form::YesNoNone, responses),
+        expected(vec![0, 1, 1], 1)
+    );
+}
+
+#[test]
+fn yes_no_always_answers() {
+    let responses = vec![
+        FormResponse::ChooseOne(Choice::YesOrNo(true)),
+        FormResponse::ChooseOne(Choice::YesOrNo(false)),
+        FormResponse::ChooseOne(Choice::YesOrNo(true)),
+    ];
+    assert_eq!(counts(Form::YesNo, responses), expected(vec![1, 2], 0));
+}
+
+#[test]
+fn ranked_counts_any_rank() {
+    let form = Form::RankTopN {
+        options: options(),
+        n: 2,
+    };
+    let responses = vec![
+        FormResponse::RankedChoice(vec![Choice::Index(2), Choice::Index(0)]),
+        FormResponse::RankedChoice(vec![Choice::Index(0)]),
+        FormResponse::RankedChoice(Vec::new()),
+    ];
+    assert_eq!(counts(form, responses), expected(vec![2, 0, 1], 1));
+}
+
+#[test]
+fn number_has_no_choices() {
+    let responses = vec![FormResponse::Number(3)];
+    assert_eq!(counts(Form::Number, responses), expected(Vec::new(), 0));
+}
+
+#[test]
+fn hidden_questions_are_not_counted() {
+    let questions = vec![
+        question(Form::YesNo),
+        Question {
+            show_if: Some((0, Choice::YesOrNo(true))),
+            ..question(Form::Multiple {
+                options: options(),
+                capacities: Vec::new(),
+            })
+        },
+    ];
+    let responses = [
+        ("Sandra", true, vec![Choice::Index(1)]),
+        ("Peter", false, vec![Choice::Index(1)]),
+        ("Bob", true, Vec::new()),
+    ]
+    .into_iter()
+    .map(|(user, going, picks)| {
+        (
+            user.to_string(),
+            vec![
+                FormResponse::ChooseOne(Choice::YesOrNo(going)),
+                FormResponse::ChooseMultiple(picks),
+            ],
+        )
+    })
+    .collect::<HashMap<_, _>>();
+    assert_eq!(
+        option_counts(&questions, 1, &responses),
+        expected(vec![0, 1, 0], 1)
+    );
+}
+
+#[test]
+fn shuffled_options_keep_pins_and_count_the_same() {
+    let pinned = [0, 4];
+    for seed in 0..50 {
+        let order = display_order(5, &pinned, seed);
+        let mut sorted = order.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, vec![0, 1, 2, 3, 4], "seed {seed}: {order:?}");
+        assert_eq!((order[0], order[4]), (0, 4), "seed {seed}: {order:?}");
+    }
+    assert!((0..50).any(|seed| display_order(5, &pinned, seed) != vec![0, 1, 2, 3, 4]));
+
+    // Whoever clicks the second button shown picks the option shown there, by its
+    // original index, so the count is the same whatever order each responder saw
+    let form = || Form::Multiple {
+        options: options(),
+        capacities: Vec::new(),
+    };
+    let orders = (0..3)
+        .map(|seed| display_order(3, &[], seed))
+        .collect::<Vec<_>>();
+    assert_eq!(orders, vec![vec![0, 2, 1], vec![1, 0, 2], vec![2, 0, 1]]);
+    let responses = orders
+        .iter()
+        .map(|order| FormResponse::ChooseMultiple(vec![Choice::Index(order[1] as u8)]))
+        .collect::<Vec<_>>();
+    assert_eq!(counts(form(), responses), expected(vec![2, 0, 1], 0));
+}
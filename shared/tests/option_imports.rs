@@ -0,0 +1,51 @@
+use areyougoing_shared::parse_option_list;
+
+fn options(options: &[&str]) -> Vec<String> {
+    options.iter().map(|o| o.to_string()).collect()
+}
+
+#[test]
+fn one_line_splits_on_commas() {
+    assert_eq!(
+        parse_option_list("Tacos, Pizza,Curry ,", false),
+        options(&["Tacos", "Pizza", "Curry"])
+    );
+    assert_eq!(
+        parse_option_list(r#"Tacos, "Fish, Chips", "The ""Good"" Place""#, false),
+        options(&["Tacos", "Fish, Chips", r#"The "Good" Place"#])
+    );
+}
+
+#[test]
+fn each_line_is_a_whole_option() {
+    assert_eq!(
+        parse_option_list("Smith, John\r\n\nDoe, Jane\n\"Lee\"\n", false),
+        options(&["Smith, John", "Doe, Jane", "Lee"])
+    );
+}
+
+#[test]
+fn json_arrays_are_read_as_json() {
+    assert_eq!(
+        parse_option_list(r#"["Tacos", "Fish, Chips", 42, ""]"#, false),
+        options(&["Tacos", "Fish, Chips", "42"])
+    );
+}
+
+#[test]
+fn csvs_skip_their_header_and_take_the_first_column() {
+    let csv = "Restaurant,Cuisine\n\
+        Taco Bell,Mexican\n\
+        \"Fish, Chips & Co\",British\n\
+        \"Two\nLines\",Fusion\n\
+        \n";
+    assert_eq!(
+        parse_option_list(csv, true),
+        options(&["Taco Bell", "Fish, Chips & Co", "Two\nLines"])
+    );
+    // Even a single column has a header
+    assert_eq!(
+        parse_option_list("Name\nTacos\nPizza", true),
+        options(&["Tacos", "Pizza"])
+    );
+}
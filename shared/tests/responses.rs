@@ -0,0 +1,173 @@
+use areyougoing_shared::{
+    option_counts, shown_questions, Choice, Constraint, Form, FormResponse, Poll, PollResponse,
+    Question, ResponderField,
+};
+use std::collections::HashMap;
+
+fn poll() -> Poll {
+    Poll {
+        title: "Dinner".to_string(),
+        questions: vec![
+            Question {
+                prompt: "Are you going?".to_string(),
+                form: Form::YesNo,
+                show_if: None,
+                results_public: false,
+                participant_visible: true,
+                pinned_options: Vec::new(),
+            },
+            Question {
+                prompt: "Where should we eat?".to_string(),
+                form: Form::One {
+                    options: vec!["Chilis".to_string(), "Taco Bell".to_string()],
+                    capacities: Vec::new(),
+                    none_of_the_above: false,
+                },
+                show_if: None,
+                results_public: false,
+                participant_visible: true,
+                pinned_options: Vec::new(),
+            },
+        ],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn initial_responses_are_valid() {
+    let poll = poll();
+    assert!(poll.validate_responses(&poll.init_responses()));
+}
+
+#[test]
+fn rejects_length_mismatch() {
+    let poll = poll();
+    let mut responses = poll.init_responses();
+    responses.pop();
+    assert!(!poll.validate_responses(&responses));
+}
+
+#[test]
+fn rejects_variant_mismatch() {
+    let poll = poll();
+    let mut responses = poll.init_responses();
+    responses[1] = FormResponse::ChooseMultiple(vec![Choice::Index(0)]);
+    assert!(!poll.validate_responses(&responses));
+}
+
+#[test]
+fn rejects_out_of_range_choice() {
+    let poll = poll();
+    let mut responses = poll.init_responses();
+    responses[1] = FormResponse::ChooseOne(Choice::Index(2));
+    assert!(!poll.validate_responses(&responses));
+}
+
+#[test]
+fn rejects_repeated_rankings() {
+    let mut poll = poll();
+    poll.questions[1].form = Form::RankTopN {
+        options: vec!["Chilis".to_string(), "Taco Bell".to_string()],
+        n: 3,
+    };
+    let mut responses = poll.init_responses();
+    responses[1] = FormResponse::RankedChoice(vec![Choice::Index(1), Choice::Index(0)]);
+    assert!(poll.validate_responses(&responses));
+    responses[1] = FormResponse::RankedChoice(vec![Choice::Index(0); 3]);
+    assert!(!poll.validate_responses(&responses));
+}
+
+#[test]
+fn unrecognized_response_deserializes_as_unknown() {
+    let json = r#"[{"ChooseOne":{"Index":1}},{"Signature":"Sandra"}]"#;
+    let responses: Vec<FormResponse> = serde_json::from_str(json).unwrap();
+    assert_eq!(
+        responses,
+        vec![
+            FormResponse::ChooseOne(Choice::Index(1)),
+            FormResponse::Unknown
+        ]
+    );
+    let round_tripped: Vec<FormResponse> =
+        serde_json::from_str(&serde_json::to_string(&responses).unwrap()).unwrap();
+    assert_eq!(round_tripped, responses);
+
+    let poll = poll();
+    let mut responses = poll.init_responses();
+    responses[1] = FormResponse::Unknown;
+    assert!(!poll.validate_responses(&responses));
+}
+
+#[test]
+fn only_picked_responses_are_answered() {
+    assert!(!FormResponse::ChooseOneOrNone(None).is_answered());
+    assert!(FormResponse::ChooseOneOrNone(Some(Choice::Index(0))).is_answered());
+    assert!(!FormResponse::ChooseMultiple(Vec::new()).is_answered());
+    assert!(FormResponse::ChooseMultiple(vec![Choice::Index(1)]).is_answered());
+    assert!(!FormResponse::RankedChoice(Vec::new()).is_answered());
+    assert!(FormResponse::Number(0).is_answered());
+    assert!(!FormResponse::Unknown.is_answered());
+}
+
+#[test]
+fn constraints_explain_what_a_response_breaks() {
+    let poll = Poll {
+        constraints: vec![
+            Constraint::UniqueBy(ResponderField::Name),
+            Constraint::RequiresChoice(1, Choice::Index(1)),
+        ],
+        ..poll()
+    };
+    assert!(poll.validate().is_ok());
+    let response = |user: &str, place| PollResponse {
+        poll_id: 1,
+        user: user.to_string(),
+        device_key: String::new(),
+        email: None,
+        responses: vec![
+            FormResponse::ChooseOne(Choice::YesOrNo(true)),
+            FormResponse::ChooseOne(Choice::Index(place)),
+        ],
+    };
+    assert_eq!(poll.constraint_violation(&response("Sandra", 1), &[]), None);
+    assert_eq!(
+        poll.unique_values(&response(" Sandra", 1)),
+        vec![(ResponderField::Name, "sandra".to_string())]
+    );
+    assert_eq!(
+        poll.constraint_violation(
+            &response("Peter", 1),
+            &[(ResponderField::Name, "peter".to_string())]
+        ),
+        Some("Someone with the same name has already responded".to_string())
+    );
+    assert_eq!(
+        poll.constraint_violation(&response("Peter", 0), &[]),
+        Some("\"Where should we eat?\" needs Taco Bell picked".to_string())
+    );
+
+    let missing_choice = Poll {
+        constraints: vec![Constraint::RequiresChoice(1, Choice::Index(2))],
+        ..poll
+    };
+    assert!(missing_choice.validate().is_err());
+}
+
+#[test]
+fn questions_responders_arent_asked_never_count() {
+    let mut poll = poll();
+    poll.questions[1].participant_visible = false;
+    let mut responses = vec![
+        FormResponse::ChooseOne(Choice::YesOrNo(true)),
+        FormResponse::ChooseOne(Choice::Index(1)),
+    ];
+    assert_eq!(shown_questions(&poll.questions, &responses), [true, false]);
+
+    poll.reset_hidden_answers(&mut responses);
+    assert_eq!(responses[1], poll.questions[1].init_response());
+
+    let responses = [("Sandra".to_string(), responses)]
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+    assert_eq!(option_counts(&poll.questions, 1, &responses).counts, [0, 0]);
+}
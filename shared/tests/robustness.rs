@@ -0,0 +1,353 @@
+//! Throws arbitrary polls and responses at the tally logic. Anything a poll that passes
+//! `Poll::validate` can be tallied against, including stored responses that no longer
+//! match its questions, must not panic.
+
+use areyougoing_shared::{
+    option_counts, remap_metric_indices, Choice, DedupStrategy, Form, FormResponse, ListEdits,
+    Metric, MetricTracker, Poll, PollResult, Progress, Question, Requirement, ResultState,
+    TiebreakRule,
+};
+use proptest::{collection::vec, option, prelude::*};
+use std::collections::HashMap;
+
+fn choice() -> impl Strategy<Value = Choice> {
+    prop_oneof![
+        (0u8..6).prop_map(Choice::Index),
+        any::<bool>().prop_map(Choice::YesOrNo),
+        Just(Choice::Maybe),
+        Just(Choice::NoneOfTheAbove),
+        Just(Choice::NoSelection),
+    ]
+}
+
+fn form() -> impl Strategy<Value = Form> {
+    let options = || vec("[a-d]", 1..4);
+    let capacities = || vec(option::of(0u64..3), 0..4);
+    prop_oneof![
+        (options(), capacities()).prop_map(|(options, capacities)| Form::OneOrNone {
+            options,
+            capacities
+        }),
+        (options(), capacities(), any::<bool>()).prop_map(
+            |(options, capacities, none_of_the_above)| Form::One {
+                options,
+                capacities,
+                none_of_the_above
+            }
+        ),
+        (options(), capacities()).prop_map(|(options, capacities)| Form::Multiple {
+            options,
+            capacities
+        }),
+        Just(Form::YesNoNone),
+        Just(Form::YesNo),
+        (options(), 0usize..5).prop_map(|(options, n)| Form::RankTopN { options, n }),
+        Just(Form::Number),
+    ]
+}
+
+fn question() -> impl Strategy<Value = Question> {
+    (
+        form(),
+        option::of((0usize..4, choice())),
+        any::<bool>(),
+        any::<bool>(),
+    )
+        .prop_map(
+            |(form, show_if, results_public, participant_visible)| Question {
+                prompt: "Question".to_string(),
+                form,
+                show_if,
+                results_public,
+                participant_visible,
+                pinned_options: Vec::new(),
+            },
+        )
+}
+
+fn metric() -> impl Strategy<Value = Metric> {
+    let tiebreak = prop_oneof![
+        Just(TiebreakRule::Lexicographic),
+        any::<u64>().prop_map(TiebreakRule::Seeded),
+        Just(TiebreakRule::FirstSubmitted),
+    ];
+    prop_oneof![
+        (0usize..4, choice()).prop_map(|(question_index, choice)| Metric::SpecificResponses {
+            question_index,
+            choice
+        }),
+        (0usize..4, vec(-3i64..3, 0..3)).prop_map(|(question_index, buckets)| {
+            Metric::NumberHistogram {
+                question_index,
+                buckets,
+            }
+        }),
+        (0usize..4, tiebreak).prop_map(|(question_index, tiebreak)| Metric::RankedWinner {
+            question_index,
+            tiebreak
+        }),
+        (0usize..4).prop_map(|question_index| Metric::Plurality { question_index }),
+        (0usize..4, choice()).prop_map(|(question_index, choice)| Metric::MajorityReached {
+            question_index,
+            choice
+        }),
+        (0usize..4, choice(), choice()).prop_map(|(question_index, choice_a, choice_b)| {
+            Metric::CoOccurrence {
+                question_index,
+                choice_a,
+                choice_b,
+            }
+        }),
+        vec(0usize..4, 0..3)
+            .prop_map(|question_indices| Metric::CompositeRanking { question_indices }),
+        Just(Metric::CompletionRate {}),
+    ]
+}
+
+fn poll() -> impl Strategy<Value = Poll> {
+    let requirement = prop_oneof![
+        (0u16..4, 0u64..3).prop_map(|(metric_index, minimum)| Requirement::AtLeast {
+            metric_index,
+            minimum,
+        }),
+        (0u16..4, 0u64..3).prop_map(|(metric_index, count)| Requirement::Exactly {
+            metric_index,
+            count,
+        }),
+    ];
+    let result = vec(requirement, 0..3).prop_map(|requirements| PollResult {
+        desc: "Result".to_string(),
+        requirements,
+        disabled: false,
+    });
+    (
+        vec(question(), 0..4),
+        vec(metric(), 0..3),
+        vec(result, 0..3),
+    )
+        .prop_map(|(questions, metrics, results)| Poll {
+            title: "Poll".to_string(),
+            questions,
+            metric_trackers: metrics
+                .into_iter()
+                .map(|metric| MetricTracker {
+                    metric,
+                    publicly_visible: true,
+                })
+                .collect(),
+            results,
+            ..Default::default()
+        })
+}
+
+fn form_response() -> impl Strategy<Value = FormResponse> {
+    prop_oneof![
+        option::of(choice()).prop_map(FormResponse::ChooseOneOrNone),
+        choice().prop_map(FormResponse::ChooseOne),
+        vec(choice(), 0..4).prop_map(FormResponse::ChooseMultiple),
+        vec(choice(), 0..4).prop_map(FormResponse::RankedChoice),
+        any::<i64>().prop_map(FormResponse::Number),
+        Just(FormResponse::Unknown),
+    ]
+}
+
+/// Responses of any length, with any choices, as if stored against some other poll
+fn responses() -> impl Strategy<Value = HashMap<String, Vec<FormResponse>>> {
+    proptest::collection::hash_map("[a-c]", vec(form_response(), 0..5), 0..4)
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig {
+        max_global_rejects: 100_000,
+        ..ProptestConfig::default()
+    })]
+
+    #[test]
+    fn tallying_valid_polls_never_panics(poll in poll(), responses in responses()) {
+        prop_assume!(poll.validate().is_ok());
+        let order = responses.keys().cloned().collect::<Vec<_>>();
+        let (progresses, _) = poll.tally(&responses, &order);
+        for (tracker, progress) in poll.metric_trackers.iter().zip(progresses) {
+            tracker.metric.render(&poll.questions);
+            progress.render();
+        }
+        for i in 0..poll.questions.len() {
+            option_counts(&poll.questions, i, &responses);
+        }
+        if let Some(new_responses) = responses.values().next() {
+            poll.validate_responses(new_responses);
+            poll.full_choices(&responses, "a", new_responses);
+        }
+    }
+
+    #[test]
+    fn deserializing_arbitrary_json_never_panics(json in "[\\[\\]{}\":,0-9a-zA-Z ]{0,40}") {
+        let _ = serde_json::from_str::<Vec<FormResponse>>(&json);
+        let _ = serde_json::from_str::<Poll>(&json);
+    }
+}
+
+fn yes_no_poll() -> Poll {
+    Poll {
+        title: "Party".to_string(),
+        questions: vec![Question {
+            prompt: "Are you going?".to_string(),
+            form: Form::YesNoNone,
+            show_if: None,
+            results_public: false,
+            participant_visible: true,
+            pinned_options: Vec::new(),
+        }],
+        metric_trackers: vec![MetricTracker {
+            metric: Metric::SpecificResponses {
+                question_index: 0,
+                choice: Choice::YesOrNo(true),
+            },
+            publicly_visible: true,
+        }],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn short_stored_responses_count_nothing() {
+    let poll = yes_no_poll();
+    let responses = [("Sandra".to_string(), Vec::new())].into_iter().collect();
+    let (progresses, _) = poll.tally(&responses, &[]);
+    assert_eq!(progresses, vec![Progress::Count(0)]);
+}
+
+#[test]
+fn results_on_missing_metrics_are_invalid() {
+    let mut poll = yes_no_poll();
+    poll.results = vec![PollResult {
+        desc: "Party".to_string(),
+        requirements: vec![Requirement::AtLeast {
+            metric_index: 1,
+            minimum: 1,
+        }],
+        disabled: false,
+    }];
+    assert!(poll.validate().is_err());
+}
+
+#[test]
+fn results_follow_their_metrics_when_metrics_move() {
+    let mut poll = yes_no_poll();
+    let maybe = Metric::SpecificResponses {
+        question_index: 0,
+        choice: Choice::Maybe,
+    };
+    poll.metric_trackers.push(MetricTracker {
+        metric: maybe.clone(),
+        publicly_visible: true,
+    });
+    poll.results = vec![PollResult {
+        desc: "Ask again later".to_string(),
+        requirements: vec![Requirement::AtLeast {
+            metric_index: 1,
+            minimum: 1,
+        }],
+        disabled: false,
+    }];
+
+    // Moving "Maybe" up, as its up button does
+    let edits = ListEdits {
+        swapped: Some((1, 0)),
+        ..Default::default()
+    };
+    poll.metric_trackers.swap(1, 0);
+    remap_metric_indices(&mut poll.results, |i| edits.new_position(i));
+    let Requirement::AtLeast { metric_index, .. } = poll.results[0].requirements[0] else {
+        unreachable!()
+    };
+    assert_eq!(poll.metric_trackers[metric_index as usize].metric, maybe);
+    assert!(poll.validate().is_ok());
+
+    // Deleting the metric keeps its result, disabled, rather than pointing it at another
+    let edits = ListEdits {
+        deleted: Some(0),
+        ..Default::default()
+    };
+    poll.metric_trackers.remove(0);
+    remap_metric_indices(&mut poll.results, |i| edits.new_position(i));
+    assert_eq!(poll.results.len(), 1);
+    assert!(poll.results[0].disabled);
+    assert!(poll.validate().is_err());
+    let met = ResultState::evaluate(&poll.results[0], &[Progress::Count(5)]);
+    assert!(!met.overall_met);
+}
+
+#[test]
+fn list_edits_track_where_items_went() {
+    let inserted = ListEdits {
+        inserted: Some(1),
+        ..Default::default()
+    };
+    assert_eq!(
+        (0..3).map(|i| inserted.new_position(i)).collect::<Vec<_>>(),
+        vec![Some(0), Some(2), Some(3)]
+    );
+    let deleted = ListEdits {
+        deleted: Some(1),
+        ..Default::default()
+    };
+    assert_eq!(
+        (0..3).map(|i| deleted.new_position(i)).collect::<Vec<_>>(),
+        vec![Some(0), None, Some(1)]
+    );
+}
+
+#[test]
+fn metrics_on_missing_answers_are_invalid() {
+    let mut poll = yes_no_poll();
+    poll.metric_trackers[0].metric = Metric::SpecificResponses {
+        question_index: 0,
+        choice: Choice::Index(3),
+    };
+    assert!(poll.validate().is_err());
+}
+
+#[test]
+fn polls_need_a_title_and_options_to_pick() {
+    let untitled = Poll {
+        title: " ".to_string(),
+        ..yes_no_poll()
+    };
+    assert!(untitled.validate().is_err());
+    let mut no_options = yes_no_poll();
+    no_options.questions[0].form = Form::One {
+        options: Vec::new(),
+        capacities: Vec::new(),
+        none_of_the_above: true,
+    };
+    no_options.metric_trackers.clear();
+    assert!(no_options.validate().is_err());
+}
+
+#[test]
+fn polls_with_every_question_hidden_are_invalid() {
+    let mut poll = yes_no_poll();
+    assert!(poll.validate().is_ok());
+    poll.questions[0].participant_visible = false;
+    assert!(poll.validate().is_err());
+}
+
+#[test]
+fn responder_names_need_responses_kept_by_name() {
+    let poll = Poll {
+        show_responder_names: Some(0),
+        ..yes_no_poll()
+    };
+    assert!(poll.validate().is_ok());
+    let by_device = Poll {
+        dedup: DedupStrategy::ByDevice,
+        ..poll.clone()
+    };
+    assert!(by_device.validate().is_err());
+    let missing_question = Poll {
+        show_responder_names: Some(1),
+        ..poll
+    };
+    assert!(missing_question.validate().is_err());
+}
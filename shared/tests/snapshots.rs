@@ -0,0 +1,88 @@
+//! Golden fixtures pinning down how polls tally. Each file in `tests/fixtures` holds a
+//! poll, its responses in submission order, and the `PollProgress` they should produce.
+//! New kinds of metric or requirement need a fixture that uses them.
+
+use areyougoing_shared::{FormResponse, Metric, Poll, PollProgress, Requirement};
+use serde::Deserialize;
+use std::{collections::HashSet, fs, path::Path};
+
+#[derive(Deserialize)]
+struct Fixture {
+    poll: Poll,
+    responses: Vec<(String, Vec<FormResponse>)>,
+    expected: PollProgress,
+}
+
+fn fixtures() -> Vec<(String, Fixture)> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures");
+    let mut fixtures = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| {
+            let path = entry.unwrap().path();
+            let fixture = ron::from_str(&fs::read_to_string(&path).unwrap())
+                .unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+            (
+                path.file_stem().unwrap().to_string_lossy().into_owned(),
+                fixture,
+            )
+        })
+        .collect::<Vec<_>>();
+    fixtures.sort_by(|(a, _), (b, _)| a.cmp(b));
+    fixtures
+}
+
+/// Adding a variant breaks these matches, as a reminder to give it a fixture
+fn metric_kind(metric: &Metric) -> usize {
+    match metric {
+        Metric::SpecificResponses { .. } => 0,
+        Metric::NumberHistogram { .. } => 1,
+        Metric::RankedWinner { .. } => 2,
+        Metric::Plurality { .. } => 3,
+        Metric::CompositeRanking { .. } => 4,
+        Metric::MajorityReached { .. } => 5,
+        Metric::CoOccurrence { .. } => 6,
+        Metric::CompletionRate {} => 7,
+    }
+}
+const METRIC_KINDS: usize = 8;
+
+fn requirement_kind(requirement: &Requirement) -> usize {
+    match requirement {
+        Requirement::AtLeast { .. } => 0,
+        Requirement::Exactly { .. } => 1,
+    }
+}
+const REQUIREMENT_KINDS: usize = 2;
+
+#[test]
+fn snapshots_match_fixtures() {
+    for (name, fixture) in fixtures() {
+        fixture
+            .poll
+            .validate()
+            .unwrap_or_else(|e| panic!("{name}: {e}"));
+        assert_eq!(
+            fixture.poll.snapshot(&fixture.responses),
+            fixture.expected,
+            "{name}"
+        );
+    }
+}
+
+#[test]
+fn every_metric_and_requirement_has_a_fixture() {
+    let fixtures = fixtures();
+    let polls = fixtures.iter().map(|(_, fixture)| &fixture.poll);
+    let metric_kinds = polls
+        .clone()
+        .flat_map(|poll| &poll.metric_trackers)
+        .map(|tracker| metric_kind(&tracker.metric))
+        .collect::<HashSet<_>>();
+    assert_eq!(metric_kinds.len(), METRIC_KINDS);
+    let requirement_kinds = polls
+        .flat_map(|poll| &poll.results)
+        .flat_map(|result| &result.requirements)
+        .map(requirement_kind)
+        .collect::<HashSet<_>>();
+    assert_eq!(requirement_kinds.len(), REQUIREMENT_KINDS);
+}
@@ -0,0 +1,634 @@
+use areyougoing_shared::{
+    format_count, Choice, Form, FormResponse, Metric, MetricTracker, Poll, Progress, Question,
+    Requirement, TiebreakRule, STANDINGS_SHOWN,
+};
+use std::collections::HashMap;
+
+fn multiple_choice_poll() -> Poll {
+    Poll {
+        title: "Snacks".to_string(),
+        questions: vec![Question {
+            prompt: "What should we bring?".to_string(),
+            form: Form::Multiple {
+                options: vec!["Chips".to_string(), "Salsa".to_string(), "Soda".to_string()],
+                capacities: Vec::new(),
+            },
+            show_if: None,
+            results_public: false,
+            participant_visible: true,
+            pinned_options: Vec::new(),
+        }],
+        ..Default::default()
+    }
+}
+
+fn count(metric_choice: u8, responses: &[(&str, Vec<Choice>)]) -> u64 {
+    let poll = multiple_choice_poll();
+    let metric = Metric::SpecificResponses {
+        question_index: 0,
+        choice: Choice::Index(metric_choice),
+    };
+    let responses = responses
+        .iter()
+        .map(|(user, choices)| {
+            (
+                user.to_string(),
+                vec![FormResponse::ChooseMultiple(choices.clone())],
+            )
+        })
+        .collect::<HashMap<_, _>>();
+    let Progress::Count(count) = metric.calculate_progress(&poll.questions, &responses, &[]) else {
+        panic!("SpecificResponses should produce a count");
+    };
+    count
+}
+
+#[test]
+fn empty_multiple_selection_counts_nothing() {
+    assert_eq!(count(0, &[("Sandra", vec![])]), 0);
+}
+
+#[test]
+fn single_matching_selection_counts_once() {
+    assert_eq!(count(1, &[("Sandra", vec![Choice::Index(1)])]), 1);
+}
+
+#[test]
+fn only_matching_selections_count() {
+    let responses = [
+        ("Sandra", vec![Choice::Index(0), Choice::Index(2)]),
+        ("Peter", vec![Choice::Index(1)]),
+        ("Bob", vec![Choice::Index(2), Choice::Index(1)]),
+    ];
+    assert_eq!(count(0, &responses), 1);
+    assert_eq!(count(1, &responses), 2);
+    assert_eq!(count(2, &responses), 2);
+}
+
+#[test]
+fn duplicated_selection_counts_once() {
+    assert_eq!(
+        count(0, &[("Sandra", vec![Choice::Index(0), Choice::Index(0)])]),
+        1
+    );
+}
+
+#[test]
+fn init_responses_start_empty_for_multiple() {
+    assert_eq!(
+        multiple_choice_poll().init_responses(),
+        vec![FormResponse::ChooseMultiple(Vec::new())]
+    );
+}
+
+#[test]
+fn empty_multiple_selection_round_trips() {
+    let responses = multiple_choice_poll().init_responses();
+    let json = serde_json::to_string(&responses).unwrap();
+    let round_tripped: Vec<FormResponse> = serde_json::from_str(&json).unwrap();
+    assert_eq!(round_tripped, responses);
+}
+
+#[test]
+fn histogram_boundary_values_go_to_upper_bucket() {
+    let questions = vec![Question {
+        prompt: "How many guests are you bringing?".to_string(),
+        form: Form::Number,
+        show_if: None,
+        results_public: false,
+        participant_visible: true,
+        pinned_options: Vec::new(),
+    }];
+    let metric = Metric::NumberHistogram {
+        question_index: 0,
+        buckets: vec![0, 2, 5],
+    };
+    let responses = [
+        ("Sandra", -1),
+        ("Peter", 0),
+        ("Bob", 2),
+        ("Ann", 4),
+        ("Lee", 5),
+    ]
+    .iter()
+    .map(|(user, value)| (user.to_string(), vec![FormResponse::Number(*value)]))
+    .collect::<HashMap<_, _>>();
+    assert_eq!(
+        metric.calculate_progress(&questions, &responses, &[]),
+        Progress::Histogram(vec![1, 1, 2, 1])
+    );
+}
+
+fn ranked_winner(tiebreak: TiebreakRule, rankings: &[(&str, Vec<u8>)]) -> Progress {
+    let questions = vec![Question {
+        prompt: "Where should we eat?".to_string(),
+        form: Form::RankTopN {
+            options: vec![
+                "Tacos".to_string(),
+                "Pizza".to_string(),
+                "Curry".to_string(),
+            ],
+            n: 2,
+        },
+        show_if: None,
+        results_public: false,
+        participant_visible: true,
+        pinned_options: Vec::new(),
+    }];
+    let responses = rankings
+        .iter()
+        .map(|(user, ranking)| {
+            (
+                user.to_string(),
+                vec![FormResponse::RankedChoice(
+                    ranking.iter().map(|&i| Choice::Index(i)).collect(),
+                )],
+            )
+        })
+        .collect::<HashMap<_, _>>();
+    let order = rankings
+        .iter()
+        .map(|(user, _)| user.to_string())
+        .collect::<Vec<_>>();
+    Metric::RankedWinner {
+        question_index: 0,
+        tiebreak,
+    }
+    .calculate_progress(&questions, &responses, &order)
+}
+
+#[test]
+fn ranked_ties_break_by_rule() {
+    let rankings = [("Sandra", vec![2, 0]), ("Peter", vec![0, 2])];
+    assert_eq!(
+        ranked_winner(TiebreakRule::Lexicographic, &rankings),
+        Progress::Winner {
+            winner: Some("Curry".to_string()),
+            tiebreak: Some(TiebreakRule::Lexicographic),
+        }
+    );
+    // The same seed always picks the same option, and different seeds can differ
+    for (seed, winner) in [(7, "Curry"), (2, "Tacos")] {
+        assert_eq!(
+            ranked_winner(TiebreakRule::Seeded(seed), &rankings),
+            Progress::Winner {
+                winner: Some(winner.to_string()),
+                tiebreak: Some(TiebreakRule::Seeded(seed)),
+            }
+        );
+    }
+}
+
+#[test]
+fn ranked_ties_can_go_to_the_first_submitted() {
+    let first_wins = |rankings: &[(&str, Vec<u8>)], winner: &str| {
+        assert_eq!(
+            ranked_winner(TiebreakRule::FirstSubmitted, rankings),
+            Progress::Winner {
+                winner: Some(winner.to_string()),
+                tiebreak: Some(TiebreakRule::FirstSubmitted),
+            }
+        );
+    };
+    first_wins(&[("Peter", vec![0, 2]), ("Sandra", vec![2, 0])], "Tacos");
+    first_wins(&[("Sandra", vec![2, 0]), ("Peter", vec![0, 2])], "Curry");
+}
+
+#[test]
+fn plurality_ties_go_to_first_alphabetically() {
+    let questions = vec![Question {
+        prompt: "Where should we eat?".to_string(),
+        form: Form::One {
+            options: vec![
+                "Tacos".to_string(),
+                "Curry".to_string(),
+                "Pizza".to_string(),
+            ],
+            capacities: Vec::new(),
+            none_of_the_above: false,
+        },
+        show_if: None,
+        results_public: false,
+        participant_visible: true,
+        pinned_options: Vec::new(),
+    }];
+    let winner = |picks: &[(&str, u8)]| {
+        let responses = picks
+            .iter()
+            .map(|(user, i)| {
+                (
+                    user.to_string(),
+                    vec![FormResponse::ChooseOne(Choice::Index(*i))],
+                )
+            })
+            .collect::<HashMap<_, _>>();
+        Metric::Plurality { question_index: 0 }.calculate_progress(&questions, &responses, &[])
+    };
+    assert_eq!(
+        winner(&[("Sandra", 0), ("Peter", 0), ("Bob", 2)]),
+        Progress::Winner {
+            winner: Some("Tacos".to_string()),
+            tiebreak: None,
+        }
+    );
+    assert_eq!(
+        winner(&[("Sandra", 0), ("Peter", 1)]),
+        Progress::Winner {
+            winner: Some("Curry".to_string()),
+            tiebreak: Some(TiebreakRule::Lexicographic),
+        }
+    );
+}
+
+#[test]
+fn composite_ranking_sums_points_across_questions() {
+    let ranking = |prompt: &str, options: &[&str]| Question {
+        prompt: prompt.to_string(),
+        form: Form::RankTopN {
+            options: options.iter().map(|o| o.to_string()).collect(),
+            n: 2,
+        },
+        show_if: None,
+        results_public: false,
+        participant_visible: true,
+        pinned_options: Vec::new(),
+    };
+    let questions = vec![
+        ranking("Where?", &["Park", "Beach"]),
+        ranking("When?", &["Friday", "Saturday"]),
+    ];
+    let ranked = |venues: [u8; 2], dates: [u8; 2]| {
+        vec![
+            FormResponse::RankedChoice(venues.iter().map(|&i| Choice::Index(i)).collect()),
+            FormResponse::RankedChoice(dates.iter().map(|&i| Choice::Index(i)).collect()),
+        ]
+    };
+    // Park 2 + 2 + 1 = 5, Beach 1 + 1 + 2 = 4, Friday 1 + 2 + 1 = 4, Saturday 2 + 1 + 2 = 5
+    let responses = [
+        ("Sandra", ranked([0, 1], [1, 0])),
+        ("Peter", ranked([0, 1], [0, 1])),
+        ("Bob", ranked([1, 0], [1, 0])),
+    ]
+    .into_iter()
+    .map(|(user, responses)| (user.to_string(), responses))
+    .collect::<HashMap<_, _>>();
+    let metric = Metric::CompositeRanking {
+        question_indices: vec![0, 1],
+    };
+    assert_eq!(
+        metric.calculate_progress(&questions, &responses, &[]),
+        Progress::Standings(vec![
+            ("Park + Saturday".to_string(), 10),
+            ("Beach + Saturday".to_string(), 9),
+            ("Park + Friday".to_string(), 9),
+            ("Beach + Friday".to_string(), 8),
+        ])
+    );
+}
+
+#[test]
+fn composite_rankings_stay_small() {
+    let ranking = |options: usize| Question {
+        prompt: "Which?".to_string(),
+        form: Form::RankTopN {
+            options: (0..options).map(|o| format!("Option {o}")).collect(),
+            n: 1,
+        },
+        show_if: None,
+        results_public: false,
+        participant_visible: true,
+        pinned_options: Vec::new(),
+    };
+    let metric = |questions: usize| MetricTracker {
+        metric: Metric::CompositeRanking {
+            question_indices: (0..questions).collect(),
+        },
+        publicly_visible: true,
+    };
+    let poll = |questions: Vec<Question>| Poll {
+        title: "Trip".to_string(),
+        metric_trackers: vec![metric(questions.len())],
+        questions,
+        ..Default::default()
+    };
+
+    // 10 * 10 * 10 is just enough
+    let fits = poll(vec![ranking(10), ranking(10), ranking(10)]);
+    fits.validate().unwrap();
+    let Progress::Standings(standings) =
+        fits.metric_trackers[0]
+            .metric
+            .calculate_progress(&fits.questions, &HashMap::new(), &[])
+    else {
+        panic!("Composite rankings make standings");
+    };
+    assert_eq!(standings.len(), STANDINGS_SHOWN);
+
+    let too_many = poll(vec![ranking(10); 10]);
+    assert!(too_many
+        .validate()
+        .unwrap_err()
+        .to_string()
+        .contains("combinations"));
+    assert_eq!(
+        too_many.metric_trackers[0].metric.calculate_progress(
+            &too_many.questions,
+            &HashMap::new(),
+            &[]
+        ),
+        Progress::Standings(Vec::new())
+    );
+}
+
+#[test]
+fn none_of_the_above_is_counted_and_labelled() {
+    let questions = vec![Question {
+        prompt: "Where should we eat?".to_string(),
+        form: Form::One {
+            options: vec!["Tacos".to_string(), "Curry".to_string()],
+            capacities: Vec::new(),
+            none_of_the_above: true,
+        },
+        show_if: None,
+        results_public: false,
+        participant_visible: true,
+        pinned_options: Vec::new(),
+    }];
+    let responses = [
+        ("Sandra", Choice::NoneOfTheAbove),
+        ("Peter", Choice::NoneOfTheAbove),
+        ("Bob", Choice::Index(0)),
+    ]
+    .into_iter()
+    .map(|(user, choice)| (user.to_string(), vec![FormResponse::ChooseOne(choice)]))
+    .collect::<HashMap<_, _>>();
+    let rejections = Metric::SpecificResponses {
+        question_index: 0,
+        choice: Choice::NoneOfTheAbove,
+    };
+    assert_eq!(
+        rejections.render(&questions),
+        "None of the above to Where should we eat?"
+    );
+    assert_eq!(
+        rejections.calculate_progress(&questions, &responses, &[]),
+        Progress::Count(2)
+    );
+    assert_eq!(
+        Metric::Plurality { question_index: 0 }.calculate_progress(&questions, &responses, &[]),
+        Progress::Winner {
+            winner: Some("None of the above".to_string()),
+            tiebreak: None,
+        }
+    );
+    // Unticking "None of the above" leaves the metric without its option
+    let mut questions = questions;
+    if let Form::One {
+        none_of_the_above, ..
+    } = &mut questions[0].form
+    {
+        *none_of_the_above = false;
+    }
+    assert_eq!(
+        rejections.render(&questions),
+        "(removed option) to Where should we eat?"
+    );
+}
+
+#[test]
+fn exactly_requires_the_precise_count() {
+    let exactly_six = Requirement::Exactly {
+        metric_index: 0,
+        count: 6,
+    };
+    assert!(!exactly_six.evaluate(&[Progress::Count(5)]));
+    assert!(exactly_six.evaluate(&[Progress::Count(6)]));
+    assert!(!exactly_six.evaluate(&[Progress::Count(7)]));
+}
+
+#[test]
+fn majority_needs_more_than_half_of_responders() {
+    let poll = multiple_choice_poll();
+    let majority = Metric::MajorityReached {
+        question_index: 0,
+        choice: Choice::Index(0),
+    };
+    let progress = |picks: &[Vec<Choice>]| {
+        let responses = picks
+            .iter()
+            .enumerate()
+            .map(|(i, choices)| {
+                (
+                    i.to_string(),
+                    vec![FormResponse::ChooseMultiple(choices.clone())],
+                )
+            })
+            .collect::<HashMap<_, _>>();
+        majority.calculate_progress(&poll.questions, &responses, &[])
+    };
+    assert_eq!(majority.render(&poll.questions), "Majority chose Chips");
+    assert_eq!(progress(&[]), Progress::Count(0));
+    let chips = vec![Choice::Index(0)];
+    assert_eq!(progress(&[chips.clone(), vec![]]), Progress::Count(0));
+    assert_eq!(
+        progress(&[chips.clone(), chips.clone(), vec![]]),
+        Progress::Count(1)
+    );
+    let without_options = Poll {
+        questions: vec![Question {
+            form: Form::Multiple {
+                options: Vec::new(),
+                capacities: Vec::new(),
+            },
+            ..poll.questions[0].clone()
+        }],
+        ..poll
+    };
+    assert_eq!(
+        majority.render(&without_options.questions),
+        "Majority chose (removed option)"
+    );
+}
+
+#[test]
+fn co_occurrence_counts_responders_who_picked_both() {
+    let poll = multiple_choice_poll();
+    let responses = [
+        vec![Choice::Index(0), Choice::Index(2)],
+        vec![Choice::Index(0)],
+        vec![Choice::Index(2), Choice::Index(1), Choice::Index(0)],
+    ]
+    .into_iter()
+    .enumerate()
+    .map(|(i, choices)| (i.to_string(), vec![FormResponse::ChooseMultiple(choices)]))
+    .collect::<HashMap<_, _>>();
+    let co_occurrence = |choice_a, choice_b| Metric::CoOccurrence {
+        question_index: 0,
+        choice_a: Choice::Index(choice_a),
+        choice_b: Choice::Index(choice_b),
+    };
+    assert_eq!(
+        co_occurrence(0, 2).render(&poll.questions),
+        "Chips and Soda to What should we bring?"
+    );
+    assert_eq!(
+        co_occurrence(0, 2).calculate_progress(&poll.questions, &responses, &[]),
+        Progress::Count(2)
+    );
+    assert_eq!(
+        co_occurrence(1, 2).calculate_progress(&poll.questions, &responses, &[]),
+        Progress::Count(1)
+    );
+    // The same option twice is a plain count of it
+    assert_eq!(
+        co_occurrence(0, 0).render(&poll.questions),
+        "Chips to What should we bring?"
+    );
+    assert_eq!(
+        co_occurrence(0, 0).calculate_progress(&poll.questions, &responses, &[]),
+        Progress::Count(3)
+    );
+    // Deleting an option the metric pairs up leaves a placeholder in its place
+    assert_eq!(
+        co_occurrence(0, 3).render(&poll.questions),
+        "Chips and (removed option) to What should we bring?"
+    );
+}
+
+#[test]
+fn at_least_says_how_many_more_it_needs() {
+    let at_least_three = Requirement::AtLeast {
+        metric_index: 0,
+        minimum: 3,
+    };
+    assert_eq!(
+        at_least_three.still_needed(&[Some(Progress::Count(1))]),
+        Some(2)
+    );
+    assert_eq!(
+        at_least_three.still_needed(&[Some(Progress::Count(3))]),
+        None
+    );
+    assert_eq!(at_least_three.still_needed(&[None]), None);
+    let exactly_three = Requirement::Exactly {
+        metric_index: 0,
+        count: 3,
+    };
+    assert_eq!(
+        exactly_three.still_needed(&[Some(Progress::Count(1))]),
+        None
+    );
+}
+
+#[test]
+fn completion_counts_responders_who_answered_everything_asked() {
+    let mut poll = multiple_choice_poll();
+    let follow_up = |prompt: &str| Question {
+        prompt: prompt.to_string(),
+        form: Form::OneOrNone {
+            options: vec!["Yes".to_string()],
+            capacities: Vec::new(),
+        },
+        show_if: Some((0, Choice::Index(0))),
+        results_public: false,
+        participant_visible: true,
+        pinned_options: Vec::new(),
+    };
+    poll.questions.push(follow_up("Spicy chips?"));
+    poll.questions.push(Question {
+        participant_visible: false,
+        ..follow_up("Internal notes")
+    });
+    let metric = Metric::CompletionRate {};
+    assert_eq!(metric.render(&poll.questions), "Fully completed responses");
+
+    let picked = |choices: Vec<u8>| {
+        FormResponse::ChooseMultiple(choices.into_iter().map(Choice::Index).collect())
+    };
+    let yes = FormResponse::ChooseOneOrNone(Some(Choice::Index(0)));
+    let skipped = FormResponse::ChooseOneOrNone(None);
+    let responses = [
+        // Answered the follow up it was asked
+        (
+            "Sandra",
+            vec![picked(vec![0]), yes.clone(), skipped.clone()],
+        ),
+        // Skipped the follow up
+        (
+            "Peter",
+            vec![picked(vec![0]), skipped.clone(), skipped.clone()],
+        ),
+        // Never asked the follow up
+        (
+            "Bob",
+            vec![picked(vec![1]), skipped.clone(), skipped.clone()],
+        ),
+        // Answered nothing
+        ("Ann", vec![picked(vec![]), skipped.clone(), skipped]),
+        // Stored before the follow ups were added, and wouldn't have been asked them
+        ("Kim", vec![picked(vec![2])]),
+    ]
+    .into_iter()
+    .map(|(user, responses)| (user.to_string(), responses))
+    .collect::<HashMap<_, _>>();
+    assert_eq!(
+        metric.calculate_progress(&poll.questions, &responses, &[]),
+        Progress::Count(3)
+    );
+}
+
+#[test]
+fn counts_get_separators_and_abbreviate_only_when_asked() {
+    let cases = [
+        (0, "0", "0"),
+        (999, "999", "999"),
+        (9_999, "9,999", "9,999"),
+        (12_345, "12,345", "12.3k"),
+        (20_000, "20,000", "20k"),
+        (999_960, "999,960", "1M"),
+        (1_234_567, "1,234,567", "1.2M"),
+        (u64::MAX, "18,446,744,073,709,551,615", "18,446,744,073.7B"),
+    ];
+    for (count, full, abbreviated) in cases {
+        assert_eq!(format_count(count, false), full);
+        assert_eq!(format_count(count, true), abbreviated);
+    }
+    assert_eq!(Progress::Count(12_345).render(), "12,345");
+}
+
+#[test]
+fn no_selection_counts_responders_who_skipped() {
+    let mut poll = multiple_choice_poll();
+    let metric = Metric::SpecificResponses {
+        question_index: 0,
+        choice: Choice::NoSelection,
+    };
+    let responses = [
+        ("Sandra", vec![Choice::Index(0)]),
+        ("Peter", Vec::new()),
+        ("Bob", Vec::new()),
+    ]
+    .into_iter()
+    .map(|(user, picks)| (user.to_string(), vec![FormResponse::ChooseMultiple(picks)]))
+    .collect::<HashMap<_, _>>();
+    assert_eq!(
+        metric.calculate_progress(&poll.questions, &responses, &[]),
+        Progress::Count(2)
+    );
+    assert_eq!(
+        metric.render(&poll.questions),
+        "No selection for What should we bring?"
+    );
+
+    // Only questions that can be skipped, and only as a count
+    poll.metric_trackers = vec![MetricTracker {
+        metric,
+        publicly_visible: true,
+    }];
+    assert!(poll.validate().is_ok());
+    poll.metric_trackers[0].metric = Metric::MajorityReached {
+        question_index: 0,
+        choice: Choice::NoSelection,
+    };
+    assert!(poll.validate().is_err());
+}